@@ -0,0 +1,185 @@
+// Drift USDC position of the SOL strategy
+// reads the spot deposit straight from Drift's User and SpotMarket accounts so collected
+// yield can be told apart from principal, the withdrawal itself goes through the strategy manager
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::strategy_allocation::invoke_strategy_collect;
+
+
+pub const DRIFT_PROGRAM_ID: Pubkey = pinocchio_pubkey::pubkey!("dRiftyHA39MWEi3m9aunc5MzRF1JYuBsbn6VPcn33UH");
+
+pub const DRIFT_USDC_MARKET_INDEX: u16 = 0;
+
+// strategy manager instruction moving realized Drift yield into the yields vault
+pub const STRATEGY_MANAGER_COLLECT_DRIFT_YIELD_IX: u8 = 5;
+
+// User: discriminator (8), authority (32), delegate (32), name (32), spot positions
+pub const DRIFT_USER_AUTHORITY_OFFSET: usize = 8;
+pub const DRIFT_USER_SPOT_POSITIONS_OFFSET: usize = 104;
+pub const DRIFT_MAX_SPOT_POSITIONS: usize = 8;
+
+// SpotPosition: scaled balance (u64), open bids, open asks, cumulative deposits (i64),
+// market index (u16), balance type (u8, 0 = deposit)
+pub const DRIFT_SPOT_POSITION_SIZE: usize = 40;
+pub const DRIFT_SPOT_POSITION_CUMULATIVE_DEPOSITS_OFFSET: usize = 24;
+pub const DRIFT_SPOT_POSITION_MARKET_INDEX_OFFSET: usize = 32;
+pub const DRIFT_SPOT_POSITION_BALANCE_TYPE_OFFSET: usize = 34;
+
+// SpotMarket: discriminator (8), pubkey (32), oracle (32), mint (32)
+pub const DRIFT_SPOT_MARKET_MINT_OFFSET: usize = 72;
+
+// SpotMarket fields used for the token amount
+pub const DRIFT_SPOT_MARKET_CUMULATIVE_DEPOSIT_INTEREST_OFFSET: usize = 464;
+pub const DRIFT_SPOT_MARKET_DECIMALS_OFFSET: usize = 680;
+pub const DRIFT_SPOT_MARKET_MARKET_INDEX_OFFSET: usize = 684;
+
+// scaled balance (1e9) × cumulative interest (1e10) brought down to token decimals
+pub const DRIFT_BALANCE_PRECISION_EXPONENT: u32 = 19;
+
+
+// spot deposit of one market as Drift reports it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DriftSpotDeposit {
+    pub token_amount: u64,
+    // net deposits Drift tracks on the position, withdrawals lower it
+    pub cumulative_deposits: i64,
+}
+
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    data.get(offset..offset + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, ProgramError> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+pub fn drift_user_authority(user_data: &[u8]) -> Result<Pubkey, ProgramError> {
+    user_data
+        .get(DRIFT_USER_AUTHORITY_OFFSET..DRIFT_USER_AUTHORITY_OFFSET + 32)
+        .map(|bytes| bytes.try_into().unwrap())
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+pub fn drift_spot_market_mint(spot_market_data: &[u8]) -> Result<Pubkey, ProgramError> {
+    spot_market_data
+        .get(DRIFT_SPOT_MARKET_MINT_OFFSET..DRIFT_SPOT_MARKET_MINT_OFFSET + 32)
+        .map(|bytes| bytes.try_into().unwrap())
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+// token amount and cumulative deposits of the user's deposit in a spot market
+// no deposit in the market reads as zero
+pub fn drift_spot_deposit(
+    user_data: &[u8],
+    spot_market_data: &[u8],
+    market_index: u16,
+) -> Result<DriftSpotDeposit, ProgramError> {
+    if read_u16(spot_market_data, DRIFT_SPOT_MARKET_MARKET_INDEX_OFFSET)? != market_index {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let cumulative_deposit_interest = spot_market_data
+        .get(DRIFT_SPOT_MARKET_CUMULATIVE_DEPOSIT_INTEREST_OFFSET..DRIFT_SPOT_MARKET_CUMULATIVE_DEPOSIT_INTEREST_OFFSET + 16)
+        .map(|bytes| u128::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(ProgramError::InvalidAccountData)?;
+    let decimals = spot_market_data
+        .get(DRIFT_SPOT_MARKET_DECIMALS_OFFSET..DRIFT_SPOT_MARKET_DECIMALS_OFFSET + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(ProgramError::InvalidAccountData)?;
+    if decimals > DRIFT_BALANCE_PRECISION_EXPONENT {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let precision_decrease = 10u128.pow(DRIFT_BALANCE_PRECISION_EXPONENT - decimals);
+
+    for i in 0..DRIFT_MAX_SPOT_POSITIONS {
+        let position = DRIFT_USER_SPOT_POSITIONS_OFFSET + i * DRIFT_SPOT_POSITION_SIZE;
+        if read_u16(user_data, position + DRIFT_SPOT_POSITION_MARKET_INDEX_OFFSET)? != market_index {
+            continue;
+        }
+        let scaled_balance = read_u64(user_data, position)?;
+        // borrows hold no collectable yield
+        if scaled_balance == 0 || user_data[position + DRIFT_SPOT_POSITION_BALANCE_TYPE_OFFSET] != 0 {
+            continue;
+        }
+
+        let token_amount = (scaled_balance as u128)
+            .checked_mul(cumulative_deposit_interest)
+            .ok_or(ProgramError::ArithmeticOverflow)?
+            / precision_decrease;
+
+        return Ok(DriftSpotDeposit {
+            token_amount: u64::try_from(token_amount).map_err(|_| ProgramError::ArithmeticOverflow)?,
+            cumulative_deposits: read_u64(user_data, position + DRIFT_SPOT_POSITION_CUMULATIVE_DEPOSITS_OFFSET)? as i64,
+        });
+    }
+
+    Ok(DriftSpotDeposit { token_amount: 0, cumulative_deposits: 0 })
+}
+
+
+// principal moved by deposits or withdrawals since the last snapshot of cumulative deposits
+pub fn drift_principal(
+    last_principal: u64,
+    last_cumulative_deposits: i64,
+    cumulative_deposits: i64,
+) -> u64 {
+    let change = cumulative_deposits.saturating_sub(last_cumulative_deposits);
+    if change >= 0 {
+        last_principal.saturating_add(change as u64)
+    } else {
+        last_principal.saturating_sub(change.unsigned_abs())
+    }
+}
+
+// realized yield above principal, and the principal that stays in the position
+pub fn split_realized_yield(position_value: u64, principal: u64) -> (u64, u64) {
+    (position_value.saturating_sub(principal), principal.min(position_value))
+}
+
+// the yields vault has to grow by exactly the collected yield
+pub fn verify_vault_delta(balance_before: u64, balance_after: u64, expected: u64) -> Result<u64, ProgramError> {
+    let delta = balance_after.saturating_sub(balance_before);
+    if balance_after < balance_before || delta != expected {
+        return Err(crate::error::ProtocolControllerError::YieldHarvestingFailed.into());
+    }
+    Ok(delta)
+}
+
+
+// asks the strategy manager to withdraw realized yield from the Drift position into the yields vault
+// manager-specific Drift accounts are passed through after the fixed ones
+pub fn invoke_drift_yield_withdraw(
+    strategy_program: &AccountInfo,
+    protocol_controller: &AccountInfo,
+    strategy_state: &AccountInfo,
+    yields_vault: &AccountInfo,
+    token_program: &AccountInfo,
+    drift_user: &AccountInfo,
+    drift_spot_market: &AccountInfo,
+    passthrough_accounts: &[AccountInfo],
+    amount: u64,
+    protocol_controller_bump: u8,
+) -> Result<(), ProgramError> {
+    invoke_strategy_collect(
+        STRATEGY_MANAGER_COLLECT_DRIFT_YIELD_IX,
+        strategy_program,
+        protocol_controller,
+        strategy_state,
+        yields_vault,
+        token_program,
+        &[drift_user, drift_spot_market],
+        passthrough_accounts,
+        amount,
+        protocol_controller_bump,
+    )
+}
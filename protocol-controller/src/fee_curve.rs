@@ -0,0 +1,277 @@
+// dynamic mint and redeem fees
+// a governed piecewise-linear curve over the collateral ratio, plus a flow term that charges
+// the side pushing the net flow, update_tvl_data_and_dynamic_fees keeps the last fees on-chain
+// and the SOL and USDC mint and burn paths charge them
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    msg,
+    clock::Clock,
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::ProgramAccount;
+
+
+pub const DYNAMIC_FEE_CURVE_SEED: &[u8] = b"dynamic_fee_curve";
+pub const MAX_FEE_CURVE_POINTS: usize = 8;
+
+// hard bound on any mint or redeem fee, curve points included
+pub const MAX_DYNAMIC_FEE_BPS: u16 = 1_000;
+
+// update_tvl_data_and_dynamic_fees modes
+pub const DYNAMIC_FEES_UPDATE: u8 = 0;
+pub const DYNAMIC_FEES_SET_CURVE: u8 = 1;
+
+// ~1 day of slots at 400ms
+pub const MAX_FEE_OVERRIDE_SLOTS: u64 = 216_000;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct FeeCurvePoint {
+    pub collateral_ratio_bps: u64,
+    pub mint_fee_bps: u16,
+    pub redeem_fee_bps: u16,
+    pub _padding: [u8; 4],
+}
+
+
+// master fee override, replaces the curve fees until expiry_slot
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct FeeOverride {
+    pub mint_fee_bps: u16,
+    pub redeem_fee_bps: u16,
+    pub reason_code: u32,
+    pub set_slot: u64,
+    pub set_timestamp: i64,
+    // zero when no override is set
+    pub expiry_slot: u64,
+    pub set_by: Pubkey,
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct DynamicFeeCurve {
+    // ordered by collateral ratio, fees flat outside the first and last point
+    pub points: [FeeCurvePoint; MAX_FEE_CURVE_POINTS],
+    // fee bps added per 100% of TVL of net flow since the previous update
+    pub flow_sensitivity_bps: u64,
+    // inputs of the last update, USD values with 6 decimals
+    pub last_tvl_usd: u64,
+    pub last_collateral_ratio_bps: u64,
+    // USDtx minted minus burned since the update before
+    pub last_flow_imbalance: i64,
+    // controller mint / burn totals at the last update
+    pub minted_snapshot: u64,
+    pub burned_snapshot: u64,
+    pub last_update_timestamp: i64,
+    pub last_update_slot: u64,
+    pub update_count: u64,
+    pub mint_fee_bps: u16,
+    pub redeem_fee_bps: u16,
+    pub point_count: u8,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 1],
+    pub override_count: u64,
+    pub fee_override: FeeOverride,
+}
+
+impl ProgramAccount for DynamicFeeCurve {}
+
+impl DynamicFeeCurve {
+    pub fn verify_address(curve_account: &AccountInfo) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[DYNAMIC_FEE_CURVE_SEED],
+            &crate::ID,
+        );
+        if curve_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(bump)
+    }
+
+    // the override fees while it has not expired
+    pub fn active_override(&self, slot: u64) -> Option<(u16, u16)> {
+        if self.fee_override.expiry_slot > 0 && slot <= self.fee_override.expiry_slot {
+            Some((self.fee_override.mint_fee_bps, self.fee_override.redeem_fee_bps))
+        } else {
+            None
+        }
+    }
+
+    // (mint, redeem) fees to charge at a slot, the curve takes over again once an override expires
+    pub fn effective_fees(&self, slot: u64) -> (u16, u16) {
+        self.active_override(slot).unwrap_or((self.mint_fee_bps, self.redeem_fee_bps))
+    }
+}
+
+
+// ratios strictly increasing, fees never higher at a better ratio and inside the hard bound
+pub fn is_valid_fee_curve(points: &[FeeCurvePoint]) -> bool {
+    if points.is_empty() || points.len() > MAX_FEE_CURVE_POINTS {
+        return false;
+    }
+    if points
+        .iter()
+        .any(|point| point.mint_fee_bps > MAX_DYNAMIC_FEE_BPS || point.redeem_fee_bps > MAX_DYNAMIC_FEE_BPS)
+    {
+        return false;
+    }
+    points.windows(2).all(|pair| {
+        pair[0].collateral_ratio_bps < pair[1].collateral_ratio_bps
+            && pair[0].mint_fee_bps >= pair[1].mint_fee_bps
+            && pair[0].redeem_fee_bps >= pair[1].redeem_fee_bps
+    })
+}
+
+// mint and redeem fee at a collateral ratio, linear between the surrounding points
+pub fn curve_fees(points: &[FeeCurvePoint], collateral_ratio_bps: u64) -> (u16, u16) {
+    let first = &points[0];
+    let last = &points[points.len() - 1];
+    if collateral_ratio_bps <= first.collateral_ratio_bps {
+        return (first.mint_fee_bps, first.redeem_fee_bps);
+    }
+    if collateral_ratio_bps >= last.collateral_ratio_bps {
+        return (last.mint_fee_bps, last.redeem_fee_bps);
+    }
+
+    let upper = points.iter().position(|point| point.collateral_ratio_bps > collateral_ratio_bps).unwrap();
+    let (low, high) = (&points[upper - 1], &points[upper]);
+    let interpolate = |from: u16, to: u16| -> u16 {
+        let span = (high.collateral_ratio_bps - low.collateral_ratio_bps) as u128;
+        let progress = (collateral_ratio_bps - low.collateral_ratio_bps) as u128;
+        // fees only fall along the curve
+        (from as u128 - (from - to) as u128 * progress / span) as u16
+    };
+    (
+        interpolate(low.mint_fee_bps, high.mint_fee_bps),
+        interpolate(low.redeem_fee_bps, high.redeem_fee_bps),
+    )
+}
+
+// extra fee on the side of the net flow, (mint, redeem)
+pub fn flow_fee_adjustment(flow_imbalance: i64, tvl_usd: u64, flow_sensitivity_bps: u64) -> (u16, u16) {
+    if tvl_usd == 0 || flow_imbalance == 0 {
+        return (0, 0);
+    }
+    let imbalance_bps = (flow_imbalance.unsigned_abs() as u128) * 10_000u128 / tvl_usd as u128;
+    let extra = (imbalance_bps * flow_sensitivity_bps as u128 / 10_000u128).min(MAX_DYNAMIC_FEE_BPS as u128) as u16;
+    if flow_imbalance > 0 {
+        (extra, 0)
+    } else {
+        (0, extra)
+    }
+}
+
+// curve fees plus the flow term, within the hard bound
+pub fn dynamic_fees(
+    points: &[FeeCurvePoint],
+    collateral_ratio_bps: u64,
+    flow_imbalance: i64,
+    tvl_usd: u64,
+    flow_sensitivity_bps: u64,
+) -> (u16, u16) {
+    let (mint_fee, redeem_fee) = curve_fees(points, collateral_ratio_bps);
+    let (mint_extra, redeem_extra) = flow_fee_adjustment(flow_imbalance, tvl_usd, flow_sensitivity_bps);
+    (
+        mint_fee.saturating_add(mint_extra).min(MAX_DYNAMIC_FEE_BPS),
+        redeem_fee.saturating_add(redeem_extra).min(MAX_DYNAMIC_FEE_BPS),
+    )
+}
+
+
+// (mint, redeem) fees the mint and burn paths charge at a slot, a live master override first,
+// default_fee_bps until the curve is set
+pub fn read_charged_fees(curve_account: &AccountInfo, slot: u64, default_fee_bps: u64) -> Result<(u64, u64), ProgramError> {
+    DynamicFeeCurve::verify_address(curve_account)?;
+    if curve_account.lamports() == 0 {
+        return Ok((default_fee_bps, default_fee_bps));
+    }
+    if !curve_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let curve_data = curve_account.try_borrow_data()?;
+    let curve = DynamicFeeCurve::load(&curve_data)?;
+    if curve.is_initialized == 0 {
+        return Ok((default_fee_bps, default_fee_bps));
+    }
+    let (mint_fee_bps, redeem_fee_bps) = curve.effective_fees(slot);
+    Ok((mint_fee_bps as u64, redeem_fee_bps as u64))
+}
+
+
+// master fee override for every program reading the dynamic fee curve, within the hard fee bound
+// expires by itself after duration_slots; a zero duration clears it
+pub fn master_authority_update_all_dynamic_fees(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("master authority dynamic fee override");
+
+    // mint fee bps (2), redeem fee bps (2), duration in slots (8), reason code (4)
+    if data.len() < 16 {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol parameters, 1 = master authority, 2 = dynamic fee curve
+    if accounts.len() < 3 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let mint_fee_bps = u16::from_le_bytes(data[0..2].try_into().unwrap());
+    let redeem_fee_bps = u16::from_le_bytes(data[2..4].try_into().unwrap());
+    let duration_slots = u64::from_le_bytes(data[4..12].try_into().unwrap());
+    let reason_code = u32::from_le_bytes(data[12..16].try_into().unwrap());
+
+    crate::parameters::read_protocol_parameters(&accounts[0])?.require_authority(&accounts[1])?;
+
+    if duration_slots > MAX_FEE_OVERRIDE_SLOTS
+        || mint_fee_bps > MAX_DYNAMIC_FEE_BPS
+        || redeem_fee_bps > MAX_DYNAMIC_FEE_BPS
+    {
+        msg!("fees at most {} bps for at most {} slots", MAX_DYNAMIC_FEE_BPS, MAX_FEE_OVERRIDE_SLOTS);
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let curve_account = &accounts[2];
+    DynamicFeeCurve::verify_address(curve_account)?;
+    if !curve_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut curve_data = curve_account.try_borrow_mut_data()?;
+    let curve = DynamicFeeCurve::load_mut(&mut curve_data)?;
+    if curve.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let clock = Clock::get()?;
+
+    if duration_slots == 0 {
+        curve.fee_override = FeeOverride::zeroed();
+        msg!("fee override cleared by {:?}, reason code: {}", accounts[1].key(), reason_code);
+        return Ok(());
+    }
+
+    curve.fee_override = FeeOverride {
+        mint_fee_bps,
+        redeem_fee_bps,
+        reason_code,
+        set_slot: clock.slot,
+        set_timestamp: clock.unix_timestamp,
+        expiry_slot: clock.slot.saturating_add(duration_slots),
+        set_by: *accounts[1].key(),
+    };
+    curve.override_count = curve.override_count.saturating_add(1);
+
+    msg!("fee override: mint {} bps, redeem {} bps until slot {}", mint_fee_bps, redeem_fee_bps, curve.fee_override.expiry_slot);
+    msg!("reason code: {}", reason_code);
+
+    Ok(())
+}
@@ -0,0 +1,221 @@
+// treasury fee split
+// the yield fee rate is a governed parameter (treasury_fee_bps), the fee itself is split
+// by weight across recipients (treasury, insurance fund, referrers) with cumulative payouts
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    msg,
+    instruction::{Seed, Signer},
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::{ProgramAccount, create_program_account};
+
+
+pub const FEE_SPLIT_SEED: &[u8] = b"fee_split";
+pub const MAX_FEE_RECIPIENTS: usize = 4;
+
+// recipient kinds
+pub const FEE_RECIPIENT_TREASURY: u8 = 1;
+pub const FEE_RECIPIENT_INSURANCE: u8 = 2;
+pub const FEE_RECIPIENT_REFERRER: u8 = 3;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct FeeRecipient {
+    // USDC token account receiving the share
+    pub token_account: Pubkey,
+    pub cumulative_paid: u64,
+    pub weight_bps: u16,
+    pub kind: u8,
+    pub _padding: [u8; 5],
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct FeeSplitConfig {
+    pub total_fees_paid: u64,
+    pub recipient_count: u8,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 5],
+    pub recipients: [FeeRecipient; MAX_FEE_RECIPIENTS],
+}
+
+impl ProgramAccount for FeeSplitConfig {}
+
+impl FeeSplitConfig {
+    pub fn verify_address(config_account: &AccountInfo) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[FEE_SPLIT_SEED],
+            &crate::ID,
+        );
+        if config_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(bump)
+    }
+
+    // per-recipient amounts of a fee, the last recipient takes the rounding remainder
+    pub fn split(&self, fee: u64) -> [u64; MAX_FEE_RECIPIENTS] {
+        let mut amounts = [0u64; MAX_FEE_RECIPIENTS];
+        let count = self.recipient_count as usize;
+        let mut allocated: u64 = 0;
+
+        for (i, recipient) in self.recipients[..count].iter().enumerate() {
+            amounts[i] = if i + 1 == count {
+                fee.saturating_sub(allocated)
+            } else {
+                ((fee as u128) * (recipient.weight_bps as u128) / 10_000u128) as u64
+            };
+            allocated = allocated.saturating_add(amounts[i]);
+        }
+
+        amounts
+    }
+}
+
+
+// pays the fee out of the yields vault to every recipient and records the totals
+// recipient token accounts are passed in config order
+pub fn pay_fee_split(
+    config: &mut FeeSplitConfig,
+    fee: u64,
+    yields_vault: &AccountInfo,
+    recipient_accounts: &[AccountInfo],
+    protocol_controller: &AccountInfo,
+    protocol_controller_bump: u8,
+) -> Result<(), ProgramError> {
+    let count = config.recipient_count as usize;
+    if count == 0 {
+        if fee > 0 {
+            msg!("no fee recipients configured");
+            return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+        }
+        return Ok(());
+    }
+    if recipient_accounts.len() < count {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let amounts = config.split(fee);
+
+    let bump_seed = [protocol_controller_bump];
+    let seeds = [
+        Seed::from(crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED),
+        Seed::from(&bump_seed),
+    ];
+
+    for i in 0..count {
+        let recipient = &mut config.recipients[i];
+        if recipient_accounts[i].key() != &recipient.token_account {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if amounts[i] == 0 {
+            continue;
+        }
+
+        pinocchio_token::instructions::Transfer {
+            from: yields_vault,
+            to: &recipient_accounts[i],
+            authority: protocol_controller,
+            amount: amounts[i],
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+
+        recipient.cumulative_paid = recipient.cumulative_paid.saturating_add(amounts[i]);
+        msg!("fee recipient {} (kind {}): {}", i, recipient.kind, amounts[i]);
+    }
+
+    config.total_fees_paid = config.total_fees_paid.saturating_add(fee);
+
+    Ok(())
+}
+
+
+// set the fee recipients and weights, weights add up to 10_000 bps
+// recipients that stay keep their cumulative totals
+pub fn update_fee_recipients(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("updating fee recipients");
+
+    // count, then per recipient: token account (32), weight bps (2), kind (1)
+    if data.is_empty() {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    let count = data[0] as usize;
+    if count == 0 || count > MAX_FEE_RECIPIENTS || data.len() < 1 + 35 * count {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol parameters, 1 = authority (payer), 2 = fee split config, 3 = system program
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let authority_account = &accounts[1];
+    crate::parameters::read_protocol_parameters(&accounts[0])?.require_authority(authority_account)?;
+
+    let config_account = &accounts[2];
+    let config_bump = FeeSplitConfig::verify_address(config_account)?;
+    if config_account.lamports() == 0 {
+        let bump_seed = [config_bump];
+        create_program_account(
+            authority_account,
+            config_account,
+            FeeSplitConfig::LEN,
+            &[Seed::from(FEE_SPLIT_SEED), Seed::from(&bump_seed)],
+        )?;
+    }
+    if !config_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut recipients = [FeeRecipient::zeroed(); MAX_FEE_RECIPIENTS];
+    let mut total_weight: u64 = 0;
+
+    for (i, recipient) in recipients.iter_mut().take(count).enumerate() {
+        let offset = 1 + 35 * i;
+        recipient.token_account = data[offset..offset + 32].try_into().unwrap();
+        recipient.weight_bps = u16::from_le_bytes(data[offset + 32..offset + 34].try_into().unwrap());
+        recipient.kind = data[offset + 34];
+
+        if recipient.kind == 0 || recipient.kind > FEE_RECIPIENT_REFERRER {
+            return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+        }
+        total_weight += recipient.weight_bps as u64;
+    }
+
+    if total_weight != 10_000 {
+        msg!("fee weights add up to {} bps, expected 10000", total_weight);
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let mut config_data = config_account.try_borrow_mut_data()?;
+    let config = FeeSplitConfig::load_mut(&mut config_data)?;
+
+    for recipient in recipients.iter_mut().take(count) {
+        if let Some(existing) = config.recipients[..config.recipient_count as usize]
+            .iter()
+            .find(|existing| existing.token_account == recipient.token_account)
+        {
+            recipient.cumulative_paid = existing.cumulative_paid;
+        }
+    }
+
+    config.recipients = recipients;
+    config.recipient_count = count as u8;
+    config.bump = config_bump;
+    config.is_initialized = 1;
+
+    msg!("{} fee recipients set", count);
+
+    Ok(())
+}
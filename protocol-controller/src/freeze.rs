@@ -0,0 +1,235 @@
+// USDtx freeze positions
+// frozen USDtx stays in the user's token account and is locked by the USDtx token program,
+// the controller keeps a position per user and the total frozen supply next to the TWAB ledgers
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    msg,
+    instruction::{AccountMeta, Instruction, Seed, Signer},
+    program::invoke_signed,
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::ProgramAccount;
+use crate::thaler_distribution::invoke_thaler_transfer;
+use crate::twab::{TwabLedger, period_share};
+use crate::yield_epoch::YieldEpoch;
+
+
+pub const FREEZE_POSITION_SEED: &[u8] = b"freeze_position";
+pub const FREEZE_SUPPLY_SEED: &[u8] = b"freeze_supply";
+
+// USDtx token program instructions locking and releasing frozen balances
+pub const USDTX_FREEZE_FOR_YIELD_IX: u8 = 4;
+pub const USDTX_UNFREEZE_FROM_YIELD_IX: u8 = 5;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct FreezePosition {
+    pub owner: Pubkey,
+    pub frozen_amount: u64,
+    // start of the current position, reset when it was fully unfrozen
+    pub frozen_since: i64,
+    // latest freeze, the unfreeze cooldown runs from here
+    pub last_frozen_at: i64,
+    pub last_unfrozen_at: i64,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+}
+
+impl ProgramAccount for FreezePosition {}
+
+impl FreezePosition {
+    pub fn verify_address(position_account: &AccountInfo, owner: &Pubkey) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[FREEZE_POSITION_SEED, owner.as_ref()],
+            &crate::ID,
+        );
+        if position_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(bump)
+    }
+
+    pub fn cooldown_ends_at(&self, cooldown: u64) -> i64 {
+        self.last_frozen_at.saturating_add(cooldown as i64)
+    }
+}
+
+
+// the controller's record of frozen USDtx supply
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct FreezeSupply {
+    pub total_frozen_supply: u64,
+    pub frozen_position_count: u64,
+    pub last_update_timestamp: i64,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+}
+
+impl ProgramAccount for FreezeSupply {}
+
+impl FreezeSupply {
+    pub fn verify_address(supply_account: &AccountInfo) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[FREEZE_SUPPLY_SEED],
+            &crate::ID,
+        );
+        if supply_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(bump)
+    }
+}
+
+
+// freeze or unfreeze CPI into the USDtx token program, the user and the protocol controller PDA sign
+pub fn invoke_usdtx_freeze(
+    instruction_id: u8,
+    usdtx_program: &AccountInfo,
+    protocol_controller: &AccountInfo,
+    user: &AccountInfo,
+    user_usdtx_account: &AccountInfo,
+    usdtx_mint: &AccountInfo,
+    amount: u64,
+    protocol_controller_bump: u8,
+) -> Result<(), ProgramError> {
+    if usdtx_program.key() != &usdtx_token::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let account_metas = [
+        AccountMeta::readonly_signer(protocol_controller.key()),
+        AccountMeta::readonly_signer(user.key()),
+        AccountMeta::writable(user_usdtx_account.key()),
+        AccountMeta::readonly(usdtx_mint.key()),
+    ];
+
+    let mut instruction_data = [0u8; 9];
+    instruction_data[0] = instruction_id;
+    instruction_data[1..9].copy_from_slice(&amount.to_le_bytes());
+
+    let instruction = Instruction {
+        program_id: usdtx_program.key(),
+        accounts: &account_metas,
+        data: &instruction_data,
+    };
+
+    let bump_seed = [protocol_controller_bump];
+    let seeds = [
+        Seed::from(crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED),
+        Seed::from(&bump_seed),
+    ];
+
+    invoke_signed(
+        &instruction,
+        &[protocol_controller, user, user_usdtx_account, usdtx_mint],
+        &[Signer::from(&seeds)],
+    )
+}
+
+
+// pays the user's Thalers for the passed closed epochs after the ledger's last settled one,
+// in order, from the ledger's own TWAB history, returns the Thalers paid
+// any number of epochs can be settled per call, the rest stays claimable later
+pub fn settle_thaler_entitlements(
+    ledger: &mut TwabLedger,
+    last_closed_epoch: u64,
+    epoch_accounts: &[AccountInfo],
+    distribution_vault: &AccountInfo,
+    user_thaler_account: &AccountInfo,
+    protocol_controller: &AccountInfo,
+    protocol_controller_bump: u8,
+) -> Result<u64, ProgramError> {
+    let unsettled = last_closed_epoch.saturating_sub(ledger.last_settled_distribution) as usize;
+    if epoch_accounts.len() < unsettled {
+        msg!("settling {} of {} unsettled epochs", epoch_accounts.len(), unsettled);
+    }
+
+    let mut settled_total = 0u64;
+
+    for epoch_account in epoch_accounts.iter().take(unsettled) {
+        let epoch = ledger.last_settled_distribution + 1;
+        YieldEpoch::verify_address(epoch_account, epoch)?;
+        if !epoch_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut epoch_data = epoch_account.try_borrow_mut_data()?;
+        let yield_epoch = YieldEpoch::load_mut(&mut epoch_data)?;
+
+        let share = period_share(
+            ledger,
+            yield_epoch.total_amount,
+            yield_epoch.start_timestamp,
+            yield_epoch.end_timestamp,
+            yield_epoch.end_twab.saturating_sub(yield_epoch.start_twab),
+        )
+        .ok_or_else(|| {
+            msg!("epoch {} is past the ledger history, claim it with its proof first", epoch);
+            ProgramError::from(crate::error::ProtocolControllerError::CoordinationOperationMismatch)
+        })?
+        .min(yield_epoch.total_amount.saturating_sub(yield_epoch.claimed_amount));
+
+        if share > 0 {
+            invoke_thaler_transfer(
+                distribution_vault,
+                user_thaler_account,
+                protocol_controller,
+                share,
+                protocol_controller_bump,
+            )?;
+            yield_epoch.claimed_amount = yield_epoch.claimed_amount.saturating_add(share);
+            settled_total = settled_total.saturating_add(share);
+        }
+
+        ledger.last_settled_distribution = epoch;
+        ledger.last_settled_timestamp = yield_epoch.end_timestamp;
+    }
+
+    Ok(settled_total)
+}
+
+
+// sync_freeze_states_across_programs report bits, per user
+pub const SYNC_LEDGER_MISMATCH: u8 = 1;
+pub const SYNC_TOKEN_FLAG_MISMATCH: u8 = 2;
+pub const SYNC_OVER_TOKEN_BALANCE: u8 = 4;
+// USDtx account locked with no position behind it, only the token program can thaw it
+pub const SYNC_UNREPAIRABLE: u8 = 8;
+
+pub const MAX_SYNC_USERS: usize = 16;
+
+
+// how the controller position, the TWAB ledger and the USDtx account of one user disagree
+pub fn freeze_discrepancy(position_amount: u64, ledger_balance: u64, token_frozen: bool, token_amount: u64) -> u8 {
+    let mut report = 0u8;
+    if position_amount != ledger_balance {
+        report |= SYNC_LEDGER_MISMATCH;
+    }
+    if token_frozen != (position_amount > 0) {
+        report |= SYNC_TOKEN_FLAG_MISMATCH;
+        if token_frozen {
+            report |= SYNC_UNREPAIRABLE;
+        }
+    }
+    if position_amount > token_amount {
+        report |= SYNC_OVER_TOKEN_BALANCE;
+    }
+    report
+}
+
+// frozen amount the controller side is repaired to, the USDtx lock is the source of truth
+// None when the position cannot be rebuilt from the token account
+pub fn repaired_frozen_amount(position_amount: u64, token_frozen: bool, token_amount: u64) -> Option<u64> {
+    match (token_frozen, position_amount) {
+        (false, _) => Some(0),
+        (true, 0) => None,
+        (true, amount) => Some(amount.min(token_amount)),
+    }
+}
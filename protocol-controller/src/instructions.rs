@@ -9,7 +9,7 @@ use pinocchio::{
     msg,
     clock::Clock,
     program::{invoke, invoke_signed},
-    instruction::{AccountMeta, Instruction},
+    instruction::{AccountMeta, Instruction, Seed},
 };
 
 use crate::{
@@ -17,8 +17,24 @@ use crate::{
     state::*,
     math::*,
     oracle::*,
+    parameters::*,
+    oracle_guard::*,
+    yield_accounting::*,
+    twab::*,
+    thaler_distribution::*,
+    fee_split::*,
+    strategy_allocation::*,
+    strategy_registry::*,
+    drift::*,
+    kamino::*,
+    minting::*,
+    redemption_queue::*,
+    freeze::*,
+    solvency::*,
+    fee_curve::*,
+    program_account::ProgramAccount,
 };
-use bytemuck;
+use bytemuck::{self, Zeroable};
 
 
 
@@ -35,7 +51,7 @@ pub fn initialize_protocol(
         return Err(ProgramError::NotEnoughAccountKeys);
     }
     
-    if data.len() < 160 { // 5 program IDs * 32 bytes each
+    if data.len() < 192 { // 5 program IDs and the doppler oracle * 32 bytes each
         return Err(ProtocolControllerError::ParameterValidationFailed.into());
     }
     
@@ -72,6 +88,40 @@ pub fn initialize_protocol(
     let magicblock_program_id = Pubkey::new_from_array(
         data[offset..offset+32].try_into().unwrap()
     );
+    offset += 32;
+
+
+    let doppler_oracle = Pubkey::new_from_array(
+        data[offset..offset+32].try_into().unwrap()
+    );
+
+
+    // protocol parameters PDA with defaults, governed by the signing authority
+    // 1 = authority (payer), 2 = protocol parameters, 3 = system program
+    let authority_account = &accounts[1];
+    let parameters_account = &accounts[2];
+    if !authority_account.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (expected_address, parameters_bump) = Pubkey::find_program_address(&[PROTOCOL_PARAMETERS_SEED], &crate::ID);
+    if parameters_account.key() != &expected_address {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if parameters_account.lamports() > 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let bump_seed = [parameters_bump];
+    crate::program_account::create_program_account(
+        authority_account,
+        parameters_account,
+        ProtocolParameters::LEN,
+        &[Seed::from(PROTOCOL_PARAMETERS_SEED), Seed::from(&bump_seed)],
+    )?;
+
+    let mut parameters_data = parameters_account.try_borrow_mut_data()?;
+    ProtocolParameters::load_mut(&mut parameters_data)?.initialize(authority_account.key(), &doppler_oracle, parameters_bump);
     
     // now this would create the ProtocolController account with these addresses
     
@@ -89,20 +139,158 @@ pub fn update_protocol_parameters(
 ) -> Result<(), ProgramError> {
     msg!("updating protocol parameters");
     
-    if data.len() < 8 {
+    if data.len() < 9 {
         return Err(ProtocolControllerError::ParameterValidationFailed.into());
     }
+
+    // 0 = protocol parameters, 1 = authority
+    if accounts.len() < 2 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
     
     let parameter_type = data[0];
     let parameter_value = u64::from_le_bytes(data[1..9].try_into().unwrap());
 
+    let parameters_account = &accounts[0];
+    ProtocolParameters::verify_address(parameters_account)?;
+    let mut parameters_data = parameters_account.try_borrow_mut_data()?;
+    let parameters = ProtocolParameters::load_mut(&mut parameters_data)?;
+    if parameters.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    parameters.require_authority(&accounts[1])?;
+
 
   // matching correct parameter values, simplified
     match parameter_type {
-        1 => msg!("updating minimum collateral ratio to: {}", parameter_value),
-        2 => msg!("updating rebalance frequency to: {} seconds", parameter_value),
-        3 => msg!("updating yield distribution frequency to: {} seconds", parameter_value),
-        4 => msg!("updating emergency threshold to: {}", parameter_value),
+        1 => {
+            msg!("updating minimum collateral ratio to: {}", parameter_value);
+            parameters.min_collateral_ratio_bps = parameter_value;
+        },
+        2 => {
+            msg!("updating rebalance frequency to: {} seconds", parameter_value);
+            parameters.rebalance_frequency = parameter_value;
+        },
+        3 => {
+            msg!("updating yield distribution frequency to: {} seconds", parameter_value);
+            parameters.yield_distribution_frequency = parameter_value;
+        },
+        4 => {
+            msg!("updating emergency threshold to: {}", parameter_value);
+            parameters.emergency_threshold = parameter_value;
+        },
+        5 => {
+            if parameter_value == 0 || parameter_value > 10_000 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            msg!("updating max source deviation to: {} bps", parameter_value);
+            parameters.max_source_deviation_bps = parameter_value;
+        },
+        6 => {
+            if parameter_value == 0 || parameter_value > 10_000 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            msg!("updating max aggregate deviation to: {} bps", parameter_value);
+            parameters.max_aggregate_deviation_bps = parameter_value;
+        },
+        7 => {
+            if parameter_value == 0 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            msg!("updating max price staleness to: {} seconds", parameter_value);
+            parameters.max_price_staleness = parameter_value;
+        },
+        8 | 9 => {
+            if parameter_value == 0 || parameter_value > MAX_PRICE_SOURCES as u64 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            if parameter_type == 8 {
+                msg!("updating primary price source to: {}", parameter_value);
+                parameters.primary_price_source = parameter_value;
+            } else {
+                msg!("updating secondary price source to: {}", parameter_value);
+                parameters.secondary_price_source = parameter_value;
+            }
+        },
+        10 => {
+            if parameter_value > 10_000 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            msg!("updating treasury fee to: {} bps", parameter_value);
+            parameters.treasury_fee_bps = parameter_value;
+        },
+        11 => {
+            if parameter_value > 10_000 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            msg!("updating insurance fund share to: {} bps", parameter_value);
+            parameters.insurance_fund_bps = parameter_value;
+        },
+        12 => {
+            if parameter_value > 10_000 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            msg!("updating PSM fee to: {} bps", parameter_value);
+            parameters.psm_fee_bps = parameter_value;
+        },
+        13 => {
+            msg!("updating PSM epoch cap to: {}", parameter_value);
+            parameters.psm_epoch_cap = parameter_value;
+        },
+        14 => {
+            if parameter_value == 0 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            msg!("updating PSM epoch length to: {} seconds", parameter_value);
+            parameters.psm_epoch_length = parameter_value;
+        },
+        15 => {
+            if parameter_value > 10_000 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            msg!("updating SOL liquidity buffer to: {} bps", parameter_value);
+            parameters.sol_liquidity_buffer_bps = parameter_value;
+        },
+        16 => {
+            if parameter_value > 10_000 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            msg!("updating USDC liquidity buffer to: {} bps", parameter_value);
+            parameters.usdc_liquidity_buffer_bps = parameter_value;
+        },
+        17 => {
+            msg!("updating unfreeze cooldown to: {} seconds", parameter_value);
+            parameters.freeze_cooldown_seconds = parameter_value;
+        },
+        18 => {
+            msg!("updating liquid backing threshold to: {} bps", parameter_value);
+            parameters.liquid_min_backing_bps = parameter_value;
+        },
+        19 => {
+            msg!("updating frozen backing threshold to: {} bps", parameter_value);
+            parameters.frozen_min_backing_bps = parameter_value;
+        },
+        20 => {
+            if parameter_value == 0 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            msg!("updating max fallback TWAP age to: {} seconds", parameter_value);
+            parameters.max_fallback_twap_age = parameter_value;
+        },
+        21 => {
+            if parameter_value > 1 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            msg!("updating mint pause to: {}", parameter_value);
+            parameters.mint_paused = parameter_value as u8;
+        },
+        22 => {
+            if parameter_value == 0 {
+                return Err(ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            msg!("updating liquidation threshold to: {} bps", parameter_value);
+            parameters.liquidation_threshold_bps = parameter_value;
+        },
         _ => return Err(ProtocolControllerError::ParameterValidationFailed.into()),
     }
     
@@ -236,6 +424,9 @@ pub fn coordinate_mint_operation(
 
 
 // coordinate burn across all programs
+// redeems USDtx for the collateral of its bucket, paid from the liquid buffer in the escrow
+// and only pulled from the strategy when the buffer cannot cover it, what neither covers
+// (or anything behind earlier tickets) is locked in the redemption queue for a keeper to fill
 pub fn coordinate_burn_operation(
     _program_id: &Pubkey,
     accounts: &[AccountInfo],
@@ -243,7 +434,7 @@ pub fn coordinate_burn_operation(
 ) -> Result<(), ProgramError> {
     msg!("USDtx burn");
     
-    if data.len() < 16 {
+    if data.len() < 17 {
         return Err(ProtocolControllerError::ParameterValidationFailed.into());
     }
     
@@ -253,13 +444,135 @@ pub fn coordinate_burn_operation(
     let redeem_type = data[8];
     let expected_collateral = u64::from_le_bytes(data[9..17].try_into().unwrap());
 
-  
-    ///work in progress
-    // steps:
-    // 1.call USDtx token program to burn tokens
-    // 2.call strategy manager to withdraw collateral
-    // 3.call escrow program to release collateral
-    // 4.validate solvency post-burn
+    if burn_amount == 0 || (redeem_type != ASSET_SOL && redeem_type != ASSET_USDC) {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol controller, 1 = escrow (SOL escrow or USDC PSM vault), 2 = strategy registry,
+    // 3 = strategy manager program, 4 = strategy state, 5 = token program,
+    // 6 = protocol parameters, 7 = minting state, 8 = user (signer), 9 = user USDtx account,
+    // 10 = USDtx mint, 11 = user collateral token account, 12 = redemption queue,
+    // 13 = redemption ticket (created when queued), 14 = USDtx queue escrow, 15 = system program,
+    // 16 = dynamic fee curve,
+    // SOL only: 17 = doppler oracle, 18 = oracle guard, 19 = primary source feed,
+    // 20 = secondary source feed, 21 = doppler price history
+    let required_accounts = if redeem_type == ASSET_SOL { 22 } else { 17 };
+    if accounts.len() < required_accounts {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let protocol_controller_account = &accounts[0];
+    let escrow = &accounts[1];
+    let user = &accounts[8];
+    let usdtx_mint = &accounts[10];
+
+    if !user.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (_, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    {
+        let controller_data = protocol_controller_account.try_borrow_data()?;
+        if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+            &controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        if controller_state.is_paused {
+            msg!("protocol paused, no redemptions");
+            return Err(ProtocolControllerError::CoordinationOperationMismatch.into());
+        }
+    }
+
+    let parameters = read_protocol_parameters(&accounts[6])?;
+
+    MintingState::verify_address(&accounts[7])?;
+    let bucket_supply = {
+        let minting_data = accounts[7].try_borrow_data()?;
+        let minting = MintingState::load(&minting_data)?;
+        if redeem_type == ASSET_SOL {
+            minting.verify_sol_accounts(usdtx_mint, escrow)?;
+            minting.sol_backed_supply
+        } else {
+            minting.verify_psm_accounts(usdtx_mint, escrow)?;
+            minting.psm_backed_supply
+        }
+    };
+    if burn_amount > bucket_supply {
+        msg!("burn {} over the bucket supply of {}", burn_amount, bucket_supply);
+        return Err(ProtocolControllerError::CoordinationOperationMismatch.into());
+    }
+
+    // both buckets pay the curve's redeem fee, SOL's stays in the escrow as extra collateral
+    // SOL redeems through the Doppler aggregate, USDC 1:1 minus the fee
+    let clock = Clock::get()?;
+    let (collateral_out, psm_fee) = if redeem_type == ASSET_SOL {
+        let (_, redeem_fee_bps) = read_charged_fees(&accounts[16], clock.slot, 0)?;
+        let (_, redeemed_usd) = psm_mint_amounts(burn_amount, redeem_fee_bps);
+        let (sol_price_usd, _) = resolve_doppler_price(&accounts[18..22], accounts[17].key(), &parameters, &clock)?;
+        (sol_lamports_for_usd(redeemed_usd, sol_price_usd), 0)
+    } else {
+        let (_, redeem_fee_bps) = read_charged_fees(&accounts[16], clock.slot, parameters.psm_fee_bps)?;
+        let (fee, usdc_out) = psm_mint_amounts(burn_amount, redeem_fee_bps);
+        (usdc_out, fee)
+    };
+
+    if collateral_out == 0 || collateral_out < expected_collateral {
+        msg!("collateral out {} under expected {}", collateral_out, expected_collateral);
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let queue_pending = redemption_queue_pending(&accounts[12], redeem_type)?;
+
+    let (from_buffer, from_strategies) = redemption_sources(token_account_amount(escrow)?, collateral_out);
+    if !queue_pending && from_strategies > 0 {
+        msg!("liquidity buffer short by {}, recalling from strategy", from_strategies);
+        recall_collateral_from_strategy(&accounts[0..6], redeem_type, from_strategies, protocol_controller_bump)?;
+    }
+
+    if queue_pending || token_account_amount(escrow)? < collateral_out {
+        enqueue_redemption(
+            &accounts[12],
+            &accounts[13],
+            user,
+            &accounts[9],
+            &accounts[14],
+            redeem_type,
+            burn_amount,
+            collateral_out,
+            psm_fee,
+        )?;
+        return Ok(());
+    }
+
+    pinocchio_token::instructions::Burn {
+        account: &accounts[9],
+        mint: usdtx_mint,
+        authority: user,
+        amount: burn_amount,
+    }
+    .invoke()?;
+
+    crate::insurance::invoke_controller_transfer(
+        escrow,
+        &accounts[11],
+        protocol_controller_account,
+        collateral_out,
+        protocol_controller_bump,
+    )?;
+
+    {
+        let mut minting_data = accounts[7].try_borrow_mut_data()?;
+        MintingState::load_mut(&mut minting_data)?.record_redemption(redeem_type, burn_amount, collateral_out, psm_fee);
+    }
+    record_controller_redemption(protocol_controller_account, redeem_type, burn_amount, collateral_out)?;
+
+    msg!("burned {} USDtx for {} collateral ({} from buffer)", burn_amount, collateral_out, from_buffer);
     
     Ok(())
 }
@@ -294,215 +607,3100 @@ pub fn sync_vault_states(
 
 
 
-// full system solvency across all programs
-pub fn validate_system_solvency(
+
+// SOL collateral workflow
+// wrap, escrow, deploy, price and mint in one transaction
+
+
+// deploy escrowed SOL into a strategy, the authority picks the strategy
+// the SOL liquidity buffer stays in the escrow
+pub fn coordinate_sol_strategy_deployment(
     _program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> Result<(), ProgramError> {
-    msg!("system solvency with CPIs");
+    msg!("deploying SOL collateral to strategy");
 
-  
     if data.len() < 8 {
         return Err(ProtocolControllerError::ParameterValidationFailed.into());
     }
-    if accounts.len() < 5 {
+    let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+
+    // 0 = protocol controller, 1 = SOL escrow, 2 = SOL allocation table, 3 = strategy registry,
+    // 4 = strategy manager program, 5 = strategy state, 6 = token program,
+    // 7 = protocol parameters, 8 = authority, 9 = minting state
+    if accounts.len() < 10 {
         return Err(ProgramError::NotEnoughAccountKeys);
     }
 
-  
-    let min_collateral_ratio_bps = u16::from_le_bytes(data[0..2].try_into().unwrap());
-    
-    // protocol controller PDA bump
-    let protocol_controller_account = &accounts[0];
+    let parameters = read_protocol_parameters(&accounts[7])?;
+    parameters.require_authority(&accounts[8])?;
+
+    let liquidity_buffer = {
+        MintingState::verify_address(&accounts[9])?;
+        let minting_data = accounts[9].try_borrow_data()?;
+        let minting = MintingState::load(&minting_data)?;
+        if minting.is_initialized == 0 || accounts[1].key() != &minting.sol_escrow {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        liquidity_buffer_target(minting.sol_collateral_lamports, parameters.sol_liquidity_buffer_bps)
+    };
+
     let (_, protocol_controller_bump) = Pubkey::find_program_address(
         &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
         &crate::ID,
     );
-    
-    
-    // invoke solvency checks for all vaults
-    crate::cpi::ProtocolCPI::invoke_solvency_check(accounts, protocol_controller_bump)
-        .map_err(|e| ProgramError::Custom(e as u32))?;
-    
-    //updated oracle prices
-    let sol_price_usd = crate::cpi::ProtocolCPI::invoke_oracle_price_update(accounts, protocol_controller_bump)
-        .map_err(|e| ProgramError::Custom(e as u32))?;
-    
-    //update protocol controller state with the solvency results
-    let mut controller_data = protocol_controller_account.try_borrow_mut_data()?;
-    if controller_data.len() >= std::mem::size_of::<crate::state::ProtocolController>() {
-        let controller_state = bytemuck::cast_mut::<crate::state::ProtocolController>(&mut controller_data);
-        
-        // mathematical calculations with collateral data
-        use crate::math::ProtocolMath;
-        
-        // these values would come from vault's CPIs
-        let total_usdtx_supply = controller_state.total_usdtx_minted.saturating_sub(controller_state.total_usdtx_burned);
-        let total_sol_collateral = controller_state.current_sol_tvl;
-        let total_usdc_collateral = controller_state.current_usdc_tvl;
-        
-        let sol_value_usd = (total_sol_collateral as u128)
-            .saturating_mul(sol_price_usd as u128)
-            .saturating_div(1_000_000_000u128) as u64;
-        
-        let total_backing_value = sol_value_usd.saturating_add(total_usdc_collateral);
-        
-        // actual collateralization ratio using our math utilities
-        let collateral_ratio = ProtocolMath::calculate_collateralization_ratio(
-            total_usdtx_supply,
-            total_backing_value,
-        ).map_err(|e| ProgramError::Custom(e as u32))?;
-        
-        // updating state
-        controller_state.global_collateral_ratio = collateral_ratio;
-        controller_state.last_solvency_check = pinocchio::clock::Clock::get()?.unix_timestamp;
-
-        msg!("check results");
-        msg!("total USDtx supply: ${}", total_pusd_supply / 1_000_000);
-        msg!("SOL collateral value: ${}", sol_value_usd / 1_000_000);
-        msg!("USDC collateral: ${}", total_usdc_collateral / 1_000_000);
-        msg!("total collateral value: ${}", total_backing_value / 1_000_000);
-        msg!("collateral ratio: {:.2}%", collateral_ratio as u32 / 100.0);
-      //should add more messages for the bellow cases
-      //this check should be manually called to monitor the system
-      //automatic checks to be implemented
 
+    deploy_collateral_to_strategy(&accounts[0..7], ASSET_SOL, amount, liquidity_buffer, protocol_controller_bump)?;
 
-      
-///        //minimum ratio.. update emergency state
-///        if collateral_ratio < min_collateral_ratio_bps {
-///            msg!("collateral ratio below minimum");
-///            controller_state.emergency_mode = true;
-///            controller_state.last_emergency_action = pinocchio::clock::Clock::get()?.unix_timestamp;
-///            return Err(ProtocolControllerError::InsufficientCollateralization.into());
-///      
-///      //1% threshold to minimum collateral ratio
-///        } else if collateral_ratio < min_collateral_ratio_bps + 100 {
-///            msg!("collateral ratio close to minimum");
-///      
-///        } else {
-///            msg!("system healthy");
-///            if controller_state.emergency_mode {
-///                controller_state.emergency_mode = false;
-///            }
-///        }
-///    }
-    
     Ok(())
 }
 
 
-// harvesting yield from all strategies
-// this is done with CPIs and based on timestamp or specific parameters
-pub fn harvest_all_yield(
+// SOL in, USDtx out: wrap SOL into the escrow, deploy it, price it through the Doppler
+// aggregate and mint at the minimum collateral ratio, failing under min_usdtx_out
+pub fn orchestrate_sol_to_usdtx_workflow(
     _program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> Result<(), ProgramError> {
-    msg!("yield harvest from all strategies via CPIs");
-    
-    if data.len() < 1 {
+    msg!("SOL to USDtx workflow");
+
+    // lamports (8), min USDtx out (8)
+    if data.len() < 16 {
         return Err(ProtocolControllerError::ParameterValidationFailed.into());
     }
-    
-    if accounts.len() < 4 {
+    let lamports = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let min_usdtx_out = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    if lamports == 0 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol controller, 1 = SOL escrow, 2 = SOL allocation table, 3 = strategy registry,
+    // 4 = strategy manager program, 5 = strategy state, 6 = token program,
+    // 7 = protocol parameters, 8 = minting state, 9 = user (signer), 10 = user USDtx account,
+    // 11 = USDtx mint, 12 = system program, 13 = doppler oracle, 14 = oracle guard,
+    // 15 = primary source feed, 16 = secondary source feed, 17 = doppler price history,
+    // 18 = dynamic fee curve
+    if accounts.len() < 19 {
         return Err(ProgramError::NotEnoughAccountKeys);
     }
-    
-    let harvest_mode = data[0];
 
-  
-    // protocol controller PDA bump
     let protocol_controller_account = &accounts[0];
+    let sol_escrow = &accounts[1];
+    let user = &accounts[9];
+    let user_usdtx_account = &accounts[10];
+    let usdtx_mint = &accounts[11];
+
+    if !user.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
     let (_, protocol_controller_bump) = Pubkey::find_program_address(
         &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
         &crate::ID,
     );
 
-  // yield harvest parameters
-  // 3 different modes
-    msg!("harvest mode: {}", match harvest_mode {
-        1 => "SOL strategies only",
-        2 => "USDC strategies only", 
-        3 => "All strategies",
-        _ => "Invalid mode",
-    });
-    
-    if harvest_mode == 0 || harvest_mode > 3 {
-        return Err(ProtocolControllerError::YieldHarvestingFailed.into());
+    {
+        let controller_data = protocol_controller_account.try_borrow_data()?;
+        if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+            &controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        if controller_state.is_paused {
+            msg!("protocol paused, no minting");
+            return Err(ProtocolControllerError::CoordinationOperationMismatch.into());
+        }
     }
-    
-    //CPIs to strategy managers for harvesting the yields
-    let total_yield_harvested = crate::cpi::ProtocolCPI::invoke_yield_harvesting(
-        accounts, 
-        protocol_controller_bump
-    ).map_err(|e| ProgramError::Custom(e as u32))?;
-    
-    // update protocol controller state(with harvest results)
+
+    let parameters = read_protocol_parameters(&accounts[7])?;
+    parameters.require_minting_open()?;
+
+    MintingState::verify_address(&accounts[8])?;
+    let liquidity_buffer = {
+        let minting_data = accounts[8].try_borrow_data()?;
+        let minting = MintingState::load(&minting_data)?;
+        minting.verify_sol_accounts(usdtx_mint, sol_escrow)?;
+        // the buffer counts the collateral coming in
+        liquidity_buffer_target(
+            minting.sol_collateral_lamports.saturating_add(lamports),
+            parameters.sol_liquidity_buffer_bps,
+        )
+    };
+
+    // price first, nothing moves if the oracle cannot price the collateral
+    let clock = Clock::get()?;
+    let (sol_price_usd, price_origin) = resolve_doppler_price(&accounts[14..18], accounts[13].key(), &parameters, &clock)?;
+    let collateral_value_usd = sol_value_usd(lamports, sol_price_usd);
+    // the mint fee is never minted, it stays in the escrow as extra collateral
+    let (mint_fee_bps, _) = read_charged_fees(&accounts[18], clock.slot, 0)?;
+    let (_, usdtx_out) = psm_mint_amounts(
+        usdtx_for_collateral(collateral_value_usd, parameters.min_collateral_ratio_bps),
+        mint_fee_bps,
+    );
+
+    msg!("SOL price: {} (origin {}), collateral value: ${}", sol_price_usd, price_origin, collateral_value_usd / 1_000_000);
+
+    if usdtx_out == 0 || usdtx_out < min_usdtx_out {
+        msg!("USDtx out {} under minimum {}", usdtx_out, min_usdtx_out);
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // wrap: lamports into the wSOL escrow, then sync its token amount
+    pinocchio_system::instructions::Transfer {
+        from: user,
+        to: sol_escrow,
+        lamports,
+    }
+    .invoke()?;
+    pinocchio_token::instructions::SyncNative {
+        native_token: sol_escrow,
+    }
+    .invoke()?;
+
+    // deploy, coordinate_sol_strategy_deployment without the authority
+    deploy_collateral_to_strategy(&accounts[0..7], ASSET_SOL, lamports, liquidity_buffer, protocol_controller_bump)?;
+
+    invoke_usdtx_mint(usdtx_mint, user_usdtx_account, protocol_controller_account, usdtx_out, protocol_controller_bump)?;
+
+    {
+        let mut minting_data = accounts[8].try_borrow_mut_data()?;
+        let minting = MintingState::load_mut(&mut minting_data)?;
+        minting.sol_backed_supply = minting.sol_backed_supply.saturating_add(usdtx_out);
+        minting.sol_collateral_lamports = minting.sol_collateral_lamports.saturating_add(lamports);
+        minting.sol_mint_count = minting.sol_mint_count.saturating_add(1);
+    }
+
     let mut controller_data = protocol_controller_account.try_borrow_mut_data()?;
     if controller_data.len() >= std::mem::size_of::<crate::state::ProtocolController>() {
         let controller_state = bytemuck::cast_mut::<crate::state::ProtocolController>(&mut controller_data);
-        
-        controller_state.total_yield_harvested = controller_state.total_yield_harvested
-            .saturating_add(total_yield_harvested);
-        
-        let current_time = pinocchio::clock::Clock::get()?.unix_timestamp;
-        
+        controller_state.total_usdtx_minted = controller_state.total_usdtx_minted.saturating_add(usdtx_out);
+        controller_state.current_sol_tvl = controller_state.current_sol_tvl.saturating_add(lamports);
+    }
+
+    msg!("minted {} USDtx for {} lamports", usdtx_out, lamports);
+
     Ok(())
 }
 
 
-  
-//distribute yields to stakers (through the thaler escrow)
-pub fn distribute_yield_to_thaler(
+
+
+// USDC peg stability module (PSM)
+// USDC in, USDtx out 1:1 minus the fee, capped per epoch and accounted apart from SOL minting
+
+
+// deploy USDC held by the PSM vault into a strategy, the authority picks the strategy
+// the USDC liquidity buffer stays in the vault
+pub fn coordinate_usdc_strategy_deployment(
     _program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> Result<(), ProgramError> {
-    msg!("distributing yield");
-    
-    if data.len() < 16 {
+    msg!("deploying USDC collateral to strategy");
+
+    if data.len() < 8 {
         return Err(ProtocolControllerError::ParameterValidationFailed.into());
     }
-    
-    let total_yield_usdc = u64::from_le_bytes(data[0..8].try_into().unwrap());
-    let eligible_stakers = u32::from_le_bytes(data[8..12].try_into().unwrap());
-    let treasury_fee_bps = u16::from_le_bytes(data[12..14].try_into().unwrap());
+    let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
 
+    // 0 = protocol controller, 1 = USDC PSM vault, 2 = USDC allocation table, 3 = strategy registry,
+    // 4 = strategy manager program, 5 = strategy state, 6 = token program,
+    // 7 = protocol parameters, 8 = authority, 9 = minting state
+    if accounts.len() < 10 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
 
-  
-    //treasury fee (default 20%)
-    let treasury_fee = (total_yield_usdc as u128)
-        .saturating_mul(treasury_fee_bps as u128)
-        .saturating_div(10_000u128) as u64;
-    
-    let distributable_yield = total_yield_usdc.saturating_sub(treasury_fee);
-    
-    //calculate Thalers to mint(each worth exactly $100 USDC)
-    let thaler_tokens_to_mint = distributable_yield / 100_000_000;
+    let parameters = read_protocol_parameters(&accounts[7])?;
+    parameters.require_authority(&accounts[8])?;
+
+    let liquidity_buffer = {
+        MintingState::verify_address(&accounts[9])?;
+        let minting_data = accounts[9].try_borrow_data()?;
+        let minting = MintingState::load(&minting_data)?;
+        if minting.is_initialized == 0 || accounts[1].key() != &minting.usdc_psm_vault {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        liquidity_buffer_target(minting.psm_usdc_collateral, parameters.usdc_liquidity_buffer_bps)
+    };
+
+    let (_, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    deploy_collateral_to_strategy(&accounts[0..7], ASSET_USDC, amount, liquidity_buffer, protocol_controller_bump)?;
 
-    // steps:
-    // 1.call Thaler program to mint new tokens
-    // 2.call TWAB staking to distribute based on time-weighted balances
-    // 3.update yields state
-    
     Ok(())
 }
 
-//rebalance all strategy allocations
+
+// USDC to USDtx through the PSM, the fee stays in the PSM vault and the rest is deployed
+pub fn orchestrate_usdc_to_usdtx_workflow(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("USDC to USDtx workflow (PSM)");
+
+    // USDC amount (8), min USDtx out (8)
+    if data.len() < 16 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    let usdc_amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let min_usdtx_out = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    if usdc_amount == 0 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol controller, 1 = USDC PSM vault, 2 = USDC allocation table, 3 = strategy registry,
+    // 4 = strategy manager program, 5 = strategy state, 6 = token program,
+    // 7 = protocol parameters, 8 = minting state, 9 = user (signer), 10 = user USDC account,
+    // 11 = user USDtx account, 12 = USDtx mint, 13 = dynamic fee curve
+    if accounts.len() < 14 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let protocol_controller_account = &accounts[0];
+    let usdc_psm_vault = &accounts[1];
+    let user = &accounts[9];
+    let usdtx_mint = &accounts[12];
+
+    if !user.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (_, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    {
+        let controller_data = protocol_controller_account.try_borrow_data()?;
+        if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+            &controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        if controller_state.is_paused {
+            msg!("protocol paused, no minting");
+            return Err(ProtocolControllerError::CoordinationOperationMismatch.into());
+        }
+    }
+
+    let parameters = read_protocol_parameters(&accounts[7])?;
+    parameters.require_minting_open()?;
+    let clock = Clock::get()?;
+    let (mint_fee_bps, _) = read_charged_fees(&accounts[13], clock.slot, parameters.psm_fee_bps)?;
+    let (fee, usdtx_out) = psm_mint_amounts(usdc_amount, mint_fee_bps);
+
+    if usdtx_out == 0 || usdtx_out < min_usdtx_out {
+        msg!("USDtx out {} under minimum {}", usdtx_out, min_usdtx_out);
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // epoch cap is taken before any funds move
+    MintingState::verify_address(&accounts[8])?;
+    let liquidity_buffer = {
+        let mut minting_data = accounts[8].try_borrow_mut_data()?;
+        let minting = MintingState::load_mut(&mut minting_data)?;
+        minting.verify_psm_accounts(usdtx_mint, usdc_psm_vault)?;
+        minting.reserve_psm_capacity(
+            usdtx_out,
+            clock.unix_timestamp,
+            parameters.psm_epoch_length,
+            parameters.psm_epoch_cap,
+        )?;
+        // the buffer counts the collateral coming in
+        liquidity_buffer_target(
+            minting.psm_usdc_collateral.saturating_add(usdtx_out),
+            parameters.usdc_liquidity_buffer_bps,
+        )
+    };
+
+    pinocchio_token::instructions::Transfer {
+        from: &accounts[10],
+        to: usdc_psm_vault,
+        authority: user,
+        amount: usdc_amount,
+    }
+    .invoke()?;
+
+    // deploy, coordinate_usdc_strategy_deployment without the authority
+    deploy_collateral_to_strategy(&accounts[0..7], ASSET_USDC, usdtx_out, liquidity_buffer, protocol_controller_bump)?;
+
+    invoke_usdtx_mint(usdtx_mint, &accounts[11], protocol_controller_account, usdtx_out, protocol_controller_bump)?;
+
+    let utilization_bps = {
+        let mut minting_data = accounts[8].try_borrow_mut_data()?;
+        let minting = MintingState::load_mut(&mut minting_data)?;
+        minting.psm_backed_supply = minting.psm_backed_supply.saturating_add(usdtx_out);
+        minting.psm_usdc_collateral = minting.psm_usdc_collateral.saturating_add(usdtx_out);
+        minting.psm_fees_collected = minting.psm_fees_collected.saturating_add(fee);
+        minting.psm_mint_count = minting.psm_mint_count.saturating_add(1);
+        minting.psm_utilization_bps
+    };
+
+    let mut controller_data = protocol_controller_account.try_borrow_mut_data()?;
+    if controller_data.len() >= std::mem::size_of::<crate::state::ProtocolController>() {
+        let controller_state = bytemuck::cast_mut::<crate::state::ProtocolController>(&mut controller_data);
+        controller_state.total_usdtx_minted = controller_state.total_usdtx_minted.saturating_add(usdtx_out);
+        controller_state.current_usdc_tvl = controller_state.current_usdc_tvl.saturating_add(usdtx_out);
+    }
+
+    msg!("PSM minted {} USDtx, fee {}", usdtx_out, fee);
+    msg!("PSM epoch utilization: {} bps", utilization_bps);
+
+    Ok(())
+}
+
+
+
+
+// USDtx freeze for yield
+// frozen balances are tracked per user in a TWAB ledger and globally in the TWAB registry,
+// the USDtx token program locks them and the controller keeps positions and the frozen supply
+
+
+// freeze USDtx for yield
+pub fn coordinate_usdtx_freeze_for_yield(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("USDtx freeze for yield");
+
+    if data.len() < 8 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = user (signer, payer), 1 = user USDtx token account, 2 = TWAB registry, 3 = user TWAB ledger,
+    // 4 = user freeze position, 5 = freeze supply, 6 = USDtx mint, 7 = USDtx token program,
+    // 8 = protocol controller, 9 = minting state, 10 = thaler distribution, 11 = system program
+    if accounts.len() < 12 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let freeze_amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    if freeze_amount == 0 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let user_account = &accounts[0];
+    if !user_account.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    // the TWAB registry on the first freeze ever, the user's ledger on their first freeze
+    let registry_bump = TwabRegistry::verify_address(&accounts[2])?;
+    if accounts[2].lamports() == 0 {
+        let bump_seed = [registry_bump];
+        crate::program_account::create_program_account(
+            user_account,
+            &accounts[2],
+            TwabRegistry::LEN,
+            &[Seed::from(TWAB_REGISTRY_SEED), Seed::from(&bump_seed)],
+        )?;
+    } else if !accounts[2].is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let ledger_bump = TwabLedger::verify_address(&accounts[3], user_account.key())?;
+    if accounts[3].lamports() == 0 {
+        let bump_seed = [ledger_bump];
+        crate::program_account::create_program_account(
+            user_account,
+            &accounts[3],
+            TwabLedger::LEN,
+            &[Seed::from(TWAB_LEDGER_SEED), Seed::from(user_account.key().as_ref()), Seed::from(&bump_seed)],
+        )?;
+    } else if !accounts[3].is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+
+    MintingState::verify_address(&accounts[9])?;
+    let mut minting_data = accounts[9].try_borrow_mut_data()?;
+    let minting = MintingState::load_mut(&mut minting_data)?;
+    if minting.is_initialized == 0 || accounts[6].key() != &minting.usdtx_mint {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let token_balance = {
+        let token_account = pinocchio_token::state::TokenAccount::from_account_info(&accounts[1])?;
+        if token_account.owner() != user_account.key() || token_account.mint() != accounts[6].key() {
+            return Err(ProgramError::IllegalOwner);
+        }
+        token_account.amount()
+    };
+
+    // nothing is owed for periods the ledger held nothing in, settlement starts after them
+    let (distribution_count, last_period_end) = if accounts[10].is_owned_by(&crate::ID) {
+        ThalerDistribution::verify_address(&accounts[10])?;
+        let distribution_data = accounts[10].try_borrow_data()?;
+        let distribution = ThalerDistribution::load(&distribution_data)?;
+        (distribution.distribution_count, distribution.period_end_timestamp)
+    } else {
+        (0, 0)
+    };
+
+    let mut ledger_data = accounts[3].try_borrow_mut_data()?;
+    let ledger = TwabLedger::load_mut(&mut ledger_data)?;
+    if ledger.is_initialized == 0 {
+        ledger.owner = *user_account.key();
+        ledger.bump = ledger_bump;
+        ledger.is_initialized = 1;
+    }
+    ledger.skip_empty_periods(distribution_count, last_period_end);
+
+    let new_balance = ledger.balance
+        .checked_add(freeze_amount)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    if new_balance > token_balance {
+        msg!("freeze amount exceeds USDtx balance");
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    let (_, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    invoke_usdtx_freeze(
+        USDTX_FREEZE_FOR_YIELD_IX,
+        &accounts[7],
+        &accounts[8],
+        user_account,
+        &accounts[1],
+        &accounts[6],
+        freeze_amount,
+        protocol_controller_bump,
+    )?;
+
+    let position_account = &accounts[4];
+    let position_bump = FreezePosition::verify_address(position_account, user_account.key())?;
+    if position_account.lamports() == 0 {
+        let bump_seed = [position_bump];
+        crate::program_account::create_program_account(
+            user_account,
+            position_account,
+            FreezePosition::LEN,
+            &[Seed::from(FREEZE_POSITION_SEED), Seed::from(user_account.key().as_ref()), Seed::from(&bump_seed)],
+        )?;
+    } else if !position_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let supply_account = &accounts[5];
+    let supply_bump = FreezeSupply::verify_address(supply_account)?;
+    if supply_account.lamports() == 0 {
+        let bump_seed = [supply_bump];
+        crate::program_account::create_program_account(
+            user_account,
+            supply_account,
+            FreezeSupply::LEN,
+            &[Seed::from(FREEZE_SUPPLY_SEED), Seed::from(&bump_seed)],
+        )?;
+    } else if !supply_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut position_data = position_account.try_borrow_mut_data()?;
+    let position = FreezePosition::load_mut(&mut position_data)?;
+    if position.is_initialized == 0 {
+        position.owner = *user_account.key();
+        position.bump = position_bump;
+        position.is_initialized = 1;
+    }
+
+    let mut supply_data = supply_account.try_borrow_mut_data()?;
+    let supply = FreezeSupply::load_mut(&mut supply_data)?;
+    if supply.is_initialized == 0 {
+        supply.bump = supply_bump;
+        supply.is_initialized = 1;
+    }
+
+    if position.frozen_amount == 0 {
+        position.frozen_since = current_time;
+        supply.frozen_position_count = supply.frozen_position_count.saturating_add(1);
+    }
+    position.frozen_amount = position.frozen_amount.saturating_add(freeze_amount);
+    position.last_frozen_at = current_time;
+
+    supply.total_frozen_supply = supply.total_frozen_supply.saturating_add(freeze_amount);
+    supply.last_update_timestamp = current_time;
+    minting.frozen_supply = minting.frozen_supply.saturating_add(freeze_amount);
+
+    let mut registry_data = accounts[2].try_borrow_mut_data()?;
+    let registry = TwabRegistry::load_mut(&mut registry_data)?;
+    if registry.is_initialized == 0 {
+        registry.bump = registry_bump;
+        registry.is_initialized = 1;
+    }
+
+    registry.accrue(current_time);
+    if ledger.balance == 0 {
+        registry.staker_count = registry.staker_count.saturating_add(1);
+    }
+    registry.total_balance = registry.total_balance.saturating_add(freeze_amount);
+
+    ledger.set_balance(new_balance, current_time, registry.last_period_end_timestamp)?;
+
+    msg!("frozen balance: {}", new_balance);
+    msg!("total frozen: {}", supply.total_frozen_supply);
+
+    Ok(())
+}
+
+
+// unfreeze USDtx from yield
+// after the cooldown since the last freeze; the passed closed epochs are settled first, the ledger
+// keeps what the others need so they stay claimable
+pub fn coordinate_usdtx_unfreeze_from_yield(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("USDtx unfreeze from yield");
+
+    if data.len() < 8 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = user, 1 = TWAB registry, 2 = user TWAB ledger, 3 = user freeze position, 4 = freeze supply,
+    // 5 = user USDtx token account, 6 = USDtx mint, 7 = USDtx token program, 8 = protocol controller,
+    // 9 = protocol parameters, 10 = thaler distribution, 11 = thaler distribution vault,
+    // 12 = user Thaler token account, 13 = token program, 14 = minting state,
+    // 15.. = yield epochs after the ledger's last settled one, in order (any number)
+    if accounts.len() < 15 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let unfreeze_amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+
+    let user_account = &accounts[0];
+    if !user_account.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    TwabRegistry::verify_address(&accounts[1])?;
+    TwabLedger::verify_address(&accounts[2], user_account.key())?;
+    if !accounts[1].is_owned_by(&crate::ID) || !accounts[2].is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let parameters = read_protocol_parameters(&accounts[9])?;
+
+    let mut position_data = {
+        FreezePosition::verify_address(&accounts[3], user_account.key())?;
+        if !accounts[3].is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        accounts[3].try_borrow_mut_data()?
+    };
+    let position = FreezePosition::load_mut(&mut position_data)?;
+    if position.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if current_time < position.cooldown_ends_at(parameters.freeze_cooldown_seconds) {
+        msg!("unfreeze cooldown until {}", position.cooldown_ends_at(parameters.freeze_cooldown_seconds));
+        return Err(ProtocolControllerError::CoordinationOperationMismatch.into());
+    }
+
+    let mut ledger_data = accounts[2].try_borrow_mut_data()?;
+    let ledger = TwabLedger::load_mut(&mut ledger_data)?;
+    if ledger.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if unfreeze_amount == 0 || unfreeze_amount > ledger.balance || unfreeze_amount > position.frozen_amount {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let (_, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    MintingState::verify_address(&accounts[14])?;
+    let mut minting_data = accounts[14].try_borrow_mut_data()?;
+    let minting = MintingState::load_mut(&mut minting_data)?;
+    if minting.is_initialized == 0 || accounts[6].key() != &minting.usdtx_mint {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // settle before the balance changes, nothing to settle before the first distribution
+    let settled_thalers = if accounts[10].is_owned_by(&crate::ID) {
+        let distribution = {
+            ThalerDistribution::verify_address(&accounts[10])?;
+            let distribution_data = accounts[10].try_borrow_data()?;
+            *ThalerDistribution::load(&distribution_data)?
+        };
+        if accounts[11].key() != &distribution.distribution_vault {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        {
+            let recipient = pinocchio_token::state::TokenAccount::from_account_info(&accounts[12])?;
+            if recipient.owner() != user_account.key() || recipient.mint() != &distribution.thaler_mint {
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+
+        settle_thaler_entitlements(
+            ledger,
+            distribution.last_closed_epoch,
+            &accounts[15..],
+            &accounts[11],
+            &accounts[12],
+            &accounts[8],
+            protocol_controller_bump,
+        )?
+    } else {
+        if accounts[10].key() != &Pubkey::find_program_address(&[THALER_DISTRIBUTION_SEED], &crate::ID).0 {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        0
+    };
+
+    invoke_usdtx_freeze(
+        USDTX_UNFREEZE_FROM_YIELD_IX,
+        &accounts[7],
+        &accounts[8],
+        user_account,
+        &accounts[5],
+        &accounts[6],
+        unfreeze_amount,
+        protocol_controller_bump,
+    )?;
+
+    FreezeSupply::verify_address(&accounts[4])?;
+    let mut supply_data = accounts[4].try_borrow_mut_data()?;
+    let supply = FreezeSupply::load_mut(&mut supply_data)?;
+
+    position.frozen_amount -= unfreeze_amount;
+    position.last_unfrozen_at = current_time;
+    if position.frozen_amount == 0 {
+        position.frozen_since = 0;
+        supply.frozen_position_count = supply.frozen_position_count.saturating_sub(1);
+    }
+    supply.total_frozen_supply = supply.total_frozen_supply.saturating_sub(unfreeze_amount);
+    supply.last_update_timestamp = current_time;
+    minting.frozen_supply = minting.frozen_supply.saturating_sub(unfreeze_amount);
+
+    let mut registry_data = accounts[1].try_borrow_mut_data()?;
+    let registry = TwabRegistry::load_mut(&mut registry_data)?;
+
+    let new_balance = ledger.balance - unfreeze_amount;
+
+    registry.accrue(current_time);
+    registry.total_balance = registry.total_balance.saturating_sub(unfreeze_amount);
+    if new_balance == 0 {
+        registry.staker_count = registry.staker_count.saturating_sub(1);
+    }
+
+    ledger.set_balance(new_balance, current_time, registry.last_period_end_timestamp)?;
+
+    msg!("settled Thalers (base units): {}", settled_thalers);
+    msg!("frozen balance: {}", new_balance);
+    msg!("total frozen: {}", supply.total_frozen_supply);
+
+    Ok(())
+}
+
+
+// compare the freeze state held by the USDtx token accounts, the TWAB ledgers (Thaler side)
+// and the controller positions for a set of users, report every disagreement and, signed by the
+// authority, repair the controller and TWAB side towards the USDtx lock
+pub fn sync_freeze_states_across_programs(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("sync freeze states");
+
+    // flags (1): bit 0 repair, bit 1 the users are every open position; user count (1)
+    if data.len() < 2 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    let repair = data[0] & 1 != 0;
+    let complete_set = data[0] & 2 != 0;
+    let user_count = data[1] as usize;
+    if user_count == 0 || user_count > MAX_SYNC_USERS {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = freeze supply, 1 = TWAB registry, 2 = protocol parameters, 3 = authority (repair only),
+    // 4 = minting state, 5.. = per user: freeze position, TWAB ledger, USDtx token account
+    if accounts.len() < 5 + 3 * user_count {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    if repair {
+        read_protocol_parameters(&accounts[2])?.require_authority(&accounts[3])?;
+    }
+
+    FreezeSupply::verify_address(&accounts[0])?;
+    if !accounts[0].is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut supply_data = accounts[0].try_borrow_mut_data()?;
+    let supply = FreezeSupply::load_mut(&mut supply_data)?;
+
+    TwabRegistry::verify_address(&accounts[1])?;
+    if !accounts[1].is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut registry_data = accounts[1].try_borrow_mut_data()?;
+    let registry = TwabRegistry::load_mut(&mut registry_data)?;
+
+    let current_time = Clock::get()?.unix_timestamp;
+    if repair {
+        registry.accrue(current_time);
+    }
+
+    // users checked, users out of sync, users repaired, frozen supply discrepancy (i64),
+    // TWAB registry discrepancy (i64), then one report byte per user
+    let mut report = [0u8; 19 + MAX_SYNC_USERS];
+    let mut mismatched = 0u8;
+    let mut repaired = 0u8;
+    let mut frozen_sum = 0u64;
+    let mut open_positions = 0u64;
+    let mut owners = [[0u8; 32]; MAX_SYNC_USERS];
+
+    for i in 0..user_count {
+        let position_account = &accounts[5 + 3 * i];
+        let ledger_account = &accounts[6 + 3 * i];
+        let token_account = &accounts[7 + 3 * i];
+
+        let (token_owner, token_frozen, token_amount) = {
+            let token = pinocchio_token::state::TokenAccount::from_account_info(token_account)?;
+            (*token.owner(), token.is_frozen(), token.amount())
+        };
+
+        // a user listed twice would count twice towards the frozen supply
+        if owners[..i].contains(&token_owner) {
+            msg!("user {} listed twice", i);
+            return Err(ProtocolControllerError::ParameterValidationFailed.into());
+        }
+        owners[i] = token_owner;
+
+        FreezePosition::verify_address(position_account, &token_owner)?;
+        TwabLedger::verify_address(ledger_account, &token_owner)?;
+        if !position_account.is_owned_by(&crate::ID) || !ledger_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut position_data = position_account.try_borrow_mut_data()?;
+        let position = FreezePosition::load_mut(&mut position_data)?;
+        let mut ledger_data = ledger_account.try_borrow_mut_data()?;
+        let ledger = TwabLedger::load_mut(&mut ledger_data)?;
+
+        let discrepancy = freeze_discrepancy(position.frozen_amount, ledger.balance, token_frozen, token_amount);
+        report[19 + i] = discrepancy;
+
+        if discrepancy != 0 {
+            mismatched += 1;
+            msg!("user {} out of sync: {} (position {}, ledger {})", i, discrepancy, position.frozen_amount, ledger.balance);
+
+            if repair {
+                if let Some(amount) = repaired_frozen_amount(position.frozen_amount, token_frozen, token_amount) {
+                    // supply and position count follow the position, the registry follows the ledger
+                    if position.frozen_amount > 0 && amount == 0 {
+                        supply.frozen_position_count = supply.frozen_position_count.saturating_sub(1);
+                        position.frozen_since = 0;
+                    }
+                    supply.total_frozen_supply = supply.total_frozen_supply
+                        .saturating_sub(position.frozen_amount)
+                        .saturating_add(amount);
+                    position.frozen_amount = amount;
+
+                    if ledger.balance > 0 && amount == 0 {
+                        registry.staker_count = registry.staker_count.saturating_sub(1);
+                    } else if ledger.balance == 0 && amount > 0 {
+                        registry.staker_count = registry.staker_count.saturating_add(1);
+                    }
+                    registry.total_balance = registry.total_balance
+                        .saturating_sub(ledger.balance)
+                        .saturating_add(amount);
+                    ledger.set_balance(amount, current_time, registry.last_period_end_timestamp)?;
+
+                    repaired += 1;
+                }
+            }
+        }
+
+        frozen_sum = frozen_sum.saturating_add(position.frozen_amount);
+        if position.frozen_amount > 0 {
+            open_positions += 1;
+        }
+    }
+
+    // totals can only be compared when the users cover every open position
+    let (supply_discrepancy, registry_discrepancy) = if complete_set {
+        let supply_discrepancy = supply.total_frozen_supply as i64 - frozen_sum as i64;
+        let registry_discrepancy = registry.total_balance as i64 - frozen_sum as i64;
+        if repair && (supply_discrepancy != 0 || registry_discrepancy != 0) {
+            supply.total_frozen_supply = frozen_sum;
+            supply.frozen_position_count = open_positions;
+            registry.total_balance = frozen_sum;
+            registry.staker_count = open_positions;
+        }
+        (supply_discrepancy, registry_discrepancy)
+    } else {
+        (0, 0)
+    };
+
+    // the minting state's frozen total follows the repaired supply
+    if repair {
+        supply.last_update_timestamp = current_time;
+
+        MintingState::verify_address(&accounts[4])?;
+        let mut minting_data = accounts[4].try_borrow_mut_data()?;
+        MintingState::load_mut(&mut minting_data)?.frozen_supply = supply.total_frozen_supply;
+    }
+
+    report[0] = user_count as u8;
+    report[1] = mismatched;
+    report[2] = repaired;
+    report[3..11].copy_from_slice(&supply_discrepancy.to_le_bytes());
+    report[11..19].copy_from_slice(&registry_discrepancy.to_le_bytes());
+    pinocchio::program::set_return_data(&report[..19 + user_count]);
+
+    msg!("users out of sync: {} of {}, repaired: {}", mismatched, user_count, repaired);
+    if complete_set {
+        msg!("frozen supply discrepancy: {}", supply_discrepancy);
+        msg!("TWAB registry discrepancy: {}", registry_discrepancy);
+    }
+
+    Ok(())
+}
+
+
+// solvency split by freeze state: liquid USDtx against the collateral liquid in the escrows,
+// frozen USDtx against what the liquid side does not need, each with its own threshold
+// the report is stored and returned for risk dashboards
+pub fn validate_system_solvency_freeze_based(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("freeze-based solvency check");
+
+    // 0 = protocol controller, 1 = protocol parameters, 2 = minting state, 3 = freeze supply,
+    // 4 = SOL escrow, 5 = USDC PSM vault, 6 = solvency report, 7 = payer (first check only),
+    // 8 = system program, 9 = doppler oracle, 10 = oracle guard, 11 = primary source feed,
+    // 12 = secondary source feed, 13 = doppler price history
+    if accounts.len() < 14 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    crate::program_account::verify_protocol_controller(&accounts[0])?;
+    let parameters = read_protocol_parameters(&accounts[1])?;
+
+    MintingState::verify_address(&accounts[2])?;
+    {
+        let minting_data = accounts[2].try_borrow_data()?;
+        let minting = MintingState::load(&minting_data)?;
+        if minting.is_initialized == 0
+            || accounts[4].key() != &minting.sol_escrow
+            || accounts[5].key() != &minting.usdc_psm_vault
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    let (total_usdtx_supply, sol_tvl, usdc_tvl) = {
+        let controller_data = accounts[0].try_borrow_data()?;
+        if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+            &controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        (
+            controller_state.total_usdtx_minted.saturating_sub(controller_state.total_usdtx_burned),
+            controller_state.current_sol_tvl,
+            controller_state.current_usdc_tvl,
+        )
+    };
+
+    // no freeze yet means no frozen supply
+    let frozen_supply = if accounts[3].is_owned_by(&crate::ID) {
+        FreezeSupply::verify_address(&accounts[3])?;
+        let supply_data = accounts[3].try_borrow_data()?;
+        FreezeSupply::load(&supply_data)?.total_frozen_supply.min(total_usdtx_supply)
+    } else {
+        0
+    };
+    let liquid_supply = total_usdtx_supply - frozen_supply;
+
+    let clock = Clock::get()?;
+    let (sol_price_usd, _) = resolve_fallback_price(&accounts[10..14], accounts[9].key(), &parameters, &clock)?;
+
+    let liquid_collateral_value = sol_value_usd(token_account_amount(&accounts[4])?, sol_price_usd)
+        .saturating_add(token_account_amount(&accounts[5])?);
+    let total_collateral_value = sol_value_usd(sol_tvl, sol_price_usd).saturating_add(usdc_tvl);
+
+    let (liquid_backing_ratio_bps, frozen_backing_ratio_bps) = split_backing_ratios(
+        liquid_collateral_value,
+        liquid_supply,
+        frozen_supply,
+        parameters.liquid_min_backing_bps,
+    );
+    let collateral_ratio_bps = backing_ratio_bps(total_collateral_value, total_usdtx_supply);
+
+    let mut status = 0u8;
+    if liquid_backing_ratio_bps < parameters.liquid_min_backing_bps {
+        msg!("liquid backing under threshold");
+        status |= SOLVENCY_LIQUID_BREACH;
+    }
+    if frozen_backing_ratio_bps < parameters.frozen_min_backing_bps {
+        msg!("frozen backing under threshold");
+        status |= SOLVENCY_FROZEN_BREACH;
+    }
+    if collateral_ratio_bps < parameters.min_collateral_ratio_bps {
+        msg!("collateral ratio under minimum");
+        status |= SOLVENCY_UNDERCOLLATERALIZED;
+    }
+
+    let report_account = &accounts[6];
+    let report_bump = SolvencyReport::verify_address(report_account)?;
+    if report_account.lamports() == 0 {
+        let bump_seed = [report_bump];
+        crate::program_account::create_program_account(
+            &accounts[7],
+            report_account,
+            SolvencyReport::LEN,
+            &[Seed::from(SOLVENCY_REPORT_SEED), Seed::from(&bump_seed)],
+        )?;
+    } else if !report_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut report_data = report_account.try_borrow_mut_data()?;
+    let report = SolvencyReport::load_mut(&mut report_data)?;
+    if report.is_initialized == 0 {
+        report.bump = report_bump;
+        report.is_initialized = 1;
+    }
+    report.liquid_collateral_value = liquid_collateral_value;
+    report.total_collateral_value = total_collateral_value;
+    report.liquid_supply = liquid_supply;
+    report.frozen_supply = frozen_supply;
+    report.liquid_backing_ratio_bps = liquid_backing_ratio_bps;
+    report.frozen_backing_ratio_bps = frozen_backing_ratio_bps;
+    report.collateral_ratio_bps = collateral_ratio_bps;
+    report.liquid_min_backing_bps = parameters.liquid_min_backing_bps;
+    report.frozen_min_backing_bps = parameters.frozen_min_backing_bps;
+    report.sol_price_usd = sol_price_usd;
+    report.last_check_timestamp = clock.unix_timestamp;
+    report.last_check_slot = clock.slot;
+    report.check_count = report.check_count.saturating_add(1);
+    report.status = status;
+
+    let mut return_data = [0u8; 25];
+    return_data[0..8].copy_from_slice(&liquid_backing_ratio_bps.to_le_bytes());
+    return_data[8..16].copy_from_slice(&frozen_backing_ratio_bps.to_le_bytes());
+    return_data[16..24].copy_from_slice(&collateral_ratio_bps.to_le_bytes());
+    return_data[24] = status;
+    pinocchio::program::set_return_data(&return_data);
+
+    msg!("liquid supply: ${}, frozen supply: ${}", liquid_supply / 1_000_000, frozen_supply / 1_000_000);
+    msg!("liquid collateral: ${}", liquid_collateral_value / 1_000_000);
+    msg!("liquid backing: {} bps, frozen backing: {} bps", liquid_backing_ratio_bps, frozen_backing_ratio_bps);
+    msg!("collateral ratio: {} bps", collateral_ratio_bps);
+
+    Ok(())
+}
+
+
+// close the last Thaler period as a yield epoch with a merkle root of entitlements
+// users claim with claim_epoch_thalers
+pub fn distribute_yields_to_thaler_freezers(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("distributing Thalers to freezers");
+
+    crate::yield_epoch::close_yield_epoch(accounts, data)
+}
+
+// oracle price aggregation (doppler)
+
+
+// collateral ratio of the outstanding USDtx supply at a given SOL price
+fn collateral_ratio_at_price(
+    controller_state: &crate::state::ProtocolController,
+    sol_price_usd: u64,
+) -> Result<u16, ProgramError> {
+    use crate::math::ProtocolMath;
+
+    let total_usdtx_supply = controller_state.total_usdtx_minted.saturating_sub(controller_state.total_usdtx_burned);
+
+    let sol_value_usd = (controller_state.current_sol_tvl as u128)
+        .saturating_mul(sol_price_usd as u128)
+        .saturating_div(1_000_000_000u128) as u64;
+
+    let total_backing_value = sol_value_usd.saturating_add(controller_state.current_usdc_tvl);
+
+    ProtocolMath::calculate_collateralization_ratio(total_usdtx_supply, total_backing_value)
+        .map_err(|e| ProgramError::Custom(e as u32))
+}
+
+
+// aggregate source prices into the doppler spot price and append it to the TWAP history
+pub fn aggregate_oracle_prices(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("aggregating doppler oracle prices");
+
+    // 0 = protocol controller, 1 = doppler oracle, 2 = doppler price history,
+    // first aggregate only: 3 = payer, 4 = system program
+    if accounts.len() < 3 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let doppler_oracle_account = &accounts[1];
+    let price_history_account = &accounts[2];
+    let (expected_address, history_bump) = Pubkey::find_program_address(
+        &[crate::twap::DOPPLER_PRICE_HISTORY_SEED, doppler_oracle_account.key().as_ref()],
+        &crate::ID,
+    );
+    if price_history_account.key() != &expected_address {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if price_history_account.lamports() == 0 {
+        let payer = accounts.get(3).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let bump_seed = [history_bump];
+        crate::program_account::create_program_account(
+            payer,
+            price_history_account,
+            crate::twap::DopplerPriceHistory::LEN,
+            &[
+                Seed::from(crate::twap::DOPPLER_PRICE_HISTORY_SEED),
+                Seed::from(doppler_oracle_account.key().as_ref()),
+                Seed::from(&bump_seed),
+            ],
+        )?;
+    } else if !price_history_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let (_, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    let aggregated_price = crate::cpi::ProtocolCPI::invoke_oracle_price_update(accounts, protocol_controller_bump)
+        .map_err(|e| ProgramError::Custom(e as u32))?;
+    if aggregated_price == 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let clock = Clock::get()?;
+
+    let mut history_data = price_history_account.try_borrow_mut_data()?;
+    let history = crate::twap::DopplerPriceHistory::load_mut(&mut history_data)?;
+    if history.is_initialized == 0 {
+        history.doppler_oracle = *doppler_oracle_account.key();
+        history.bump = history_bump;
+        history.is_initialized = 1;
+    }
+
+    history.record(aggregated_price, clock.unix_timestamp, clock.slot);
+
+    msg!("aggregated SOL price: ${}", aggregated_price / 1_000_000);
+    msg!("price history entries: {}", history.count);
+
+    Ok(())
+}
+
+
+// compare each source against the aggregate and the aggregate against a recent previous one
+// a breach records which feed diverged, puts the controller in emergency mode with type 2 and
+// pauses minting only, redemptions keep working until governance clears the pause
+// (update_protocol_parameters type 21)
+pub fn validate_price_deviations(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("validating oracle price deviations");
+
+    if data.len() < 1 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let source_count = data[0] as usize;
+    if source_count == 0 || source_count > MAX_PRICE_SOURCES {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol controller, 1 = protocol parameters (writable, a breach sets the mint pause),
+    // 2 = doppler oracle, 3 = doppler price history, 4 = oracle guard, 5.. = source price feeds,
+    // then payer and system program, needed when a breach finds no oracle guard yet
+    if accounts.len() < 5 + source_count {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    crate::program_account::verify_protocol_controller(&accounts[0])?;
+    let parameters = read_protocol_parameters(&accounts[1])?;
+    let doppler_oracle = accounts[2].key();
+    parameters.require_doppler_oracle(doppler_oracle)?;
+
+    let (latest, previous) = {
+        crate::twap::DopplerPriceHistory::verify_address(&accounts[3], doppler_oracle)?;
+        let history_data = accounts[3].try_borrow_data()?;
+        let history = crate::twap::DopplerPriceHistory::load(&history_data)?;
+        let latest = history.latest().ok_or(ProgramError::UninitializedAccount)?;
+        (latest, history.previous())
+    };
+
+    let clock = Clock::get()?;
+    let mut worst_breach: Option<DeviationBreach> = None;
+
+    // each source against the current aggregate
+    for feed_account in &accounts[5..5 + source_count] {
+        // a source that was never updated has nothing to compare
+        if !feed_account.is_owned_by(&crate::ID) {
+            continue;
+        }
+        let feed_data = feed_account.try_borrow_data()?;
+        let feed = SourcePriceFeed::load(&feed_data)?;
+        SourcePriceFeed::verify_address(feed_account, doppler_oracle, feed.source_id)?;
+        if feed.is_initialized == 0 || feed.price == 0 {
+            continue;
+        }
+
+        let deviation = deviation_bps(feed.price, latest.price);
+        msg!("source {} deviation from aggregate: {} bps", feed.source_id, deviation);
+
+        if deviation > parameters.max_source_deviation_bps
+            && worst_breach.map_or(true, |breach| deviation > breach.deviation_bps)
+        {
+            worst_breach = Some(DeviationBreach {
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
+                source_id: feed.source_id,
+                kind: DEVIATION_SOURCE_VS_AGGREGATE,
+                _padding: [0; 6],
+                observed_price: feed.price,
+                reference_price: latest.price,
+                deviation_bps: deviation,
+            });
+        }
+    }
+
+    // aggregate against the previous aggregate, a move over a longer stretch is no breach
+    if let Some(previous) = previous
+        .filter(|previous| latest.timestamp.saturating_sub(previous.timestamp) <= MAX_AGGREGATE_COMPARISON_WINDOW)
+    {
+        let deviation = deviation_bps(latest.price, previous.price);
+        msg!("aggregate move since previous: {} bps", deviation);
+
+        if deviation > parameters.max_aggregate_deviation_bps
+            && worst_breach.map_or(true, |breach| deviation > breach.deviation_bps)
+        {
+            worst_breach = Some(DeviationBreach {
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
+                source_id: 0,
+                kind: DEVIATION_AGGREGATE_VS_PREVIOUS,
+                _padding: [0; 6],
+                observed_price: latest.price,
+                reference_price: previous.price,
+                deviation_bps: deviation,
+            });
+        }
+    }
+
+    let breach = match worst_breach {
+        Some(breach) => breach,
+        None => {
+            msg!("all sources within deviation bands");
+            return Ok(());
+        }
+    };
+
+    {
+        let guard_account = &accounts[4];
+        let guard_bump = create_oracle_guard_if_missing(guard_account, doppler_oracle, accounts.get(5 + source_count))?;
+        let mut guard_data = guard_account.try_borrow_mut_data()?;
+        let guard = OracleGuard::load_mut(&mut guard_data)?;
+        if guard.is_initialized == 0 {
+            guard.doppler_oracle = *doppler_oracle;
+            guard.bump = guard_bump;
+            guard.is_initialized = 1;
+        }
+
+        guard.breach_count = guard.breach_count.saturating_add(1);
+        guard.last_breach = breach;
+    }
+
+    msg!("deviation band breached by source {}", breach.source_id);
+    msg!("observed: {}, reference: {}, deviation: {} bps", breach.observed_price, breach.reference_price, breach.deviation_bps);
+
+    // the breach is recorded and the pause kept, so this does not return an error
+    {
+        let mut controller_data = accounts[0].try_borrow_mut_data()?;
+        if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes_mut::<crate::state::ProtocolController>(
+            &mut controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        controller_state.emergency_mode = true;
+        controller_state.last_emergency_action = clock.unix_timestamp;
+        controller_state.emergency_override_count = controller_state.emergency_override_count.saturating_add(1);
+    }
+
+    let mut parameters_data = accounts[1].try_borrow_mut_data()?;
+    ProtocolParameters::load_mut(&mut parameters_data)?.mint_paused = 1;
+    msg!("emergency type 2: oracle price deviation, minting paused");
+
+    Ok(())
+}
+
+
+// doppler price through the fallback hierarchy, returned as (price u64, origin u8)
+pub fn get_doppler_aggregated_price(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("fetching doppler price");
+
+    // 0 = protocol parameters, 1 = doppler oracle, 2 = oracle guard,
+    // 3 = primary source feed, 4 = secondary source feed, 5 = doppler price history
+    if accounts.len() < 6 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let parameters = read_protocol_parameters(&accounts[0])?;
+    let clock = Clock::get()?;
+
+    // the price minting and redeeming use
+    let (price, origin) = resolve_doppler_price(&accounts[2..6], accounts[1].key(), &parameters, &clock)?;
+
+    msg!("doppler price: {} (origin {})", price, origin);
+
+    let mut return_data = [0u8; 9];
+    return_data[0..8].copy_from_slice(&price.to_le_bytes());
+    return_data[8] = origin;
+    pinocchio::program::set_return_data(&return_data);
+
+    Ok(())
+}
+
+
+// update the global collateral ratio with the spot or TWAP price
+pub fn update_collateral_ratios(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("updating collateral ratios");
+
+    // 0 = protocol controller, 1 = doppler oracle, 2 = doppler price history, 3 = protocol parameters
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let (price_source, _) = crate::twap::PriceSource::parse(data)?;
+
+    let protocol_controller_account = &accounts[0];
+    let protocol_controller_bump = crate::program_account::verify_protocol_controller(protocol_controller_account)?;
+    read_protocol_parameters(&accounts[3])?.require_doppler_oracle(accounts[1].key())?;
+
+    let spot_price = crate::cpi::ProtocolCPI::invoke_oracle_price_update(accounts, protocol_controller_bump)
+        .map_err(|e| ProgramError::Custom(e as u32))?;
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let sol_price_usd = price_source.resolve(spot_price, &accounts[2], accounts[1].key(), current_time)?;
+
+    let mut controller_data = protocol_controller_account.try_borrow_mut_data()?;
+    if controller_data.len() >= std::mem::size_of::<crate::state::ProtocolController>() {
+        let controller_state = bytemuck::cast_mut::<crate::state::ProtocolController>(&mut controller_data);
+
+        let collateral_ratio = collateral_ratio_at_price(controller_state, sol_price_usd)?;
+        controller_state.global_collateral_ratio = collateral_ratio;
+
+        msg!("SOL price used: ${}", sol_price_usd / 1_000_000);
+        msg!("collateral ratio: {} bps", collateral_ratio);
+    }
+
+    Ok(())
+}
+
+
+// mint and redeem fees from the governed curve over the collateral ratio, plus the net flow since
+// the last update against TVL, anyone can crank it, the authority sets the curve
+pub fn update_tvl_data_and_dynamic_fees(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    if data.is_empty() {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    match data[0] {
+        DYNAMIC_FEES_UPDATE => update_dynamic_fees(accounts),
+        DYNAMIC_FEES_SET_CURVE => set_dynamic_fee_curve(accounts, &data[1..]),
+        _ => Err(ProtocolControllerError::ParameterValidationFailed.into()),
+    }
+}
+
+fn update_dynamic_fees(accounts: &[AccountInfo]) -> Result<(), ProgramError> {
+    msg!("updating TVL data and dynamic fees");
+
+    // 0 = protocol controller, 1 = protocol parameters, 2 = dynamic fee curve,
+    // 3 = doppler oracle, 4 = oracle guard, 5 = primary source, 6 = secondary source, 7 = doppler price history
+    if accounts.len() < 8 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    crate::program_account::verify_protocol_controller(&accounts[0])?;
+    let parameters = read_protocol_parameters(&accounts[1])?;
+
+    let (total_minted, total_burned, sol_tvl, usdc_tvl) = {
+        let controller_data = accounts[0].try_borrow_data()?;
+        if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+            &controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        (
+            controller_state.total_usdtx_minted,
+            controller_state.total_usdtx_burned,
+            controller_state.current_sol_tvl,
+            controller_state.current_usdc_tvl,
+        )
+    };
+
+    let clock = Clock::get()?;
+    let (sol_price_usd, _) = resolve_fallback_price(&accounts[4..8], accounts[3].key(), &parameters, &clock)?;
+
+    let tvl_usd = sol_value_usd(sol_tvl, sol_price_usd).saturating_add(usdc_tvl);
+    let collateral_ratio_bps = backing_ratio_bps(tvl_usd, total_minted.saturating_sub(total_burned));
+
+    let curve_account = &accounts[2];
+    DynamicFeeCurve::verify_address(curve_account)?;
+    if !curve_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut curve_data = curve_account.try_borrow_mut_data()?;
+    let curve = DynamicFeeCurve::load_mut(&mut curve_data)?;
+    if curve.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    // the first update only takes the flow snapshot
+    let flow_imbalance = if curve.update_count == 0 {
+        0
+    } else {
+        let minted = total_minted.saturating_sub(curve.minted_snapshot);
+        let burned = total_burned.saturating_sub(curve.burned_snapshot);
+        (minted as i128 - burned as i128).clamp(i64::MIN as i128, i64::MAX as i128) as i64
+    };
+
+    let (mint_fee_bps, redeem_fee_bps) = dynamic_fees(
+        &curve.points[..curve.point_count as usize],
+        collateral_ratio_bps,
+        flow_imbalance,
+        tvl_usd,
+        curve.flow_sensitivity_bps,
+    );
+
+    curve.last_tvl_usd = tvl_usd;
+    curve.last_collateral_ratio_bps = collateral_ratio_bps;
+    curve.last_flow_imbalance = flow_imbalance;
+    curve.minted_snapshot = total_minted;
+    curve.burned_snapshot = total_burned;
+    curve.mint_fee_bps = mint_fee_bps;
+    curve.redeem_fee_bps = redeem_fee_bps;
+    curve.last_update_timestamp = clock.unix_timestamp;
+    curve.last_update_slot = clock.slot;
+    curve.update_count = curve.update_count.saturating_add(1);
+
+    // a master override stays in force over the curve until it expires
+    let (effective_mint_fee_bps, effective_redeem_fee_bps) = curve.effective_fees(clock.slot);
+    if curve.active_override(clock.slot).is_some() {
+        msg!("fee override active until slot {}", curve.fee_override.expiry_slot);
+    }
+
+    // effective mint fee (2), effective redeem fee (2), collateral ratio (8), TVL (8), flow imbalance (8)
+    let mut return_data = [0u8; 28];
+    return_data[0..2].copy_from_slice(&effective_mint_fee_bps.to_le_bytes());
+    return_data[2..4].copy_from_slice(&effective_redeem_fee_bps.to_le_bytes());
+    return_data[4..12].copy_from_slice(&collateral_ratio_bps.to_le_bytes());
+    return_data[12..20].copy_from_slice(&tvl_usd.to_le_bytes());
+    return_data[20..28].copy_from_slice(&flow_imbalance.to_le_bytes());
+    pinocchio::program::set_return_data(&return_data);
+
+    msg!("TVL: ${}, collateral ratio: {} bps, net flow: {}", tvl_usd / 1_000_000, collateral_ratio_bps, flow_imbalance);
+    msg!("mint fee: {} bps, redeem fee: {} bps", mint_fee_bps, redeem_fee_bps);
+
+    Ok(())
+}
+
+fn set_dynamic_fee_curve(accounts: &[AccountInfo], data: &[u8]) -> Result<(), ProgramError> {
+    msg!("setting dynamic fee curve");
+
+    // flow sensitivity bps (8), count (1),
+    // then per point: collateral ratio bps (8), mint fee bps (2), redeem fee bps (2)
+    if data.len() < 9 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    let flow_sensitivity_bps = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let count = data[8] as usize;
+    if count == 0 || count > MAX_FEE_CURVE_POINTS || data.len() < 9 + 12 * count {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let mut points = [FeeCurvePoint::zeroed(); MAX_FEE_CURVE_POINTS];
+    for (i, point) in points.iter_mut().take(count).enumerate() {
+        let offset = 9 + 12 * i;
+        point.collateral_ratio_bps = u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        point.mint_fee_bps = u16::from_le_bytes(data[offset + 8..offset + 10].try_into().unwrap());
+        point.redeem_fee_bps = u16::from_le_bytes(data[offset + 10..offset + 12].try_into().unwrap());
+    }
+    if !is_valid_fee_curve(&points[..count]) {
+        msg!("curve ratios must increase with fees not increasing, fees at most {} bps", MAX_DYNAMIC_FEE_BPS);
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol parameters, 1 = authority (payer), 2 = dynamic fee curve, 3 = system program
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let authority_account = &accounts[1];
+    read_protocol_parameters(&accounts[0])?.require_authority(authority_account)?;
+
+    let curve_account = &accounts[2];
+    let curve_bump = DynamicFeeCurve::verify_address(curve_account)?;
+    if curve_account.lamports() == 0 {
+        let bump_seed = [curve_bump];
+        crate::program_account::create_program_account(
+            authority_account,
+            curve_account,
+            DynamicFeeCurve::LEN,
+            &[Seed::from(DYNAMIC_FEE_CURVE_SEED), Seed::from(&bump_seed)],
+        )?;
+    } else if !curve_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut curve_data = curve_account.try_borrow_mut_data()?;
+    let curve = DynamicFeeCurve::load_mut(&mut curve_data)?;
+    if curve.is_initialized == 0 {
+        // fees start at the top of the curve until the first update
+        curve.mint_fee_bps = points[0].mint_fee_bps;
+        curve.redeem_fee_bps = points[0].redeem_fee_bps;
+        curve.bump = curve_bump;
+        curve.is_initialized = 1;
+    }
+    curve.points = points;
+    curve.point_count = count as u8;
+    curve.flow_sensitivity_bps = flow_sensitivity_bps;
+
+    msg!("{} curve points, flow sensitivity {} bps", count, flow_sensitivity_bps);
+
+    Ok(())
+}
+
+
+// pause the protocol with emergency type 4 when the ratio falls under the liquidation threshold
+pub fn liquidation_trigger(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("checking liquidation threshold");
+
+    // 0 = protocol controller, 1 = doppler oracle, 2 = doppler price history, 3 = protocol parameters
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let (price_source, _) = crate::twap::PriceSource::parse(data)?;
+
+    let protocol_controller_account = &accounts[0];
+    let protocol_controller_bump = crate::program_account::verify_protocol_controller(protocol_controller_account)?;
+
+    // governed, the caller only picks the price source
+    let parameters = read_protocol_parameters(&accounts[3])?;
+    parameters.require_doppler_oracle(accounts[1].key())?;
+    let liquidation_threshold_bps = parameters.liquidation_threshold_bps;
+
+    let spot_price = crate::cpi::ProtocolCPI::invoke_oracle_price_update(accounts, protocol_controller_bump)
+        .map_err(|e| ProgramError::Custom(e as u32))?;
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let sol_price_usd = price_source.resolve(spot_price, &accounts[2], accounts[1].key(), current_time)?;
+
+    let collateral_ratio = {
+        let controller_data = protocol_controller_account.try_borrow_data()?;
+        if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+            &controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        collateral_ratio_at_price(controller_state, sol_price_usd)?
+    };
+
+    msg!("collateral ratio: {} bps, threshold: {} bps", collateral_ratio, liquidation_threshold_bps);
+
+    if (collateral_ratio as u64) < liquidation_threshold_bps {
+        msg!("liquidation threshold breached");
+        return emergency_protocol_pause(program_id, accounts, &4u32.to_le_bytes());
+    }
+
+    Ok(())
+}
+
+
+
+// full system solvency across all programs
+pub fn validate_system_solvency(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("system solvency with CPIs");
+
+  
+    if data.len() < 8 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    if accounts.len() < 5 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+  
+    let min_collateral_ratio_bps = u16::from_le_bytes(data[0..2].try_into().unwrap());
+    
+    // protocol controller PDA bump
+    let protocol_controller_account = &accounts[0];
+    let (_, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+    
+    
+    // invoke solvency checks for all vaults
+    crate::cpi::ProtocolCPI::invoke_solvency_check(accounts, protocol_controller_bump)
+        .map_err(|e| ProgramError::Custom(e as u32))?;
+    
+    //updated oracle prices
+    let sol_price_usd = crate::cpi::ProtocolCPI::invoke_oracle_price_update(accounts, protocol_controller_bump)
+        .map_err(|e| ProgramError::Custom(e as u32))?;
+    
+    //update protocol controller state with the solvency results
+    let mut controller_data = protocol_controller_account.try_borrow_mut_data()?;
+    if controller_data.len() >= std::mem::size_of::<crate::state::ProtocolController>() {
+        let controller_state = bytemuck::cast_mut::<crate::state::ProtocolController>(&mut controller_data);
+        
+        // mathematical calculations with collateral data
+        use crate::math::ProtocolMath;
+        
+        // these values would come from vault's CPIs
+        let total_usdtx_supply = controller_state.total_usdtx_minted.saturating_sub(controller_state.total_usdtx_burned);
+        let total_sol_collateral = controller_state.current_sol_tvl;
+        let total_usdc_collateral = controller_state.current_usdc_tvl;
+        
+        let sol_value_usd = (total_sol_collateral as u128)
+            .saturating_mul(sol_price_usd as u128)
+            .saturating_div(1_000_000_000u128) as u64;
+        
+        let total_backing_value = sol_value_usd.saturating_add(total_usdc_collateral);
+        
+        // actual collateralization ratio using our math utilities
+        let collateral_ratio = ProtocolMath::calculate_collateralization_ratio(
+            total_usdtx_supply,
+            total_backing_value,
+        ).map_err(|e| ProgramError::Custom(e as u32))?;
+        
+        // updating state
+        controller_state.global_collateral_ratio = collateral_ratio;
+        controller_state.last_solvency_check = pinocchio::clock::Clock::get()?.unix_timestamp;
+
+        msg!("check results");
+        msg!("total USDtx supply: ${}", total_pusd_supply / 1_000_000);
+        msg!("SOL collateral value: ${}", sol_value_usd / 1_000_000);
+        msg!("USDC collateral: ${}", total_usdc_collateral / 1_000_000);
+        msg!("total collateral value: ${}", total_backing_value / 1_000_000);
+        msg!("collateral ratio: {:.2}%", collateral_ratio as u32 / 100.0);
+      //should add more messages for the bellow cases
+      //this check should be manually called to monitor the system
+      //automatic checks to be implemented
+
+
+      
+///        //minimum ratio.. update emergency state
+///        if collateral_ratio < min_collateral_ratio_bps {
+///            msg!("collateral ratio below minimum");
+///            controller_state.emergency_mode = true;
+///            controller_state.last_emergency_action = pinocchio::clock::Clock::get()?.unix_timestamp;
+///            return Err(ProtocolControllerError::InsufficientCollateralization.into());
+///      
+///      //1% threshold to minimum collateral ratio
+///        } else if collateral_ratio < min_collateral_ratio_bps + 100 {
+///            msg!("collateral ratio close to minimum");
+///      
+///        } else {
+///            msg!("system healthy");
+///            if controller_state.emergency_mode {
+///                controller_state.emergency_mode = false;
+///            }
+///        }
+///    }
+    
+    Ok(())
+}
+
+
+// harvesting yield from all strategies
+// this is done with CPIs and based on timestamp or specific parameters
+pub fn harvest_all_yield(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("yield harvest from all strategies via CPIs");
+    
+    if data.len() < 1 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    
+    let harvest_mode = data[0];
+
+  // yield harvest parameters
+  // 3 different modes
+    msg!("harvest mode: {}", match harvest_mode {
+        1 => "SOL strategies only",
+        2 => "USDC strategies only", 
+        3 => "All strategies",
+        _ => "Invalid mode",
+    });
+    
+    if harvest_mode == 0 || harvest_mode > 3 {
+        return Err(ProtocolControllerError::YieldHarvestingFailed.into());
+    }
+
+    // selected strategy managers, SOL first
+    let all_strategies: [(u8, Pubkey); 2] = [
+        (ASSET_SOL, sol_strategy_manager::ID),
+        (ASSET_USDC, usdc_strategy_manager::ID),
+    ];
+    let strategies = match harvest_mode {
+        1 => &all_strategies[0..1],
+        2 => &all_strategies[1..2],
+        _ => &all_strategies[..],
+    };
+
+    // 0 = protocol controller, 1 = protocol parameters, 2 = yields vault, 3 = token program,
+    // 4 = doppler oracle, 5 = oracle guard, 6 = primary source feed, 7 = secondary source feed,
+    // 8 = doppler price history, 9 = insurance fund, 10 = insurance vault,
+    // then per selected strategy: strategy manager program, strategy state, yield record, strategy registry
+    if accounts.len() < 11 + 4 * strategies.len() {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let parameters = read_protocol_parameters(&accounts[1])?;
+    let current_time = Clock::get()?.unix_timestamp;
+
+    // protocol controller PDA bump
+    let protocol_controller_account = &accounts[0];
+    let (protocol_controller, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    // the yields vault holds the same USDC as the pinned insurance vault
+    {
+        crate::insurance::InsuranceFund::verify_address(&accounts[9])?;
+        if !accounts[9].is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let fund_data = accounts[9].try_borrow_data()?;
+        crate::insurance::InsuranceFund::load(&fund_data)?.verify_vault(&accounts[10])?;
+        let insurance_vault = pinocchio_token::state::TokenAccount::from_account_info(&accounts[10])?;
+        verify_yields_vault(&accounts[2], &protocol_controller, insurance_vault.mint())?;
+    }
+
+    // principal for the APR snapshots
+    let (sol_tvl, usdc_tvl) = {
+        let controller_data = protocol_controller_account.try_borrow_data()?;
+        if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+            &controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        (controller_state.current_sol_tvl, controller_state.current_usdc_tvl)
+    };
+
+    let sol_price_usd = if strategies.iter().any(|(asset_type, _)| *asset_type == ASSET_SOL) {
+        resolve_fallback_price(&accounts[5..9], accounts[4].key(), &parameters, &Clock::get()?)?.0
+    } else {
+        0
+    };
+
+    let mut total_yield_harvested: u64 = 0;
+    let mut strategies_harvested = 0u8;
+
+    for (i, (asset_type, expected_program)) in strategies.iter().enumerate() {
+        let strategy_program = &accounts[11 + 4 * i];
+        let strategy_state = &accounts[12 + 4 * i];
+        let record_account = &accounts[13 + 4 * i];
+        let registry_account = &accounts[14 + 4 * i];
+
+        if strategy_program.key() != expected_program {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let registry = read_strategy_registry(registry_account, strategy_state.key())?;
+        if registry.strategy_program != *expected_program || registry.asset_type != *asset_type {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let record_bump = StrategyYieldRecord::verify_address(record_account, strategy_state.key())?;
+
+        // yield distribution frequency, per strategy
+        let is_due = {
+            let record_data = record_account.try_borrow_data()?;
+            StrategyYieldRecord::load(&record_data)?.is_due(current_time, parameters.yield_distribution_frequency)
+        };
+        if !is_due {
+            msg!("strategy {} harvested too recently, skipping", asset_type);
+            continue;
+        }
+
+        //CPI to the strategy manager, yield measured on the yields vault
+        let vault_balance_before = token_account_amount(&accounts[2])?;
+        invoke_strategy_harvest(
+            strategy_program,
+            protocol_controller_account,
+            strategy_state,
+            &accounts[2],
+            &accounts[3],
+            protocol_controller_bump,
+        )?;
+        let strategy_yield = token_account_amount(&accounts[2])?.saturating_sub(vault_balance_before);
+
+        let principal_usd = match *asset_type {
+            ASSET_SOL => (sol_tvl as u128)
+                .saturating_mul(sol_price_usd as u128)
+                .saturating_div(1_000_000_000u128) as u64,
+            _ => usdc_tvl,
+        };
+
+        let mut record_data = record_account.try_borrow_mut_data()?;
+        let record = StrategyYieldRecord::load_mut(&mut record_data)?;
+        if record.is_initialized == 0 {
+            record.initialize(expected_program, *asset_type, record_bump);
+        }
+        record.record_harvest(strategy_yield, principal_usd, current_time);
+
+        {
+            let mut registry_data = registry_account.try_borrow_mut_data()?;
+            StrategyRegistry::load_mut(&mut registry_data)?.last_harvest_timestamp = current_time;
+        }
+
+        msg!("strategy {} yield: ${}", asset_type, strategy_yield / 1_000_000);
+        msg!("strategy {} APR snapshot: {} bps", asset_type, record.last_apr_bps);
+
+        total_yield_harvested = total_yield_harvested.saturating_add(strategy_yield);
+        strategies_harvested += 1;
+    }
+
+    if strategies_harvested == 0 {
+        msg!("no selected strategy is due for harvest");
+        return Err(ProtocolControllerError::YieldHarvestingFailed.into());
+    }
+
+    // insurance slice leaves the yields vault, only the rest is distributable
+    let insurance_contribution = crate::insurance::contribute_to_insurance_fund(
+        &accounts[9],
+        &accounts[10],
+        &accounts[2],
+        protocol_controller_account,
+        total_yield_harvested,
+        parameters.insurance_fund_bps,
+        protocol_controller_bump,
+    )?;
+    msg!("insurance fund contribution: ${}", insurance_contribution / 1_000_000);
+    
+    // update protocol controller state(with harvest results)
+    let mut controller_data = protocol_controller_account.try_borrow_mut_data()?;
+    if controller_data.len() >= std::mem::size_of::<crate::state::ProtocolController>() {
+        let controller_state = bytemuck::cast_mut::<crate::state::ProtocolController>(&mut controller_data);
+        
+        controller_state.total_yield_harvested = controller_state.total_yield_harvested
+            .saturating_add(total_yield_harvested - insurance_contribution);
+
+        msg!("total yield harvested: ${}", total_yield_harvested / 1_000_000);
+    }
+        
+    Ok(())
+}
+
+
+  
+// collect realized USDC yield from the SOL strategy's Drift position into the yields vault
+// principal stays in Drift, the vault has to grow by exactly the collected yield
+pub fn collect_drift_usdc_yields_to_vault(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("collecting Drift USDC yield");
+
+    // 0 = protocol controller, 1 = strategy registry, 2 = yield record (per strategy state),
+    // 3 = strategy manager program, 4 = strategy state, 5 = Drift user, 6 = Drift USDC spot market,
+    // 7 = yields vault, 8 = token program, 9.. = Drift accounts passed through to the strategy manager
+    if accounts.len() < 9 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let protocol_controller_account = &accounts[0];
+    let (protocol_controller, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    let strategy_state = &accounts[4];
+    let registry = read_strategy_registry(&accounts[1], strategy_state.key())?;
+    if registry.strategy_program != sol_strategy_manager::ID || accounts[3].key() != &registry.strategy_program {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let drift_user = &accounts[5];
+    let drift_spot_market = &accounts[6];
+    if !drift_user.is_owned_by(&DRIFT_PROGRAM_ID) || !drift_spot_market.is_owned_by(&DRIFT_PROGRAM_ID) {
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    // the Drift user has to belong to this strategy
+    let deposit = {
+        let user_data = drift_user.try_borrow_data()?;
+        if drift_user_authority(&user_data)? != *strategy_state.key() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let spot_market_data = drift_spot_market.try_borrow_data()?;
+        verify_yields_vault(&accounts[7], &protocol_controller, &drift_spot_market_mint(&spot_market_data)?)?;
+        drift_spot_deposit(&user_data, &spot_market_data, DRIFT_USDC_MARKET_INDEX)?
+    };
+
+    let record_account = &accounts[2];
+    let record_bump = StrategyYieldRecord::verify_address(record_account, strategy_state.key())?;
+    let (is_first_collection, last_principal, last_deposit_snapshot) = {
+        let record_data = record_account.try_borrow_data()?;
+        let record = StrategyYieldRecord::load(&record_data)?;
+        (record.is_initialized == 0, record.position_principal, record.position_deposit_snapshot)
+    };
+
+    // principal follows deposits and withdrawals made since the last collection
+    let principal = if is_first_collection {
+        deposit.cumulative_deposits.max(0) as u64
+    } else {
+        drift_principal(last_principal, last_deposit_snapshot, deposit.cumulative_deposits)
+    };
+    let (realized_yield, principal) = split_realized_yield(deposit.token_amount, principal);
+
+    msg!("Drift position: {}, principal: {}, realized yield: {}", deposit.token_amount, principal, realized_yield);
+
+    let current_time = Clock::get()?.unix_timestamp;
+    let yields_vault = &accounts[7];
+
+    let cumulative_deposits_after = if realized_yield > 0 {
+        let vault_balance_before = token_account_amount(yields_vault)?;
+        invoke_drift_yield_withdraw(
+            &accounts[3],
+            protocol_controller_account,
+            strategy_state,
+            yields_vault,
+            &accounts[8],
+            drift_user,
+            drift_spot_market,
+            &accounts[9..],
+            realized_yield,
+            protocol_controller_bump,
+        )?;
+        verify_vault_delta(vault_balance_before, token_account_amount(yields_vault)?, realized_yield)?;
+
+        // Drift lowers cumulative deposits on the withdrawal, the snapshot is taken after it
+        let user_data = drift_user.try_borrow_data()?;
+        let spot_market_data = drift_spot_market.try_borrow_data()?;
+        drift_spot_deposit(&user_data, &spot_market_data, DRIFT_USDC_MARKET_INDEX)?.cumulative_deposits
+    } else {
+        deposit.cumulative_deposits
+    };
+
+    {
+        let mut record_data = record_account.try_borrow_mut_data()?;
+        let record = StrategyYieldRecord::load_mut(&mut record_data)?;
+        if record.is_initialized == 0 {
+            record.initialize(&registry.strategy_program, registry.asset_type, record_bump);
+        }
+        if realized_yield > 0 {
+            record.record_harvest(realized_yield, principal, current_time);
+        }
+        record.position_principal = principal;
+        record.position_deposit_snapshot = cumulative_deposits_after;
+    }
+
+    if realized_yield == 0 {
+        msg!("no realized yield to collect");
+        return Ok(());
+    }
+
+    {
+        let mut registry_data = accounts[1].try_borrow_mut_data()?;
+        StrategyRegistry::load_mut(&mut registry_data)?.last_harvest_timestamp = current_time;
+    }
+
+    let mut controller_data = protocol_controller_account.try_borrow_mut_data()?;
+    if controller_data.len() >= std::mem::size_of::<crate::state::ProtocolController>() {
+        let controller_state = bytemuck::cast_mut::<crate::state::ProtocolController>(&mut controller_data);
+        controller_state.total_yield_harvested = controller_state.total_yield_harvested
+            .saturating_add(realized_yield);
+    }
+
+    msg!("Drift yield collected: ${}", realized_yield / 1_000_000);
+
+    Ok(())
+}
+
+
+// redeem the interest part of the USDC strategy's Kamino deposit into the yields vault
+// interest is the cToken exchange rate move since the last collection, principal cTokens stay
+pub fn collect_kamino_usdc_yields_to_vault(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("collecting Kamino USDC interest");
+
+    // 0 = protocol controller, 1 = strategy registry, 2 = yield record (per strategy state),
+    // 3 = strategy manager program, 4 = strategy state, 5 = Kamino reserve, 6 = strategy cToken account,
+    // 7 = yields vault, 8 = token program, 9.. = Kamino accounts passed through to the strategy manager
+    if accounts.len() < 9 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let protocol_controller_account = &accounts[0];
+    let (protocol_controller, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    let strategy_state = &accounts[4];
+    let registry = read_strategy_registry(&accounts[1], strategy_state.key())?;
+    if registry.strategy_program != usdc_strategy_manager::ID || accounts[3].key() != &registry.strategy_program {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let reserve_account = &accounts[5];
+    if !reserve_account.is_owned_by(&KAMINO_LENDING_PROGRAM_ID) {
+        return Err(ProgramError::IllegalOwner);
+    }
+    let reserve = read_kamino_reserve(&reserve_account.try_borrow_data()?)?;
+
+    // the rate is only current if the reserve was refreshed earlier in this transaction
+    let clock = Clock::get()?;
+    if reserve.is_stale || reserve.last_update_slot != clock.slot {
+        msg!("Kamino reserve not refreshed this slot");
+        return Err(ProtocolControllerError::YieldHarvestingFailed.into());
+    }
+
+    let ctoken_account = &accounts[6];
+    let yields_vault = &accounts[7];
+    let ctoken_balance = {
+        let ctoken = pinocchio_token::state::TokenAccount::from_account_info(ctoken_account)?;
+        if ctoken.mint() != &reserve.collateral_mint || ctoken.owner() != strategy_state.key() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        verify_yields_vault(yields_vault, &protocol_controller, &reserve.liquidity_mint)?;
+        ctoken.amount()
+    };
+
+    let record_account = &accounts[2];
+    let record_bump = StrategyYieldRecord::verify_address(record_account, strategy_state.key())?;
+    let (rate_snapshot, ctokens_at_snapshot) = {
+        let mut record_data = record_account.try_borrow_mut_data()?;
+        let record = StrategyYieldRecord::load_mut(&mut record_data)?;
+
+        // the first collection only sets the baseline rate
+        if record.is_initialized == 0 || record.position_rate_snapshot == 0 {
+            record.initialize(&registry.strategy_program, registry.asset_type, record_bump);
+            record.position_rate_snapshot = reserve.exchange_rate;
+            record.position_principal = ctoken_balance;
+            msg!("Kamino rate baseline recorded");
+            return Ok(());
+        }
+        (record.position_rate_snapshot, record.position_principal)
+    };
+
+    let interest = accrued_interest(ctoken_balance, ctokens_at_snapshot, rate_snapshot, reserve.exchange_rate);
+    let (ctokens_to_redeem, expected_liquidity) = interest_redemption(interest, reserve.exchange_rate);
+
+    msg!("cTokens: {}, rate move: {} -> {}", ctoken_balance, rate_snapshot, reserve.exchange_rate);
+    msg!("accrued interest: {}, redeeming {} cTokens", interest, ctokens_to_redeem);
+
+    // the snapshot stays put so small interest keeps accruing
+    if ctokens_to_redeem == 0 || expected_liquidity == 0 {
+        msg!("no interest to collect");
+        return Ok(());
+    }
+
+    let vault_balance_before = token_account_amount(yields_vault)?;
+    invoke_kamino_interest_redeem(
+        &accounts[3],
+        protocol_controller_account,
+        strategy_state,
+        yields_vault,
+        &accounts[8],
+        reserve_account,
+        ctoken_account,
+        &accounts[9..],
+        ctokens_to_redeem,
+        protocol_controller_bump,
+    )?;
+    let collected = verify_redeemed_delta(vault_balance_before, token_account_amount(yields_vault)?, expected_liquidity)?;
+    let ctokens_after = token_account_amount(ctoken_account)?;
+
+    let principal = ((ctokens_after as u128).saturating_mul(reserve.exchange_rate) / RATE_WAD) as u64;
+
+    {
+        let mut record_data = record_account.try_borrow_mut_data()?;
+        let record = StrategyYieldRecord::load_mut(&mut record_data)?;
+        record.record_harvest(collected, principal, clock.unix_timestamp);
+        record.position_rate_snapshot = reserve.exchange_rate;
+        record.position_principal = ctokens_after;
+    }
+
+    {
+        let mut registry_data = accounts[1].try_borrow_mut_data()?;
+        StrategyRegistry::load_mut(&mut registry_data)?.last_harvest_timestamp = clock.unix_timestamp;
+    }
+
+    let mut controller_data = protocol_controller_account.try_borrow_mut_data()?;
+    if controller_data.len() >= std::mem::size_of::<crate::state::ProtocolController>() {
+        let controller_state = bytemuck::cast_mut::<crate::state::ProtocolController>(&mut controller_data);
+        controller_state.total_yield_harvested = controller_state.total_yield_harvested
+            .saturating_add(collected);
+    }
+
+    msg!("Kamino interest collected: ${}", collected / 1_000_000);
+
+    Ok(())
+}
+
+
+//distribute yields to stakers (through the thaler escrow)
+pub fn distribute_yield_to_thaler(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("distributing yield");
+    
+    if data.len() < 12 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol controller, 1 = TWAB registry, 2 = thaler distribution,
+    // 3 = thaler mint, 4 = thaler distribution vault, 5 = token program,
+    // 6 = protocol parameters, 7 = fee split config, 8 = yields vault,
+    // 9.. = fee recipient token accounts (config order),
+    // first distribution only: authority (payer) and system program after the fee recipients
+    if accounts.len() < 9 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    
+    let total_yield_usdc = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let eligible_stakers = u32::from_le_bytes(data[8..12].try_into().unwrap());
+
+    // governed fee rate, not caller supplied
+    let parameters = read_protocol_parameters(&accounts[6])?;
+    let treasury_fee_bps = parameters.treasury_fee_bps;
+
+    FeeSplitConfig::verify_address(&accounts[7])?;
+    if !accounts[7].is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut fee_split_data = accounts[7].try_borrow_mut_data()?;
+    let fee_split = FeeSplitConfig::load_mut(&mut fee_split_data)?;
+    let recipient_count = fee_split.recipient_count as usize;
+    if accounts.len() < 9 + recipient_count {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let protocol_controller_account = &accounts[0];
+    let (_, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    let current_time = Clock::get()?.unix_timestamp;
+
+    // TWAB registry up to now, staker count has to match the registry
+    let period_end_twab = {
+        TwabRegistry::verify_address(&accounts[1])?;
+        if !accounts[1].is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut registry_data = accounts[1].try_borrow_mut_data()?;
+        let registry = TwabRegistry::load_mut(&mut registry_data)?;
+        if registry.is_initialized == 0 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        registry.accrue(current_time);
+
+        if eligible_stakers as u64 != registry.staker_count {
+            msg!("eligible stakers {} do not match TWAB registry {}", eligible_stakers, registry.staker_count);
+            return Err(ProtocolControllerError::CoordinationOperationMismatch.into());
+        }
+        registry.cumulative_balance_seconds
+    };
+
+    let distribution_account = &accounts[2];
+    if distribution_account.lamports() == 0 {
+        let authority_account = accounts.get(9 + recipient_count).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        parameters.require_authority(authority_account)?;
+
+        let (expected_address, bump) = Pubkey::find_program_address(&[THALER_DISTRIBUTION_SEED], &crate::ID);
+        if distribution_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        let bump_seed = [bump];
+        crate::program_account::create_program_account(
+            authority_account,
+            distribution_account,
+            ThalerDistribution::LEN,
+            &[Seed::from(THALER_DISTRIBUTION_SEED), Seed::from(&bump_seed)],
+        )?;
+    }
+
+    let distribution_bump = ThalerDistribution::verify_address(distribution_account)?;
+    let mut distribution_data = distribution_account.try_borrow_mut_data()?;
+    let distribution = ThalerDistribution::load_mut(&mut distribution_data)?;
+    if distribution.is_initialized == 0 {
+        let authority_account = accounts.get(9 + recipient_count).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        parameters.require_authority(authority_account)?;
+
+        distribution.thaler_mint = *accounts[3].key();
+        distribution.distribution_vault = *accounts[4].key();
+        distribution.bump = distribution_bump;
+        distribution.is_initialized = 1;
+    }
+    distribution.verify_thaler_accounts(&accounts[3], &accounts[4])?;
+
+    // only harvested yield that has not been distributed yet
+    {
+        let controller_data = protocol_controller_account.try_borrow_data()?;
+        if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+            &controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        let undistributed_yield = controller_state.total_yield_harvested
+            .saturating_sub(distribution.total_yield_distributed);
+        if total_yield_usdc > undistributed_yield {
+            msg!("yield {} exceeds undistributed harvest {}", total_yield_usdc, undistributed_yield);
+            return Err(ProtocolControllerError::ParameterValidationFailed.into());
+        }
+    }
+
+  
+    //treasury fee (default 20%), split across the fee recipients
+    let treasury_fee = (total_yield_usdc as u128)
+        .saturating_mul(treasury_fee_bps as u128)
+        .saturating_div(10_000u128) as u64;
+
+    pay_fee_split(
+        fee_split,
+        treasury_fee,
+        &accounts[8],
+        &accounts[9..9 + recipient_count],
+        protocol_controller_account,
+        protocol_controller_bump,
+    )?;
+    
+    let distributable_yield = total_yield_usdc
+        .saturating_sub(treasury_fee)
+        .saturating_add(distribution.carry_forward_usdc);
+    
+    //calculate Thalers to mint(each worth exactly $100 USDC)
+    let (thaler_tokens_to_mint, remainder_usdc) = thalers_for_usdc(distributable_yield);
+
+    // nobody held frozen USDtx during the period, so everything waits for the next one
+    if period_end_twab <= distribution.period_end_twab || thaler_tokens_to_mint == 0 {
+        distribution.carry_forward_usdc = distributable_yield;
+        distribution.total_yield_distributed = distribution.total_yield_distributed.saturating_add(total_yield_usdc);
+        msg!("no Thalers minted, carrying forward ${}", distributable_yield / 1_000_000);
+        return Ok(());
+    }
+
+    // the previous period has to be committed as an epoch before it is overwritten
+    if distribution.last_closed_epoch < distribution.distribution_count {
+        msg!("previous Thaler period has no epoch yet");
+        return Err(ProtocolControllerError::CoordinationOperationMismatch.into());
+    }
+
+    // Thaler program mint into the distribution vault
+    invoke_thaler_mint(
+        &accounts[3],
+        &accounts[4],
+        protocol_controller_account,
+        thaler_tokens_to_mint,
+        protocol_controller_bump,
+    )?;
+
+    // close the TWAB period, stakers share period_thalers by balance·seconds within it
+    distribution.period_start_twab = distribution.period_end_twab;
+    distribution.period_end_twab = period_end_twab;
+    distribution.period_start_timestamp = distribution.period_end_timestamp;
+    distribution.period_end_timestamp = current_time;
+    distribution.period_thalers = thaler_tokens_to_mint;
+    distribution.carry_forward_usdc = remainder_usdc;
+    distribution.total_thalers_minted = distribution.total_thalers_minted.saturating_add(thaler_tokens_to_mint);
+    distribution.total_yield_distributed = distribution.total_yield_distributed.saturating_add(total_yield_usdc);
+    distribution.distribution_count = distribution.distribution_count.saturating_add(1);
+    distribution.last_distribution_timestamp = current_time;
+
+    // ledgers keep their checkpoint before this boundary from now on
+    {
+        let mut registry_data = accounts[1].try_borrow_mut_data()?;
+        TwabRegistry::load_mut(&mut registry_data)?.last_period_end_timestamp = current_time;
+    }
+
+    msg!("treasury fee: ${}", treasury_fee / 1_000_000);
+    msg!("Thalers minted: {}", thaler_tokens_to_mint);
+    msg!("carried forward: ${}", remainder_usdc / 1_000_000);
+    
+    Ok(())
+}
+
+//rebalance all strategy allocations
+// strategies drifting past the band go back to their target weights, over-weight strategies
+// are withdrawn into the rebalance vault first, then under-weight ones are topped up
 pub fn rebalance_all_strategies(
     _program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> Result<(), ProgramError> {
-    msg!("Rebalancing all strategy allocations");
-    
+    msg!("Rebalancing all strategy allocations");
+    
+    if data.len() < 2 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    
+    let rebalance_trigger = data[0];
+    let asset_type = data[1];
+    
+    msg!("rebalance trigger: {}", match rebalance_trigger {
+        1 => "scheduled",
+        2 => "authority",
+        _ => "invalid trigger",
+    });
+
+    if rebalance_trigger == 0 || rebalance_trigger > 2 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol controller, 1 = protocol parameters, 2 = strategy allocation table,
+    // 3 = rebalance vault (minting-state escrow of the asset), 4 = token program, 5 = minting state,
+    // then per strategy in table order: strategy manager program, strategy state, strategy registry,
+    // authority trigger only: authority after the strategies
+    if accounts.len() < 6 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let parameters = read_protocol_parameters(&accounts[1])?;
+    let current_time = Clock::get()?.unix_timestamp;
+
+    let protocol_controller_account = &accounts[0];
+    let (_, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    {
+        let controller_data = protocol_controller_account.try_borrow_data()?;
+        if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+            &controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        if controller_state.is_paused {
+            msg!("protocol paused, no rebalance");
+            return Err(ProtocolControllerError::CoordinationOperationMismatch.into());
+        }
+    }
+
+    // scheduled runs are permissionless, funds only move between the strategies and the escrow
+    {
+        MintingState::verify_address(&accounts[5])?;
+        let minting_data = accounts[5].try_borrow_data()?;
+        let minting = MintingState::load(&minting_data)?;
+        let escrow = if asset_type == ASSET_SOL { &minting.sol_escrow } else { &minting.usdc_psm_vault };
+        if accounts[3].key() != escrow {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+    if accounts[4].key() != &pinocchio_token::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    StrategyAllocationTable::verify_address(&accounts[2], asset_type)?;
+    if !accounts[2].is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut table_data = accounts[2].try_borrow_mut_data()?;
+    let table = StrategyAllocationTable::load_mut(&mut table_data)?;
+    if table.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    let strategy_count = table.strategy_count as usize;
+    if accounts.len() < 6 + 3 * strategy_count {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    // rebalance frequency applies to scheduled runs only
+    if rebalance_trigger == 2 {
+        let authority_account = accounts.get(6 + 3 * strategy_count).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        parameters.require_authority(authority_account)?;
+    } else if !table.is_due(current_time, parameters.rebalance_frequency) {
+        msg!("rebalanced too recently");
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // deployed principal comes from each strategy's registry
+    let mut registries = [StrategyRegistry::zeroed(); MAX_STRATEGIES_PER_ASSET];
+    for i in 0..strategy_count {
+        let strategy = &table.strategies[i];
+        if accounts[6 + 3 * i].key() != &strategy.strategy_program
+            || accounts[7 + 3 * i].key() != &strategy.strategy_state
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        registries[i] = read_strategy_registry(&accounts[8 + 3 * i], &strategy.strategy_state)?;
+    }
+
+    let total_deployed = registries[..strategy_count]
+        .iter()
+        .fold(0u64, |total, registry| total.saturating_add(registry.deployed_amount));
+    let mut targets = [0u64; MAX_STRATEGIES_PER_ASSET];
+    let mut max_drift_bps = 0u64;
+
+    for (i, strategy) in table.strategies[..strategy_count].iter().enumerate() {
+        let deployed = registries[i].deployed_amount;
+        targets[i] = target_amount(total_deployed, strategy.target_weight_bps);
+        let drift = allocation_drift_bps(deployed, targets[i], total_deployed);
+        msg!("strategy {}: deployed {}, target {}, drift {} bps", i, deployed, targets[i], drift);
+        max_drift_bps = max_drift_bps.max(drift);
+    }
+
+    if max_drift_bps <= table.drift_band_bps as u64 {
+        msg!("all strategies within the {} bps drift band", table.drift_band_bps);
+        table.last_rebalance_timestamp = current_time;
+        return Ok(());
+    }
+
+    let rebalance_vault = &accounts[3];
+    let token_program = &accounts[4];
+
+    // pull the excess out of over-weight strategies, measured on the rebalance vault
+    for i in 0..strategy_count {
+        let excess = registries[i].deployed_amount.saturating_sub(targets[i]);
+        if excess == 0 {
+            continue;
+        }
+
+        let vault_balance_before = token_account_amount(rebalance_vault)?;
+        invoke_strategy_transfer(
+            STRATEGY_MANAGER_WITHDRAW_IX,
+            &accounts[6 + 3 * i],
+            protocol_controller_account,
+            &accounts[7 + 3 * i],
+            rebalance_vault,
+            token_program,
+            excess,
+            protocol_controller_bump,
+        )?;
+        let withdrawn = token_account_amount(rebalance_vault)?.saturating_sub(vault_balance_before);
+
+        registries[i].deployed_amount = registries[i].deployed_amount.saturating_sub(withdrawn);
+        msg!("withdrew {} from strategy {}", withdrawn, i);
+    }
+
+    // top up under-weight strategies with what was pulled out, active ones and up to their cap only
+    for i in 0..strategy_count {
+        if registries[i].status != STRATEGY_STATUS_ACTIVE {
+            msg!("strategy {} not active, no deposit", i);
+            continue;
+        }
+        let shortfall = targets[i].saturating_sub(registries[i].deployed_amount);
+        let amount = shortfall
+            .min(registries[i].available_capacity())
+            .min(token_account_amount(rebalance_vault)?);
+        if amount == 0 {
+            continue;
+        }
+        registries[i].require_deployable(amount)?;
+
+        let vault_balance_before = token_account_amount(rebalance_vault)?;
+        invoke_strategy_transfer(
+            STRATEGY_MANAGER_DEPOSIT_IX,
+            &accounts[6 + 3 * i],
+            protocol_controller_account,
+            &accounts[7 + 3 * i],
+            rebalance_vault,
+            token_program,
+            amount,
+            protocol_controller_bump,
+        )?;
+        let deposited = vault_balance_before.saturating_sub(token_account_amount(rebalance_vault)?);
+
+        registries[i].deployed_amount = registries[i].deployed_amount.saturating_add(deposited);
+        msg!("deposited {} into strategy {}", deposited, i);
+    }
+
+    for i in 0..strategy_count {
+        let mut registry_data = accounts[8 + 3 * i].try_borrow_mut_data()?;
+        StrategyRegistry::load_mut(&mut registry_data)?.deployed_amount = registries[i].deployed_amount;
+    }
+
+    table.last_rebalance_timestamp = current_time;
+    table.rebalance_count = table.rebalance_count.saturating_add(1);
+
+    msg!("rebalance complete, max drift was {} bps", max_drift_bps);
+
+    Ok(())
+}
+
+
+// set target weights for whitelisted strategies of one asset, weights add up to 10_000 bps
+// a strategy can only leave the table once nothing is deployed in it
+pub fn update_strategy_weights(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("updating strategy weights");
+
+    // asset (1), drift band bps (2), count (1),
+    // then per strategy: strategy state (32), weight bps (2)
+    if data.len() < 4 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    let asset_type = data[0];
+    let drift_band_bps = u16::from_le_bytes(data[1..3].try_into().unwrap());
+    let count = data[3] as usize;
+    if count == 0 || count > MAX_STRATEGIES_PER_ASSET || data.len() < 4 + 34 * count {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    if drift_band_bps == 0 || drift_band_bps > 10_000 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol parameters, 1 = authority (payer), 2 = strategy allocation table, 3 = system program,
+    // 4.. = strategy registries in data order, then registries of strategies leaving the table
+    if accounts.len() < 4 + count {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let authority_account = &accounts[1];
+    read_protocol_parameters(&accounts[0])?.require_authority(authority_account)?;
+
+    let table_account = &accounts[2];
+    let table_bump = StrategyAllocationTable::verify_address(table_account, asset_type)?;
+    if table_account.lamports() == 0 {
+        let asset_seed = [asset_type];
+        let bump_seed = [table_bump];
+        crate::program_account::create_program_account(
+            authority_account,
+            table_account,
+            StrategyAllocationTable::LEN,
+            &[
+                Seed::from(STRATEGY_ALLOCATION_SEED),
+                Seed::from(&asset_seed),
+                Seed::from(&bump_seed),
+            ],
+        )?;
+    }
+    if !table_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut strategies = [StrategyAllocation::zeroed(); MAX_STRATEGIES_PER_ASSET];
+    for (i, strategy) in strategies.iter_mut().take(count).enumerate() {
+        let offset = 4 + 34 * i;
+        strategy.strategy_state = data[offset..offset + 32].try_into().unwrap();
+        strategy.target_weight_bps = u16::from_le_bytes(data[offset + 32..offset + 34].try_into().unwrap());
+    }
+
+    write_strategy_weights(table_account, table_bump, asset_type, drift_band_bps, &mut strategies[..count], &accounts[4..])?;
+
+    msg!("{} strategy weights set for asset {}", count, asset_type);
+
+    Ok(())
+}
+
+
+// checks new weights against the strategy registries and writes them to the allocation table,
+// registry_accounts holds the registries in weight order, then those of strategies leaving the table
+fn write_strategy_weights(
+    table_account: &AccountInfo,
+    table_bump: u8,
+    asset_type: u8,
+    drift_band_bps: u16,
+    strategies: &mut [StrategyAllocation],
+    registry_accounts: &[AccountInfo],
+) -> Result<(), ProgramError> {
+    let count = strategies.len();
+    if registry_accounts.len() < count {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let mut total_weight: u64 = 0;
+
+    for (i, strategy) in strategies.iter_mut().enumerate() {
+        // only whitelisted strategies of this asset, nothing for a strategy being wound down
+        let registry = read_strategy_registry(&registry_accounts[i], &strategy.strategy_state)?;
+        if registry.asset_type != asset_type {
+            return Err(ProtocolControllerError::ParameterValidationFailed.into());
+        }
+        if registry.status == STRATEGY_STATUS_WINDING_DOWN && strategy.target_weight_bps > 0 {
+            msg!("strategy {} is winding down", i);
+            return Err(ProtocolControllerError::CoordinationOperationMismatch.into());
+        }
+        strategy.strategy_program = registry.strategy_program;
+        total_weight += strategy.target_weight_bps as u64;
+    }
+
+    if total_weight != 10_000 {
+        msg!("strategy weights add up to {} bps, expected 10000", total_weight);
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let mut table_data = table_account.try_borrow_mut_data()?;
+    let table = StrategyAllocationTable::load_mut(&mut table_data)?;
+
+    // dropped strategies have to be empty
+    let mut dropped = 0usize;
+    for existing in table.strategies[..table.strategy_count as usize].iter() {
+        if strategies
+            .iter()
+            .any(|strategy| strategy.strategy_state == existing.strategy_state)
+        {
+            continue;
+        }
+
+        let registry_account = registry_accounts.get(count + dropped).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let registry = read_strategy_registry(registry_account, &existing.strategy_state)?;
+        if registry.deployed_amount > 0 {
+            msg!("strategy still holds {}, wind it down first", registry.deployed_amount);
+            return Err(ProtocolControllerError::ParameterValidationFailed.into());
+        }
+        dropped += 1;
+    }
+
+    table.strategies = [StrategyAllocation::zeroed(); MAX_STRATEGIES_PER_ASSET];
+    table.strategies[..count].copy_from_slice(strategies);
+    table.strategy_count = count as u8;
+    table.drift_band_bps = drift_band_bps;
+    table.asset_type = asset_type;
+    table.bump = table_bump;
+    table.is_initialized = 1;
+
+    Ok(())
+}
+
+
+// proposes strategy weights from realized APR, risk tier and cap, riskier strategies weigh less
+// the more of the supply is liquid, weights move at most max_step bps per call
+// dry run only returns the proposal, otherwise the authority applies it like update_strategy_weights
+pub fn optimize_freeze_based_yield_allocation(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("optimizing freeze-based yield allocation");
+
+    // asset (1), max step bps (2), dry run (1)
+    if data.len() < 4 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    let asset_type = data[0];
+    let max_step_bps = u16::from_le_bytes(data[1..3].try_into().unwrap());
+    let dry_run = data[3] != 0;
+    if max_step_bps == 0 || max_step_bps > 10_000 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol parameters, 1 = authority (signer unless dry run), 2 = strategy allocation table,
+    // 3 = freeze supply, 4 = protocol controller, 5 = minting state,
+    // then strategy registries in table order, then strategy yield records in table order
+    if accounts.len() < 6 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let parameters = read_protocol_parameters(&accounts[0])?;
+    if !dry_run {
+        parameters.require_authority(&accounts[1])?;
+    }
+
+    let table_account = &accounts[2];
+    StrategyAllocationTable::verify_address(table_account, asset_type)?;
+    if !table_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let table = {
+        let table_data = table_account.try_borrow_data()?;
+        *StrategyAllocationTable::load(&table_data)?
+    };
+    if table.is_initialized == 0 || table.strategy_count == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    let count = table.strategy_count as usize;
+    if accounts.len() < 6 + 2 * count {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let outstanding_supply = {
+        let controller_data = accounts[4].try_borrow_data()?;
+        if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+            &controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        controller_state.total_usdtx_minted.saturating_sub(controller_state.total_usdtx_burned)
+    };
+
+    // no freeze yet means no frozen supply
+    let frozen_supply = if accounts[3].is_owned_by(&crate::ID) {
+        FreezeSupply::verify_address(&accounts[3])?;
+        let supply_data = accounts[3].try_borrow_data()?;
+        FreezeSupply::load(&supply_data)?.total_frozen_supply.min(outstanding_supply)
+    } else {
+        0
+    };
+    let liquid_share_bps = if outstanding_supply == 0 {
+        10_000
+    } else {
+        10_000 - ((frozen_supply as u128) * 10_000u128 / outstanding_supply as u128) as u64
+    };
+
+    // what the strategies hold once the asset's collateral above the liquidity buffer is deployed
+    MintingState::verify_address(&accounts[5])?;
+    let deployable_collateral = {
+        let minting_data = accounts[5].try_borrow_data()?;
+        let collateral = MintingState::load(&minting_data)?.collateral_of(asset_type);
+        let buffer_bps = if asset_type == ASSET_SOL {
+            parameters.sol_liquidity_buffer_bps
+        } else {
+            parameters.usdc_liquidity_buffer_bps
+        };
+        collateral - liquidity_buffer_target(collateral, buffer_bps)
+    };
+
+    let mut registries = [StrategyRegistry::zeroed(); MAX_STRATEGIES_PER_ASSET];
+    let mut scores = [0u64; MAX_STRATEGIES_PER_ASSET];
+
+    for i in 0..count {
+        let strategy = &table.strategies[i];
+        registries[i] = read_strategy_registry(&accounts[6 + i], &strategy.strategy_state)?;
+
+        // created at whitelisting, zero APR until the first harvest
+        let record_account = &accounts[6 + count + i];
+        StrategyYieldRecord::verify_address(record_account, &strategy.strategy_state)?;
+        let apr_bps = {
+            let record_data = record_account.try_borrow_data()?;
+            StrategyYieldRecord::load(&record_data)?.last_apr_bps
+        };
+
+        if registries[i].status == STRATEGY_STATUS_ACTIVE {
+            scores[i] = risk_adjusted_score(apr_bps, registries[i].risk_tier, liquid_share_bps);
+        }
+        msg!("strategy {}: APR {} bps, tier {}, score {}", i, apr_bps, registries[i].risk_tier, scores[i]);
+    }
+
+    let best = (0..count)
+        .filter(|&i| scores[i] > 0)
+        .max_by_key(|&i| scores[i])
+        .ok_or_else(|| {
+            msg!("no active strategy to allocate to");
+            ProgramError::from(ProtocolControllerError::CoordinationOperationMismatch)
+        })?;
+
+    // caps as a share of the deployable collateral, binding from the first deployment on
+    let mut cap_weights = [10_000u64; MAX_STRATEGIES_PER_ASSET];
+    if deployable_collateral > 0 {
+        for i in 0..count {
+            cap_weights[i] = ((registries[i].max_allocation as u128) * 10_000u128 / deployable_collateral as u128)
+                .min(10_000) as u64;
+        }
+    }
+
+    let mut current = [0u16; MAX_STRATEGIES_PER_ASSET];
+    for i in 0..count {
+        current[i] = table.strategies[i].target_weight_bps;
+    }
+
+    let mut target = [0u16; MAX_STRATEGIES_PER_ASSET];
+    score_weights(&scores[..count], &cap_weights[..count], &mut target[..count]);
+
+    let mut proposed = [0u16; MAX_STRATEGIES_PER_ASSET];
+    step_weights(&current[..count], &target[..count], max_step_bps, &mut proposed[..count]);
+
+    // a strategy being wound down cannot keep any weight, it goes to the best strategy at once
+    for i in 0..count {
+        if registries[i].status == STRATEGY_STATUS_WINDING_DOWN && proposed[i] > 0 {
+            proposed[best] += proposed[i];
+            proposed[i] = 0;
+        }
+    }
+
+    // count (1), then per strategy: proposed weight (2), target weight (2)
+    let mut return_data = [0u8; 1 + 4 * MAX_STRATEGIES_PER_ASSET];
+    return_data[0] = count as u8;
+    for i in 0..count {
+        let offset = 1 + 4 * i;
+        return_data[offset..offset + 2].copy_from_slice(&proposed[i].to_le_bytes());
+        return_data[offset + 2..offset + 4].copy_from_slice(&target[i].to_le_bytes());
+        msg!("strategy {}: {} -> {} bps (target {})", i, current[i], proposed[i], target[i]);
+    }
+    pinocchio::program::set_return_data(&return_data[..1 + 4 * count]);
+
+    if dry_run {
+        msg!("dry run, weights not written");
+        return Ok(());
+    }
+
+    let mut strategies = table.strategies;
+    for i in 0..count {
+        strategies[i].target_weight_bps = proposed[i];
+    }
+    write_strategy_weights(
+        table_account,
+        table.bump,
+        asset_type,
+        table.drift_band_bps,
+        &mut strategies[..count],
+        &accounts[6..6 + count],
+    )?;
+
+    msg!("{} strategy weights applied for asset {}, liquid share {} bps", count, asset_type, liquid_share_bps);
+
+    Ok(())
+}
+
+
+// whitelist a strategy with its cap and risk tier, or update both for a whitelisted one
+pub fn whitelist_strategy(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("whitelisting strategy");
+
+    // strategy state (32), asset (1), risk tier (1), max allocation (8)
+    if data.len() < 42 {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    let strategy_state: Pubkey = data[0..32].try_into().unwrap();
+    let asset_type = data[32];
+    let risk_tier = data[33];
+    let max_allocation = u64::from_le_bytes(data[34..42].try_into().unwrap());
+
+    if risk_tier == 0 || risk_tier > MAX_RISK_TIER {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let expected_program = match asset_type {
+        ASSET_SOL => sol_strategy_manager::ID,
+        ASSET_USDC => usdc_strategy_manager::ID,
+        _ => return Err(ProtocolControllerError::ParameterValidationFailed.into()),
+    };
+
+    // 0 = protocol parameters, 1 = authority (payer), 2 = strategy registry,
+    // 3 = strategy manager program, 4 = system program, 5 = strategy yield record
+    if accounts.len() < 6 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let authority_account = &accounts[1];
+    read_protocol_parameters(&accounts[0])?.require_authority(authority_account)?;
+
+    if accounts[3].key() != &expected_program {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let registry_account = &accounts[2];
+    let registry_bump = StrategyRegistry::verify_address(registry_account, &strategy_state)?;
+    if registry_account.lamports() == 0 {
+        let bump_seed = [registry_bump];
+        crate::program_account::create_program_account(
+            authority_account,
+            registry_account,
+            StrategyRegistry::LEN,
+            &[
+                Seed::from(STRATEGY_REGISTRY_SEED),
+                Seed::from(strategy_state.as_ref()),
+                Seed::from(&bump_seed),
+            ],
+        )?;
+    }
+    if !registry_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // created empty, the first harvest or collection initializes it
+    let record_account = &accounts[5];
+    let (expected_record, record_bump) = Pubkey::find_program_address(
+        &[STRATEGY_YIELD_RECORD_SEED, strategy_state.as_ref()],
+        &crate::ID,
+    );
+    if record_account.key() != &expected_record {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if record_account.lamports() == 0 {
+        let bump_seed = [record_bump];
+        crate::program_account::create_program_account(
+            authority_account,
+            record_account,
+            StrategyYieldRecord::LEN,
+            &[
+                Seed::from(STRATEGY_YIELD_RECORD_SEED),
+                Seed::from(strategy_state.as_ref()),
+                Seed::from(&bump_seed),
+            ],
+        )?;
+    } else if !record_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut registry_data = registry_account.try_borrow_mut_data()?;
+    let registry = StrategyRegistry::load_mut(&mut registry_data)?;
+
+    if registry.is_initialized == 0 {
+        registry.strategy_state = strategy_state;
+        registry.strategy_program = expected_program;
+        registry.asset_type = asset_type;
+        registry.status = STRATEGY_STATUS_ACTIVE;
+        registry.whitelisted_at = Clock::get()?.unix_timestamp;
+        registry.bump = registry_bump;
+        registry.is_initialized = 1;
+    } else if registry.asset_type != asset_type {
+        return Err(ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // a lower cap stops new deposits, it does not pull funds out
+    registry.risk_tier = risk_tier;
+    registry.max_allocation = max_allocation;
+
+    msg!("strategy whitelisted, asset {}, risk tier {}, cap {}", asset_type, risk_tier, max_allocation);
+
+    Ok(())
+}
+
+
+// pause a strategy: soft pause (no new deposits), wind-down in steps, forced unwind, or resume
+// wind-down calls repeat until nothing is deployed in the strategy any more
+pub fn pause_strategy(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("updating strategy status");
+
+    // mode (1), wind-down only: max amount per step (8), 0 = everything left
     if data.len() < 1 {
         return Err(ProtocolControllerError::ParameterValidationFailed.into());
     }
-    
-    let rebalance_trigger = data[0];
-    
-    match rebalance_trigger {
-// work in progress
+    let pause_mode = data[0];
+
+    // 0 = protocol parameters, 1 = authority, 2 = strategy registry,
+    // wind-down and forced unwind: 3 = protocol controller, 4 = strategy manager program,
+    // 5 = strategy state, 6 = recall vault (minting-state escrow of the asset), 7 = token program,
+    // 8 = minting state
+    if accounts.len() < 3 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    read_protocol_parameters(&accounts[0])?.require_authority(&accounts[1])?;
+
+    let registry_account = &accounts[2];
+    if !registry_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut registry_data = registry_account.try_borrow_mut_data()?;
+    let registry = StrategyRegistry::load_mut(&mut registry_data)?;
+    StrategyRegistry::verify_address(registry_account, &registry.strategy_state)?;
+    if registry.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+
+    match pause_mode {
+        PAUSE_MODE_RESUME => {
+            // a wind-down has to finish before the strategy takes deposits again
+            if registry.status == STRATEGY_STATUS_WINDING_DOWN && !registry.is_wound_down() {
+                msg!("wind-down in progress, {} still deployed", registry.deployed_amount);
+                return Err(ProtocolControllerError::CoordinationOperationMismatch.into());
+            }
+            msg!("strategy status {} -> {}", registry.status, STRATEGY_STATUS_ACTIVE);
+            registry.status = STRATEGY_STATUS_ACTIVE;
+            return Ok(());
+        },
+        PAUSE_MODE_SOFT => {
+            if registry.status == STRATEGY_STATUS_WINDING_DOWN {
+                return Err(ProtocolControllerError::CoordinationOperationMismatch.into());
+            }
+            msg!("strategy status {} -> {}", registry.status, STRATEGY_STATUS_PAUSED);
+            registry.status = STRATEGY_STATUS_PAUSED;
+            return Ok(());
+        },
+        PAUSE_MODE_WIND_DOWN | PAUSE_MODE_FORCED_UNWIND => {},
+        _ => return Err(ProtocolControllerError::ParameterValidationFailed.into()),
+    }
+
+    if accounts.len() < 9 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let protocol_controller_account = &accounts[3];
+    let (protocol_controller, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+    if protocol_controller_account.key() != &protocol_controller {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    // forced unwind is an emergency action, next to emergency_recall_all_external_assets
+    if pause_mode == PAUSE_MODE_FORCED_UNWIND {
+        let controller_data = protocol_controller_account.try_borrow_data()?;
+        if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+            &controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        if !controller_state.emergency_mode {
+            msg!("forced unwind needs emergency mode");
+            return Err(ProtocolControllerError::CoordinationOperationMismatch.into());
+        }
+    }
+
+    if accounts[4].key() != &registry.strategy_program || accounts[5].key() != &registry.strategy_state {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // recalled funds go back to the escrow the collateral was deployed from
+    MintingState::verify_address(&accounts[8])?;
+    {
+        let minting_data = accounts[8].try_borrow_data()?;
+        let minting = MintingState::load(&minting_data)?;
+        let escrow = if registry.asset_type == ASSET_SOL { &minting.sol_escrow } else { &minting.usdc_psm_vault };
+        if accounts[6].key() != escrow {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+    if accounts[7].key() != &pinocchio_token::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let current_time = Clock::get()?.unix_timestamp;
+
+    if registry.status != STRATEGY_STATUS_WINDING_DOWN {
+        msg!("starting wind-down, {} deployed", registry.deployed_amount);
+        registry.status = STRATEGY_STATUS_WINDING_DOWN;
+        registry.wind_down_started_at = current_time;
+        registry.wind_down_initial_amount = registry.deployed_amount;
+        registry.wind_down_recovered = 0;
+    }
+
+    let max_step = if pause_mode == PAUSE_MODE_WIND_DOWN && data.len() >= 9 {
+        u64::from_le_bytes(data[1..9].try_into().unwrap())
+    } else {
+        0
+    };
+    let step_amount = match max_step {
+        0 => registry.deployed_amount,
+        max_step => registry.deployed_amount.min(max_step),
+    };
+
+    if step_amount > 0 {
+        let recall_vault = &accounts[6];
+        let vault_balance_before = token_account_amount(recall_vault)?;
+        invoke_strategy_transfer(
+            STRATEGY_MANAGER_WITHDRAW_IX,
+            &accounts[4],
+            protocol_controller_account,
+            &accounts[5],
+            recall_vault,
+            &accounts[7],
+            step_amount,
+            protocol_controller_bump,
+        )?;
+        let recovered = token_account_amount(recall_vault)?.saturating_sub(vault_balance_before);
+        let deployed_before = registry.deployed_amount;
+
+        registry.wind_down_recovered = registry.wind_down_recovered.saturating_add(recovered);
+
+        if pause_mode == PAUSE_MODE_FORCED_UNWIND {
+            // what did not come back is written off, a loss to socialize
+            let shortfall = step_amount.saturating_sub(recovered);
+            registry.unrecovered_amount = registry.unrecovered_amount.saturating_add(shortfall);
+            registry.deployed_amount = registry.deployed_amount.saturating_sub(step_amount);
+            if shortfall > 0 {
+                msg!("forced unwind shortfall: {}", shortfall);
+            }
+        } else {
+            registry.deployed_amount = registry.deployed_amount.saturating_sub(recovered);
+        }
+
+        // the bucket counts deployed and liquid collateral, it moves by what came back beyond
+        // the principal taken off the books: a gain adds, a written-off shortfall subtracts
+        let principal_released = deployed_before - registry.deployed_amount;
+        let mut minting_data = accounts[8].try_borrow_mut_data()?;
+        let minting = MintingState::load_mut(&mut minting_data)?;
+        let collateral = if registry.asset_type == ASSET_SOL {
+            &mut minting.sol_collateral_lamports
+        } else {
+            &mut minting.psm_usdc_collateral
+        };
+        *collateral = collateral
+            .saturating_add(recovered.saturating_sub(principal_released))
+            .saturating_sub(principal_released.saturating_sub(recovered));
+    }
+
+    msg!(
+        "wind-down progress: {} of {} recovered, {} still deployed",
+        registry.wind_down_recovered,
+        registry.wind_down_initial_amount,
+        registry.deployed_amount,
+    );
+
+    if registry.is_wound_down() {
+        msg!("strategy fully wound down");
+    }
+
+    Ok(())
+}
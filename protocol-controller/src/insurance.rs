@@ -0,0 +1,413 @@
+// insurance fund
+// a slice of every harvest goes into the insurance vault, strategy losses are covered
+// first by the fund, then by yield not yet turned into Thalers, anything left pauses (type 3)
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    msg,
+    clock::Clock,
+    sysvars::Sysvar,
+    instruction::{Seed, Signer},
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::{ProgramAccount, create_program_account};
+use crate::minting::{MintingState, sol_value_usd};
+use crate::strategy_registry::StrategyRegistry;
+use crate::thaler_distribution::{ThalerDistribution, THALER_DISTRIBUTION_SEED};
+use crate::yield_accounting::{token_account_amount, ASSET_SOL};
+
+
+pub const INSURANCE_FUND_SEED: &[u8] = b"insurance_fund";
+pub const LOSS_EVENT_CAPACITY: usize = 16;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct LossEvent {
+    pub timestamp: i64,
+    pub slot: u64,
+    pub strategy_program: Pubkey,
+    // USDC, 6 decimals
+    pub loss_amount: u64,
+    pub covered_by_fund: u64,
+    pub covered_by_pending_yield: u64,
+    pub uncovered: u64,
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct InsuranceFund {
+    // USDC token account owned by the protocol controller PDA
+    pub vault: Pubkey,
+    pub total_contributed: u64,
+    pub total_drawn: u64,
+    pub total_uncovered: u64,
+    pub loss_event_count: u64,
+    pub event_head: u32,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 2],
+    pub loss_events: [LossEvent; LOSS_EVENT_CAPACITY],
+}
+
+impl ProgramAccount for InsuranceFund {}
+
+impl InsuranceFund {
+    pub fn verify_address(fund_account: &AccountInfo) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[INSURANCE_FUND_SEED],
+            &crate::ID,
+        );
+        if fund_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(bump)
+    }
+
+    pub fn verify_vault(&self, vault_account: &AccountInfo) -> Result<(), ProgramError> {
+        if self.is_initialized == 0 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if vault_account.key() != &self.vault {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(())
+    }
+
+    // the oldest events are overwritten, loss_event_count keeps the full history length
+    pub fn record_loss(&mut self, event: LossEvent) {
+        self.loss_events[self.event_head as usize] = event;
+        self.event_head = ((self.event_head as usize + 1) % LOSS_EVENT_CAPACITY) as u32;
+        self.loss_event_count = self.loss_event_count.saturating_add(1);
+        self.total_drawn = self.total_drawn.saturating_add(event.covered_by_fund);
+        self.total_uncovered = self.total_uncovered.saturating_add(event.uncovered);
+    }
+}
+
+
+// token transfer out of a controller-owned account, signed by the protocol controller PDA
+pub(crate) fn invoke_controller_transfer(
+    from: &AccountInfo,
+    to: &AccountInfo,
+    protocol_controller: &AccountInfo,
+    amount: u64,
+    protocol_controller_bump: u8,
+) -> Result<(), ProgramError> {
+    let bump_seed = [protocol_controller_bump];
+    let seeds = [
+        Seed::from(crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED),
+        Seed::from(&bump_seed),
+    ];
+
+    pinocchio_token::instructions::Transfer {
+        from,
+        to,
+        authority: protocol_controller,
+        amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])
+}
+
+
+// moves the insurance slice of a harvest from the yields vault into the insurance vault
+// returns the amount contributed
+pub fn contribute_to_insurance_fund(
+    fund_account: &AccountInfo,
+    insurance_vault: &AccountInfo,
+    yields_vault: &AccountInfo,
+    protocol_controller: &AccountInfo,
+    harvested_yield: u64,
+    insurance_fund_bps: u64,
+    protocol_controller_bump: u8,
+) -> Result<u64, ProgramError> {
+    InsuranceFund::verify_address(fund_account)?;
+    if !fund_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut fund_data = fund_account.try_borrow_mut_data()?;
+    let fund = InsuranceFund::load_mut(&mut fund_data)?;
+    fund.verify_vault(insurance_vault)?;
+
+    let contribution = ((harvested_yield as u128)
+        .saturating_mul(insurance_fund_bps as u128)
+        / 10_000u128) as u64;
+    if contribution == 0 {
+        return Ok(0);
+    }
+
+    invoke_controller_transfer(
+        yields_vault,
+        insurance_vault,
+        protocol_controller,
+        contribution,
+        protocol_controller_bump,
+    )?;
+
+    fund.total_contributed = fund.total_contributed.saturating_add(contribution);
+
+    Ok(contribution)
+}
+
+
+// create the insurance fund and pin its vault
+pub fn initialize_insurance_fund(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("initializing insurance fund");
+
+    // 0 = protocol parameters, 1 = authority (payer), 2 = insurance fund,
+    // 3 = insurance vault, 4 = system program
+    if accounts.len() < 5 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let authority_account = &accounts[1];
+    crate::parameters::read_protocol_parameters(&accounts[0])?.require_authority(authority_account)?;
+
+    let fund_account = &accounts[2];
+    let fund_bump = InsuranceFund::verify_address(fund_account)?;
+    if fund_account.lamports() > 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    // the controller PDA has to own the vault, it signs every draw
+    let (protocol_controller, _) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+    {
+        let vault = pinocchio_token::state::TokenAccount::from_account_info(&accounts[3])?;
+        if vault.owner() != &protocol_controller {
+            return Err(ProgramError::IllegalOwner);
+        }
+    }
+
+    let bump_seed = [fund_bump];
+    create_program_account(
+        authority_account,
+        fund_account,
+        InsuranceFund::LEN,
+        &[Seed::from(INSURANCE_FUND_SEED), Seed::from(&bump_seed)],
+    )?;
+
+    let mut fund_data = fund_account.try_borrow_mut_data()?;
+    let fund = InsuranceFund::load_mut(&mut fund_data)?;
+    fund.vault = *accounts[3].key();
+    fund.bump = fund_bump;
+    fund.is_initialized = 1;
+
+    Ok(())
+}
+
+
+// cover a strategy loss: insurance fund first, then yield still waiting for Thalers
+// the loss is what a forced unwind could not recover, deployed principal the strategy manager
+// did not return (StrategyRegistry::unrecovered_amount), paid into the minting-state USDC escrow
+// an uncovered remainder is recorded and pauses the protocol as a strategy failure
+pub fn socialize_strategy_loss(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("socializing strategy loss");
+
+    // 0 = protocol controller, 1 = protocol parameters, 2 = authority, 3 = insurance fund,
+    // 4 = insurance vault, 5 = yields vault, 6 = thaler distribution,
+    // 7 = minting-state USDC escrow (PSM vault), 8 = token program, 9 = strategy program,
+    // 10 = strategy registry, 11 = strategy state, 12 = minting state,
+    // SOL strategies: 13 = doppler oracle, 14 = oracle guard, 15 = primary source feed,
+    // 16 = secondary source feed, 17 = doppler price history,
+    // an uncovered loss also needs the emergency pause accounts after these, protocol controller first
+    if accounts.len() < 13 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let parameters = crate::parameters::read_protocol_parameters(&accounts[1])?;
+    parameters.require_authority(&accounts[2])?;
+
+    let protocol_controller_account = &accounts[0];
+    let (protocol_controller, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+    if protocol_controller_account.key() != &protocol_controller {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if accounts[8].key() != &pinocchio_token::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    // registry of the failed strategy, its write-off is the loss
+    let registry_account = &accounts[10];
+    StrategyRegistry::verify_address(registry_account, accounts[11].key())?;
+    if !registry_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut registry_data = registry_account.try_borrow_mut_data()?;
+    let registry = StrategyRegistry::load_mut(&mut registry_data)?;
+    if registry.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if accounts[9].key() != &registry.strategy_program {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if registry.unrecovered_amount == 0 {
+        msg!("strategy has no unrecovered principal");
+        return Err(crate::error::ProtocolControllerError::CoordinationOperationMismatch.into());
+    }
+
+    let pause_accounts_start = if registry.asset_type == ASSET_SOL { 18 } else { 13 };
+
+    // registry amounts are in the asset's native units, the vaults hold USDC
+    let loss_amount = if registry.asset_type == ASSET_SOL {
+        if accounts.len() < 18 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        let (sol_price_usd, _) = crate::oracle_guard::resolve_fallback_price(
+            &accounts[14..18],
+            accounts[13].key(),
+            &parameters,
+            &Clock::get()?,
+        )?;
+        sol_value_usd(registry.unrecovered_amount, sol_price_usd)
+    } else {
+        registry.unrecovered_amount
+    };
+    if loss_amount == 0 {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // the loss is paid into the USDC escrow backing the minted supply
+    {
+        MintingState::verify_address(&accounts[12])?;
+        let minting_data = accounts[12].try_borrow_data()?;
+        let minting = MintingState::load(&minting_data)?;
+        if accounts[7].key() != &minting.usdc_psm_vault {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    // the yields vault is the controller's, same asset as the escrow
+    {
+        let yields_vault = pinocchio_token::state::TokenAccount::from_account_info(&accounts[5])?;
+        let escrow = pinocchio_token::state::TokenAccount::from_account_info(&accounts[7])?;
+        if yields_vault.owner() != &protocol_controller || escrow.mint() != yields_vault.mint() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    // before the first distribution there is no pending Thaler yield to draw on
+    let distribution_exists = if accounts[6].is_owned_by(&crate::ID) {
+        ThalerDistribution::verify_address(&accounts[6])?;
+        true
+    } else {
+        if accounts[6].key() != &Pubkey::find_program_address(&[THALER_DISTRIBUTION_SEED], &crate::ID).0 {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        false
+    };
+
+    InsuranceFund::verify_address(&accounts[3])?;
+    if !accounts[3].is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut fund_data = accounts[3].try_borrow_mut_data()?;
+    let fund = InsuranceFund::load_mut(&mut fund_data)?;
+    fund.verify_vault(&accounts[4])?;
+
+    // 1. insurance fund
+    let covered_by_fund = loss_amount.min(token_account_amount(&accounts[4])?);
+    if covered_by_fund > 0 {
+        invoke_controller_transfer(
+            &accounts[4],
+            &accounts[7],
+            protocol_controller_account,
+            covered_by_fund,
+            protocol_controller_bump,
+        )?;
+    }
+
+    // 2. pending Thaler distribution: harvested yield not distributed yet, then the carry-forward
+    let mut covered_by_pending_yield = 0u64;
+    let remaining = loss_amount - covered_by_fund;
+    if remaining > 0 && distribution_exists {
+        let mut distribution_data = accounts[6].try_borrow_mut_data()?;
+        let distribution = ThalerDistribution::load_mut(&mut distribution_data)?;
+
+        let undistributed_yield = {
+            let controller_data = protocol_controller_account.try_borrow_data()?;
+            if controller_data.len() < std::mem::size_of::<crate::state::ProtocolController>() {
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+                &controller_data[..std::mem::size_of::<crate::state::ProtocolController>()],
+            );
+            controller_state.total_yield_harvested
+                .saturating_sub(distribution.total_yield_distributed)
+        };
+
+        let from_undistributed = remaining.min(undistributed_yield);
+        let from_carry_forward = (remaining - from_undistributed).min(distribution.carry_forward_usdc);
+
+        // never more than the yields vault actually holds
+        covered_by_pending_yield = from_undistributed
+            .saturating_add(from_carry_forward)
+            .min(token_account_amount(&accounts[5])?);
+
+        if covered_by_pending_yield > 0 {
+            invoke_controller_transfer(
+                &accounts[5],
+                &accounts[7],
+                protocol_controller_account,
+                covered_by_pending_yield,
+                protocol_controller_bump,
+            )?;
+
+            let taken_from_undistributed = covered_by_pending_yield.min(from_undistributed);
+            distribution.total_yield_distributed = distribution.total_yield_distributed
+                .saturating_add(taken_from_undistributed);
+            distribution.carry_forward_usdc = distribution.carry_forward_usdc
+                .saturating_sub(covered_by_pending_yield - taken_from_undistributed);
+        }
+    }
+
+    let uncovered = loss_amount - covered_by_fund - covered_by_pending_yield;
+    let clock = Clock::get()?;
+
+    fund.record_loss(LossEvent {
+        timestamp: clock.unix_timestamp,
+        slot: clock.slot,
+        strategy_program: *accounts[9].key(),
+        loss_amount,
+        covered_by_fund,
+        covered_by_pending_yield,
+        uncovered,
+    });
+    // socialized once, the loss event keeps the amount
+    registry.unrecovered_amount = 0;
+
+    msg!("loss: ${}", loss_amount / 1_000_000);
+    msg!("covered by insurance fund: ${}", covered_by_fund / 1_000_000);
+    msg!("covered by pending Thaler yield: ${}", covered_by_pending_yield / 1_000_000);
+
+    if uncovered == 0 {
+        return Ok(());
+    }
+
+    msg!("uncovered loss: ${}", uncovered / 1_000_000);
+    drop(fund_data);
+    drop(registry_data);
+
+    // strategy failure cascade, the loss event stays recorded
+    let pause_accounts = accounts.get(pause_accounts_start..).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if pause_accounts.first().map(|account| account.key()) != Some(&protocol_controller) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    crate::instructions::emergency_protocol_pause(program_id, pause_accounts, &3u32.to_le_bytes())
+}
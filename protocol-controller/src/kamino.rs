@@ -0,0 +1,173 @@
+// Kamino lending position of the USDC strategy
+// interest shows up as a rising cToken exchange rate, only the cTokens worth the interest
+// accrued since the last collection are redeemed, principal cTokens stay deposited
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use crate::strategy_allocation::invoke_strategy_collect;
+
+
+pub const KAMINO_LENDING_PROGRAM_ID: Pubkey = pinocchio_pubkey::pubkey!("KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD");
+
+// strategy manager instruction redeeming cTokens into the yields vault
+pub const STRATEGY_MANAGER_REDEEM_KAMINO_INTEREST_IX: u8 = 6;
+
+// exchange rates are liquidity per cToken scaled by 1e18
+pub const RATE_WAD: u128 = 1_000_000_000_000_000_000;
+
+// Kamino keeps fractional amounts as 68.60 fixed point
+pub const KAMINO_FRACTION_BITS: u32 = 60;
+
+// Reserve: discriminator (8), version (8), last update (slot u64, stale u8, ..),
+// lending market, farms, then the liquidity and collateral sections
+pub const KAMINO_RESERVE_LAST_UPDATE_SLOT_OFFSET: usize = 16;
+pub const KAMINO_RESERVE_LAST_UPDATE_STALE_OFFSET: usize = 24;
+pub const KAMINO_RESERVE_LIQUIDITY_MINT_OFFSET: usize = 128;
+pub const KAMINO_RESERVE_AVAILABLE_AMOUNT_OFFSET: usize = 224;
+pub const KAMINO_RESERVE_BORROWED_AMOUNT_SF_OFFSET: usize = 232;
+pub const KAMINO_RESERVE_PROTOCOL_FEES_SF_OFFSET: usize = 344;
+pub const KAMINO_RESERVE_REFERRER_FEES_SF_OFFSET: usize = 360;
+pub const KAMINO_RESERVE_PENDING_REFERRER_FEES_SF_OFFSET: usize = 376;
+pub const KAMINO_RESERVE_COLLATERAL_MINT_OFFSET: usize = 2560;
+pub const KAMINO_RESERVE_COLLATERAL_SUPPLY_OFFSET: usize = 2592;
+
+// redeeming rounds on Kamino's side as well
+pub const KAMINO_REDEEM_TOLERANCE: u64 = 2;
+
+
+// what the collection needs out of a reserve
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KaminoReserveView {
+    pub liquidity_mint: Pubkey,
+    pub collateral_mint: Pubkey,
+    pub last_update_slot: u64,
+    pub is_stale: bool,
+    // liquidity per cToken, RATE_WAD scaled
+    pub exchange_rate: u128,
+}
+
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    data.get(offset..offset + 8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+fn read_u128(data: &[u8], offset: usize) -> Result<u128, ProgramError> {
+    data.get(offset..offset + 16)
+        .map(|bytes| u128::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Result<Pubkey, ProgramError> {
+    data.get(offset..offset + 32)
+        .map(|bytes| bytes.try_into().unwrap())
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+
+// total liquidity (available + borrowed - fees) over the cToken supply
+pub fn kamino_exchange_rate(reserve_data: &[u8]) -> Result<u128, ProgramError> {
+    let available_sf = (read_u64(reserve_data, KAMINO_RESERVE_AVAILABLE_AMOUNT_OFFSET)? as u128) << KAMINO_FRACTION_BITS;
+    let total_liquidity_sf = available_sf
+        .checked_add(read_u128(reserve_data, KAMINO_RESERVE_BORROWED_AMOUNT_SF_OFFSET)?)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .saturating_sub(read_u128(reserve_data, KAMINO_RESERVE_PROTOCOL_FEES_SF_OFFSET)?)
+        .saturating_sub(read_u128(reserve_data, KAMINO_RESERVE_REFERRER_FEES_SF_OFFSET)?)
+        .saturating_sub(read_u128(reserve_data, KAMINO_RESERVE_PENDING_REFERRER_FEES_SF_OFFSET)?);
+    let total_liquidity = total_liquidity_sf >> KAMINO_FRACTION_BITS;
+
+    let collateral_supply = read_u64(reserve_data, KAMINO_RESERVE_COLLATERAL_SUPPLY_OFFSET)?;
+    if collateral_supply == 0 {
+        // an empty reserve mints cTokens 1:1
+        return Ok(RATE_WAD);
+    }
+
+    total_liquidity
+        .checked_mul(RATE_WAD)
+        .map(|scaled| scaled / collateral_supply as u128)
+        .ok_or(ProgramError::ArithmeticOverflow)
+}
+
+pub fn read_kamino_reserve(reserve_data: &[u8]) -> Result<KaminoReserveView, ProgramError> {
+    Ok(KaminoReserveView {
+        liquidity_mint: read_pubkey(reserve_data, KAMINO_RESERVE_LIQUIDITY_MINT_OFFSET)?,
+        collateral_mint: read_pubkey(reserve_data, KAMINO_RESERVE_COLLATERAL_MINT_OFFSET)?,
+        last_update_slot: read_u64(reserve_data, KAMINO_RESERVE_LAST_UPDATE_SLOT_OFFSET)?,
+        is_stale: *reserve_data
+            .get(KAMINO_RESERVE_LAST_UPDATE_STALE_OFFSET)
+            .ok_or(ProgramError::InvalidAccountData)? != 0,
+        exchange_rate: kamino_exchange_rate(reserve_data)?,
+    })
+}
+
+
+// interest earned by cTokens held since the last snapshot, in liquidity units
+// cTokens added since then did not earn the whole rate move, so only the smaller balance counts
+pub fn accrued_interest(
+    ctoken_balance: u64,
+    ctoken_balance_at_snapshot: u64,
+    rate_snapshot: u128,
+    rate_now: u128,
+) -> u64 {
+    if rate_now <= rate_snapshot {
+        return 0;
+    }
+    let earning_ctokens = ctoken_balance.min(ctoken_balance_at_snapshot) as u128;
+    (earning_ctokens.saturating_mul(rate_now - rate_snapshot) / RATE_WAD).min(u64::MAX as u128) as u64
+}
+
+// cTokens to redeem for an interest amount at the current rate, and the liquidity they return
+pub fn interest_redemption(interest: u64, rate_now: u128) -> (u64, u64) {
+    if rate_now == 0 {
+        return (0, 0);
+    }
+    let ctokens = ((interest as u128).saturating_mul(RATE_WAD) / rate_now).min(u64::MAX as u128) as u64;
+    let liquidity = ((ctokens as u128).saturating_mul(rate_now) / RATE_WAD) as u64;
+    (ctokens, liquidity)
+}
+
+// the yields vault has to grow by the expected liquidity, give or take Kamino's rounding
+pub fn verify_redeemed_delta(balance_before: u64, balance_after: u64, expected: u64) -> Result<u64, ProgramError> {
+    if balance_after < balance_before {
+        return Err(crate::error::ProtocolControllerError::YieldHarvestingFailed.into());
+    }
+    let delta = balance_after - balance_before;
+    if delta.abs_diff(expected) > KAMINO_REDEEM_TOLERANCE {
+        return Err(crate::error::ProtocolControllerError::YieldHarvestingFailed.into());
+    }
+    Ok(delta)
+}
+
+
+// asks the strategy manager to redeem cTokens from its Kamino deposit into the yields vault
+// manager-specific Kamino accounts (lending market, authority, vaults) are passed through
+pub fn invoke_kamino_interest_redeem(
+    strategy_program: &AccountInfo,
+    protocol_controller: &AccountInfo,
+    strategy_state: &AccountInfo,
+    yields_vault: &AccountInfo,
+    token_program: &AccountInfo,
+    reserve: &AccountInfo,
+    ctoken_account: &AccountInfo,
+    passthrough_accounts: &[AccountInfo],
+    ctoken_amount: u64,
+    protocol_controller_bump: u8,
+) -> Result<(), ProgramError> {
+    invoke_strategy_collect(
+        STRATEGY_MANAGER_REDEEM_KAMINO_INTEREST_IX,
+        strategy_program,
+        protocol_controller,
+        strategy_state,
+        yields_vault,
+        token_program,
+        &[reserve, ctoken_account],
+        passthrough_accounts,
+        ctoken_amount,
+        protocol_controller_bump,
+    )
+}
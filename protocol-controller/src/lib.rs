@@ -27,6 +27,27 @@ mod dynamic_fees;
 mod user_mint_pda;
 mod shared;
 mod master_authority;
+mod twap;
+mod program_account;
+mod parameters;
+mod oracle_guard;
+mod oracle_adapter;
+mod yield_accounting;
+mod twab;
+mod thaler_distribution;
+mod merkle;
+mod yield_epoch;
+mod fee_split;
+mod insurance;
+mod strategy_allocation;
+mod strategy_registry;
+mod drift;
+mod kamino;
+mod minting;
+mod redemption_queue;
+mod freeze;
+mod solvency;
+mod fee_curve;
 
 pub use instructions::*;
 pub use state::*;
@@ -38,6 +59,27 @@ pub use dynamic_fees::*;
 pub use user_mint_pda::*;
 pub use shared::*;
 pub use master_authority::*;
+pub use twap::*;
+pub use program_account::*;
+pub use parameters::*;
+pub use oracle_guard::*;
+pub use oracle_adapter::*;
+pub use yield_accounting::*;
+pub use twab::*;
+pub use thaler_distribution::*;
+pub use merkle::*;
+pub use yield_epoch::*;
+pub use fee_split::*;
+pub use insurance::*;
+pub use strategy_allocation::*;
+pub use strategy_registry::*;
+pub use drift::*;
+pub use kamino::*;
+pub use minting::*;
+pub use redemption_queue::*;
+pub use freeze::*;
+pub use solvency::*;
+pub use fee_curve::*;
 
 entrypoint!(process_instruction);
 
@@ -61,8 +103,13 @@ pub fn process_instruction(
         0 => instructions::initialize_protocol(program_id, accounts, &instruction_data[1..]),
         1 => instructions::update_protocol_parameters(program_id, accounts, &instruction_data[1..]),
         2 => instructions::emergency_protocol_pause(program_id, accounts, &instruction_data[1..]),
+        3 => minting::initialize_minting(program_id, accounts, &instruction_data[1..]),
+        4 => redemption_queue::initialize_redemption_queue(program_id, accounts, &instruction_data[1..]),
+        5 => redemption_queue::fill_redemption_tickets(program_id, accounts, &instruction_data[1..]),
+        6 => redemption_queue::claim_redemption(program_id, accounts, &instruction_data[1..]),
         
         // cross-program
+        9 => instructions::coordinate_burn_operation(program_id, accounts, &instruction_data[1..]),
         10 => instructions::coordinate_usdtx_freeze_for_yield(program_id, accounts, &instruction_data[1..]),
         11 => instructions::coordinate_usdtx_unfreeze_from_yield(program_id, accounts, &instruction_data[1..]),
         12 => instructions::sync_freeze_states_across_programs(program_id, accounts, &instruction_data[1..]),
@@ -81,8 +128,12 @@ pub fn process_instruction(
         // yields vault (USDC)
         20 => instructions::coordinate_yields_vault_collection(program_id, accounts, &instruction_data[1..]),
         21 => instructions::distribute_yields_to_thaler_freezers(program_id, accounts, &instruction_data[1..]),
-        22 => instructions::rebalance_all_strategies_with_vault(program_id, accounts, &instruction_data[1..]),
+        22 => instructions::rebalance_all_strategies(program_id, accounts, &instruction_data[1..]),
         23 => instructions::optimize_freeze_based_yield_allocation(program_id, accounts, &instruction_data[1..]),
+        24 => yield_epoch::claim_epoch_thalers(program_id, accounts, &instruction_data[1..]),
+        25 => fee_split::update_fee_recipients(program_id, accounts, &instruction_data[1..]),
+        26 => instructions::harvest_all_yield(program_id, accounts, &instruction_data[1..]),
+        27 => instructions::distribute_yield_to_thaler(program_id, accounts, &instruction_data[1..]),
         
         // oracle price aggregation (doppler)
         30 => instructions::aggregate_oracle_prices(program_id, accounts, &instruction_data[1..]),
@@ -109,6 +160,8 @@ pub fn process_instruction(
         45 => instructions::monitor_protocol_health(program_id, accounts, &instruction_data[1..]),
         46 => instructions::execute_emergency_procedures(program_id, accounts, &instruction_data[1..]),
         47 => instructions::liquidation_trigger(program_id, accounts, &instruction_data[1..]),
+        48 => insurance::initialize_insurance_fund(program_id, accounts, &instruction_data[1..]),
+        49 => insurance::socialize_strategy_loss(program_id, accounts, &instruction_data[1..]),
         
         // strategy management
         50 => instructions::whitelist_strategy(program_id, accounts, &instruction_data[1..]),
@@ -119,9 +172,14 @@ pub fn process_instruction(
         60 => master_authority::emergency_pause_all_programs(program_id, accounts, &instruction_data[1..]),
         61 => master_authority::emergency_recall_all_external_assets(program_id, accounts, &instruction_data[1..]),
         62 => master_authority::master_authority_override_program_config(program_id, accounts, &instruction_data[1..]),
-        63 => master_authority::master_authority_update_all_dynamic_fees(program_id, accounts, &instruction_data[1..]),
+        63 => fee_curve::master_authority_update_all_dynamic_fees(program_id, accounts, &instruction_data[1..]),
         64 => master_authority::master_authority_emergency_circuit_breaker(program_id, accounts, &instruction_data[1..]),
         65 => master_authority::master_authority_resume_protocol_operations(program_id, accounts, &instruction_data[1..]),
+        66 => oracle_guard::master_authority_override_oracle_price(program_id, accounts, &instruction_data[1..]),
+        
+        // oracle adapters (registered feeds, generic price update)
+        70 => oracle_adapter::update_price(program_id, accounts, &instruction_data[1..]),
+        71 => oracle_adapter::register_oracle_feed(program_id, accounts, &instruction_data[1..]),
         
         _ => {
             msg!("Unknown protocol controller instruction: {}", instruction_data[0]);
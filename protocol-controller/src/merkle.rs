@@ -0,0 +1,135 @@
+// merkle proofs for yield epoch entitlements
+// leaves and inner nodes are domain separated, pairs are hashed in sorted order
+// the tree builder is off-chain only (keepers build it from TWAB ledgers)
+
+use pinocchio::pubkey::Pubkey;
+
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+
+pub fn entitlement_leaf(epoch: u64, leaf_index: u32, user: &Pubkey, amount: u64) -> [u8; 32] {
+    solana_program::hash::hashv(&[
+        LEAF_PREFIX,
+        &epoch.to_le_bytes(),
+        &leaf_index.to_le_bytes(),
+        user.as_ref(),
+        &amount.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+
+pub fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if left <= right { (left, right) } else { (right, left) };
+    solana_program::hash::hashv(&[NODE_PREFIX, first, second]).to_bytes()
+}
+
+
+pub fn verify_proof(leaf: [u8; 32], proof: &[[u8; 32]], root: &[u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| hash_pair(&node, sibling));
+    &computed == root
+}
+
+
+// off-chain tree over (user, amount) entitlements, leaf index = position in the list
+#[cfg(not(target_os = "solana"))]
+pub struct EpochMerkleTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+#[cfg(not(target_os = "solana"))]
+impl EpochMerkleTree {
+    pub fn from_entitlements(epoch: u64, entitlements: &[(Pubkey, u64)]) -> Self {
+        let leaves: Vec<[u8; 32]> = entitlements
+            .iter()
+            .enumerate()
+            .map(|(index, (user, amount))| entitlement_leaf(epoch, index as u32, user, *amount))
+            .collect();
+
+        let mut layers = vec![leaves];
+        while layers.last().map_or(false, |layer| layer.len() > 1) {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_pair(left, right),
+                    // odd node moves up unchanged
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+
+        EpochMerkleTree { layers }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first().copied())
+            .unwrap_or([0; 32])
+    }
+
+    pub fn proof(&self, leaf_index: usize) -> Vec<[u8; 32]> {
+        let mut proof = Vec::new();
+        let mut index = leaf_index;
+
+        for layer in &self.layers[..self.layers.len().saturating_sub(1)] {
+            let sibling = index ^ 1;
+            if sibling < layer.len() {
+                proof.push(layer[sibling]);
+            }
+            index /= 2;
+        }
+
+        proof
+    }
+}
+
+
+// entitlements of the Thaler period about to be closed as an epoch, built before the epoch exists
+#[cfg(not(target_os = "solana"))]
+pub struct PeriodEntitlements {
+    pub epoch: u64,
+    pub total_amount: u64,
+    // one leaf per ledger, zero amounts included so their owners can claim past the epoch
+    pub entitlements: Vec<(Pubkey, u64)>,
+    // ledgers whose history no longer reaches the period, left out instead of failing the tree
+    pub unpriceable: Vec<Pubkey>,
+}
+
+// per-user entitlements for the last closed Thaler period from the users' TWAB ledgers
+// ledgers already settled past the period are skipped, they are owed nothing for it
+#[cfg(not(target_os = "solana"))]
+pub fn entitlements_for_period(
+    distribution: &crate::thaler_distribution::ThalerDistribution,
+    thaler_decimals: u8,
+    ledgers: &[crate::twab::TwabLedger],
+) -> Result<PeriodEntitlements, pinocchio::program_error::ProgramError> {
+    let epoch = distribution.distribution_count;
+    let total_amount = distribution
+        .period_amount(thaler_decimals)
+        .ok_or(pinocchio::program_error::ProgramError::ArithmeticOverflow)?;
+    let global_period_twab = distribution.period_end_twab.saturating_sub(distribution.period_start_twab);
+
+    let mut entitlements = Vec::with_capacity(ledgers.len());
+    let mut unpriceable = Vec::new();
+    for ledger in ledgers.iter().filter(|ledger| ledger.last_settled_distribution < epoch) {
+        match crate::twab::period_share(
+            ledger,
+            total_amount,
+            distribution.period_start_timestamp,
+            distribution.period_end_timestamp,
+            global_period_twab,
+        ) {
+            Some(amount) => entitlements.push((ledger.owner, amount)),
+            None => unpriceable.push(ledger.owner),
+        }
+    }
+
+    Ok(PeriodEntitlements { epoch, total_amount, entitlements, unpriceable })
+}
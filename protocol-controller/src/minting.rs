@@ -0,0 +1,403 @@
+// USDtx minting state
+// pins the USDtx mint and the collateral escrows, and keeps the supply minted against SOL
+// collateral and the supply minted by the USDC peg stability module (PSM) as separate buckets
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    msg,
+    instruction::{Seed, Signer},
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::{ProgramAccount, create_program_account};
+use crate::strategy_allocation::{
+    StrategyAllocationTable, STRATEGY_MANAGER_DEPOSIT_IX, STRATEGY_MANAGER_WITHDRAW_IX, invoke_strategy_transfer,
+};
+use crate::strategy_registry::StrategyRegistry;
+use crate::yield_accounting::{token_account_amount, ASSET_SOL};
+
+
+pub const MINTING_STATE_SEED: &[u8] = b"minting_state";
+
+// the only collateral the PSM takes
+pub const USDC_MINT: Pubkey = pinocchio_pubkey::pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+
+// SOL prices are USD with 6 decimals per whole SOL
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct MintingState {
+    pub usdtx_mint: Pubkey,
+    // wSOL token account owned by the protocol controller PDA
+    pub sol_escrow: Pubkey,
+    // USDC token account owned by the protocol controller PDA
+    pub usdc_psm_vault: Pubkey,
+    // SOL-backed bucket
+    pub sol_backed_supply: u64,
+    pub sol_collateral_lamports: u64,
+    pub sol_mint_count: u64,
+    // PSM bucket, USDC in and USDtx out 1:1 minus the fee
+    pub psm_backed_supply: u64,
+    pub psm_usdc_collateral: u64,
+    pub psm_fees_collected: u64,
+    pub psm_mint_count: u64,
+    // per-epoch cap usage
+    pub psm_epoch: u64,
+    pub psm_epoch_minted: u64,
+    pub psm_utilization_bps: u64,
+    // USDtx frozen for yield, fed by every freeze and unfreeze
+    pub frozen_supply: u64,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+}
+
+impl ProgramAccount for MintingState {}
+
+impl MintingState {
+    pub fn verify_address(minting_account: &AccountInfo) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[MINTING_STATE_SEED],
+            &crate::ID,
+        );
+        if minting_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if !minting_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Ok(bump)
+    }
+
+    pub fn verify_sol_accounts(
+        &self,
+        usdtx_mint: &AccountInfo,
+        sol_escrow: &AccountInfo,
+    ) -> Result<(), ProgramError> {
+        if self.is_initialized == 0 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if usdtx_mint.key() != &self.usdtx_mint || sol_escrow.key() != &self.sol_escrow {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(())
+    }
+
+    pub fn verify_psm_accounts(
+        &self,
+        usdtx_mint: &AccountInfo,
+        usdc_psm_vault: &AccountInfo,
+    ) -> Result<(), ProgramError> {
+        if self.is_initialized == 0 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if usdtx_mint.key() != &self.usdtx_mint || usdc_psm_vault.key() != &self.usdc_psm_vault {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(())
+    }
+
+    // starts a new PSM epoch when the epoch index moved, then checks the cap
+    pub fn reserve_psm_capacity(
+        &mut self,
+        amount: u64,
+        now: i64,
+        epoch_length: u64,
+        epoch_cap: u64,
+    ) -> Result<(), ProgramError> {
+        let epoch = (now.max(0) as u64) / epoch_length.max(1);
+        if epoch != self.psm_epoch {
+            self.psm_epoch = epoch;
+            self.psm_epoch_minted = 0;
+        }
+
+        let minted = self.psm_epoch_minted.saturating_add(amount);
+        if minted > epoch_cap {
+            msg!("PSM epoch cap reached: {} of {} minted", self.psm_epoch_minted, epoch_cap);
+            return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+        }
+
+        self.psm_epoch_minted = minted;
+        self.psm_utilization_bps = psm_utilization_bps(minted, epoch_cap);
+        Ok(())
+    }
+
+    // takes a redemption out of the asset's bucket, the PSM fee stays in the vault
+    pub fn record_redemption(&mut self, asset_type: u8, burned: u64, collateral_out: u64, psm_fee: u64) {
+        if asset_type == ASSET_SOL {
+            self.sol_backed_supply = self.sol_backed_supply.saturating_sub(burned);
+            self.sol_collateral_lamports = self.sol_collateral_lamports.saturating_sub(collateral_out);
+        } else {
+            self.psm_backed_supply = self.psm_backed_supply.saturating_sub(burned);
+            self.psm_usdc_collateral = self.psm_usdc_collateral.saturating_sub(burned);
+            self.psm_fees_collected = self.psm_fees_collected.saturating_add(psm_fee);
+        }
+    }
+
+    // collateral of the asset's bucket, liquid and deployed
+    pub fn collateral_of(&self, asset_type: u8) -> u64 {
+        if asset_type == ASSET_SOL {
+            self.sol_collateral_lamports
+        } else {
+            self.psm_usdc_collateral
+        }
+    }
+}
+
+
+pub fn psm_utilization_bps(epoch_minted: u64, epoch_cap: u64) -> u64 {
+    if epoch_cap == 0 {
+        return 10_000;
+    }
+    ((epoch_minted as u128) * 10_000u128 / epoch_cap as u128).min(10_000) as u64
+}
+
+// PSM fee and the USDtx out for a USDC amount, 1:1 minus the fee
+pub fn psm_mint_amounts(usdc_amount: u64, fee_bps: u64) -> (u64, u64) {
+    let fee = ((usdc_amount as u128) * (fee_bps as u128) / 10_000u128) as u64;
+    (fee, usdc_amount - fee)
+}
+
+
+// collateral that has to stay liquid in the escrow for redemptions
+pub fn liquidity_buffer_target(total_collateral: u64, buffer_bps: u64) -> u64 {
+    ((total_collateral as u128) * (buffer_bps.min(10_000) as u128) / 10_000u128) as u64
+}
+
+// what a redemption takes from the liquid buffer and what has to come out of strategies
+pub fn redemption_sources(liquid_balance: u64, amount: u64) -> (u64, u64) {
+    let from_buffer = amount.min(liquid_balance);
+    (from_buffer, amount - from_buffer)
+}
+
+
+// USD value (6 decimals) of lamports at a SOL price
+pub fn sol_value_usd(lamports: u64, sol_price_usd: u64) -> u64 {
+    ((lamports as u128).saturating_mul(sol_price_usd as u128) / LAMPORTS_PER_SOL as u128)
+        .min(u64::MAX as u128) as u64
+}
+
+// USDtx mintable against a collateral value at a collateral ratio
+pub fn usdtx_for_collateral(collateral_value_usd: u64, collateral_ratio_bps: u64) -> u64 {
+    if collateral_ratio_bps == 0 {
+        return 0;
+    }
+    ((collateral_value_usd as u128).saturating_mul(10_000u128) / collateral_ratio_bps as u128) as u64
+}
+
+// lamports worth a USD value (6 decimals) at a SOL price
+pub fn sol_lamports_for_usd(value_usd: u64, sol_price_usd: u64) -> u64 {
+    if sol_price_usd == 0 {
+        return 0;
+    }
+    ((value_usd as u128).saturating_mul(LAMPORTS_PER_SOL as u128) / sol_price_usd as u128)
+        .min(u64::MAX as u128) as u64
+}
+
+
+// mints USDtx to a user, the protocol controller PDA is the mint authority
+pub fn invoke_usdtx_mint(
+    usdtx_mint: &AccountInfo,
+    user_usdtx_account: &AccountInfo,
+    protocol_controller: &AccountInfo,
+    amount: u64,
+    protocol_controller_bump: u8,
+) -> Result<(), ProgramError> {
+    let bump_seed = [protocol_controller_bump];
+    let seeds = [
+        Seed::from(crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED),
+        Seed::from(&bump_seed),
+    ];
+
+    pinocchio_token::instructions::MintTo {
+        mint: usdtx_mint,
+        account: user_usdtx_account,
+        mint_authority: protocol_controller,
+        amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])
+}
+
+
+// deploys collateral from a controller-owned escrow into one strategy of the asset
+// accounts: 0 = protocol controller, 1 = escrow, 2 = strategy allocation table,
+// 3 = strategy registry, 4 = strategy manager program, 5 = strategy state, 6 = token program
+// only the escrow balance over the liquidity buffer is deployed, returns the amount that left the escrow
+pub fn deploy_collateral_to_strategy(
+    accounts: &[AccountInfo],
+    asset_type: u8,
+    amount: u64,
+    liquidity_buffer: u64,
+    protocol_controller_bump: u8,
+) -> Result<u64, ProgramError> {
+    if accounts.len() < 7 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let strategy_state = &accounts[5];
+
+    // the strategy has to be in the asset's allocation table
+    {
+        StrategyAllocationTable::verify_address(&accounts[2], asset_type)?;
+        let table_data = accounts[2].try_borrow_data()?;
+        let table = StrategyAllocationTable::load(&table_data)?;
+        if !table.strategies[..table.strategy_count as usize]
+            .iter()
+            .any(|strategy| strategy.strategy_state == *strategy_state.key() && strategy.target_weight_bps > 0)
+        {
+            msg!("strategy has no target weight for asset {}", asset_type);
+            return Err(crate::error::ProtocolControllerError::CoordinationOperationMismatch.into());
+        }
+    }
+
+    let registry = crate::strategy_registry::read_strategy_registry(&accounts[3], strategy_state.key())?;
+    if registry.asset_type != asset_type || accounts[4].key() != &registry.strategy_program {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let escrow_balance_before = token_account_amount(&accounts[1])?;
+    let amount = amount.min(escrow_balance_before.saturating_sub(liquidity_buffer));
+    if amount == 0 {
+        msg!("escrow at or under its liquidity buffer of {}, nothing deployed", liquidity_buffer);
+        return Ok(0);
+    }
+
+    // active and under its cap
+    registry.require_deployable(amount)?;
+
+    invoke_strategy_transfer(
+        STRATEGY_MANAGER_DEPOSIT_IX,
+        &accounts[4],
+        &accounts[0],
+        strategy_state,
+        &accounts[1],
+        &accounts[6],
+        amount,
+        protocol_controller_bump,
+    )?;
+    let deployed = escrow_balance_before.saturating_sub(token_account_amount(&accounts[1])?);
+
+    let mut registry_data = accounts[3].try_borrow_mut_data()?;
+    let registry = StrategyRegistry::load_mut(&mut registry_data)?;
+    registry.deployed_amount = registry.deployed_amount.saturating_add(deployed);
+
+    msg!("deployed {} of asset {} into strategy", deployed, asset_type);
+
+    Ok(deployed)
+}
+
+
+// pulls collateral back from one strategy of the asset into the controller-owned escrow
+// accounts: 0 = protocol controller, 1 = escrow, 2 = strategy registry,
+// 3 = strategy manager program, 4 = strategy state, 5 = token program
+// returns the amount that reached the escrow
+pub fn recall_collateral_from_strategy(
+    accounts: &[AccountInfo],
+    asset_type: u8,
+    amount: u64,
+    protocol_controller_bump: u8,
+) -> Result<u64, ProgramError> {
+    if accounts.len() < 6 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let strategy_state = &accounts[4];
+    let registry = crate::strategy_registry::read_strategy_registry(&accounts[2], strategy_state.key())?;
+    if registry.asset_type != asset_type || accounts[3].key() != &registry.strategy_program {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let amount = amount.min(registry.deployed_amount);
+    if amount == 0 {
+        return Ok(0);
+    }
+
+    let escrow_balance_before = token_account_amount(&accounts[1])?;
+    invoke_strategy_transfer(
+        STRATEGY_MANAGER_WITHDRAW_IX,
+        &accounts[3],
+        &accounts[0],
+        strategy_state,
+        &accounts[1],
+        &accounts[5],
+        amount,
+        protocol_controller_bump,
+    )?;
+    let recalled = token_account_amount(&accounts[1])?.saturating_sub(escrow_balance_before);
+
+    let mut registry_data = accounts[2].try_borrow_mut_data()?;
+    let registry = StrategyRegistry::load_mut(&mut registry_data)?;
+    registry.deployed_amount = registry.deployed_amount.saturating_sub(recalled);
+
+    msg!("recalled {} of asset {} from strategy", recalled, asset_type);
+
+    Ok(recalled)
+}
+
+
+// create the minting state and pin the USDtx mint, SOL escrow and USDC PSM vault
+pub fn initialize_minting(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("initializing USDtx minting");
+
+    // 0 = protocol parameters, 1 = authority (payer), 2 = minting state, 3 = USDtx mint,
+    // 4 = SOL escrow (wSOL token account), 5 = USDC PSM vault, 6 = system program
+    if accounts.len() < 7 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let authority_account = &accounts[1];
+    crate::parameters::read_protocol_parameters(&accounts[0])?.require_authority(authority_account)?;
+
+    let minting_account = &accounts[2];
+    let (expected_address, minting_bump) = Pubkey::find_program_address(&[MINTING_STATE_SEED], &crate::ID);
+    if minting_account.key() != &expected_address {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if minting_account.lamports() > 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let (protocol_controller, _) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+    {
+        let mint = pinocchio_token::state::Mint::from_account_info(&accounts[3])?;
+        if mint.mint_authority() != Some(&protocol_controller) {
+            return Err(ProgramError::IncorrectAuthority);
+        }
+        let escrow = pinocchio_token::state::TokenAccount::from_account_info(&accounts[4])?;
+        if escrow.owner() != &protocol_controller || !escrow.is_native() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let psm_vault = pinocchio_token::state::TokenAccount::from_account_info(&accounts[5])?;
+        if psm_vault.owner() != &protocol_controller || psm_vault.mint() != &USDC_MINT {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    let bump_seed = [minting_bump];
+    create_program_account(
+        authority_account,
+        minting_account,
+        MintingState::LEN,
+        &[Seed::from(MINTING_STATE_SEED), Seed::from(&bump_seed)],
+    )?;
+
+    let mut minting_data = minting_account.try_borrow_mut_data()?;
+    let minting = MintingState::load_mut(&mut minting_data)?;
+    minting.usdtx_mint = *accounts[3].key();
+    minting.sol_escrow = *accounts[4].key();
+    minting.usdc_psm_vault = *accounts[5].key();
+    minting.bump = minting_bump;
+    minting.is_initialized = 1;
+
+    Ok(())
+}
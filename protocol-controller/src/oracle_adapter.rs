@@ -0,0 +1,432 @@
+// oracle adapters for the doppler oracle
+// a registry maps feed accounts to adapter types, update_price reads any registered feed
+// through its adapter and writes the normalized price into the source price feed PDA
+// new providers (e.g. a local DEX TWAP) get an adapter here, the dispatcher stays as is
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    msg,
+    clock::Clock,
+    instruction::Seed,
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::ProgramAccount;
+use crate::oracle_guard::{SourcePriceFeed, MAX_PRICE_SOURCES, SOURCE_PRICE_FEED_SEED};
+
+
+pub const ORACLE_ADAPTER_REGISTRY_SEED: &[u8] = b"oracle_adapter_registry";
+pub const MAX_REGISTERED_FEEDS: usize = 8;
+
+// adapter types
+pub const ADAPTER_PYTH: u8 = 1;
+// price and timestamp at fixed offsets, for switchboard, chainlink or program-owned feeds
+pub const ADAPTER_FIXED_LAYOUT: u8 = 2;
+
+// doppler prices are USD with 6 decimals
+pub const NORMALIZED_EXPONENT: i32 = -6;
+
+
+// provider reading before normalization
+#[derive(Clone, Copy)]
+pub struct RawPrice {
+    pub price: i128,
+    pub confidence: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+
+pub trait OracleAdapter {
+    // reads the provider account into a raw price
+    fn parse(&self, feed_data: &[u8], entry: &OracleFeedEntry) -> Result<RawPrice, ProgramError>;
+
+    // rejects stale, non-positive or low-confidence readings
+    fn validate(
+        &self,
+        raw: &RawPrice,
+        entry: &OracleFeedEntry,
+        now: i64,
+        max_staleness: u64,
+    ) -> Result<(), ProgramError> {
+        if raw.price <= 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if now.saturating_sub(raw.publish_time) > max_staleness as i64 {
+            msg!("feed price is stale");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if entry.max_confidence_bps > 0 {
+            let confidence_bps = (raw.confidence as u128)
+                .saturating_mul(10_000u128)
+                .saturating_div(raw.price as u128);
+            if confidence_bps > entry.max_confidence_bps as u128 {
+                msg!("feed confidence interval too wide: {} bps", confidence_bps as u64);
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+        Ok(())
+    }
+
+    // rescales price and confidence to 6 decimals
+    fn normalize(&self, raw: &RawPrice) -> Result<(u64, u64), ProgramError> {
+        let price = rescale(raw.price as u128, raw.exponent)?;
+        let confidence = rescale(raw.confidence as u128, raw.exponent)?;
+        Ok((price, confidence))
+    }
+}
+
+
+fn rescale(value: u128, exponent: i32) -> Result<u64, ProgramError> {
+    let shift = exponent - NORMALIZED_EXPONENT;
+    if shift.unsigned_abs() > 30 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let factor = 10u128.pow(shift.unsigned_abs());
+    let scaled = if shift >= 0 {
+        value.checked_mul(factor).ok_or(ProgramError::ArithmeticOverflow)?
+    } else {
+        value / factor
+    };
+    u64::try_from(scaled).map_err(|_| ProgramError::ArithmeticOverflow)
+}
+
+
+// pyth price account (v2 layout)
+pub struct PythAdapter;
+
+const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+const PYTH_PRICE_ACCOUNT_TYPE: u32 = 3;
+const PYTH_STATUS_TRADING: u32 = 1;
+
+impl OracleAdapter for PythAdapter {
+    fn parse(&self, feed_data: &[u8], _entry: &OracleFeedEntry) -> Result<RawPrice, ProgramError> {
+        if feed_data.len() < 240 {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+
+        let magic = u32::from_le_bytes(feed_data[0..4].try_into().unwrap());
+        let account_type = u32::from_le_bytes(feed_data[8..12].try_into().unwrap());
+        if magic != PYTH_MAGIC || account_type != PYTH_PRICE_ACCOUNT_TYPE {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let status = u32::from_le_bytes(feed_data[224..228].try_into().unwrap());
+        if status != PYTH_STATUS_TRADING {
+            msg!("pyth price not trading");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(RawPrice {
+            price: i64::from_le_bytes(feed_data[208..216].try_into().unwrap()) as i128,
+            confidence: u64::from_le_bytes(feed_data[216..224].try_into().unwrap()),
+            exponent: i32::from_le_bytes(feed_data[20..24].try_into().unwrap()),
+            publish_time: i64::from_le_bytes(feed_data[96..104].try_into().unwrap()),
+        })
+    }
+}
+
+
+// price (i64 or i128) and unix timestamp (i64) at offsets set in the registry entry
+pub struct FixedLayoutAdapter;
+
+impl OracleAdapter for FixedLayoutAdapter {
+    fn parse(&self, feed_data: &[u8], entry: &OracleFeedEntry) -> Result<RawPrice, ProgramError> {
+        let price_offset = entry.price_offset as usize;
+        let timestamp_offset = entry.timestamp_offset as usize;
+
+        let price = match entry.price_width {
+            8 => {
+                let bytes = feed_data.get(price_offset..price_offset + 8).ok_or(ProgramError::AccountDataTooSmall)?;
+                i64::from_le_bytes(bytes.try_into().unwrap()) as i128
+            },
+            16 => {
+                let bytes = feed_data.get(price_offset..price_offset + 16).ok_or(ProgramError::AccountDataTooSmall)?;
+                i128::from_le_bytes(bytes.try_into().unwrap())
+            },
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let timestamp_bytes = feed_data
+            .get(timestamp_offset..timestamp_offset + 8)
+            .ok_or(ProgramError::AccountDataTooSmall)?;
+
+        Ok(RawPrice {
+            price,
+            confidence: 0,
+            exponent: entry.exponent as i32,
+            publish_time: i64::from_le_bytes(timestamp_bytes.try_into().unwrap()),
+        })
+    }
+}
+
+
+// reads a registered feed through its adapter, returns (price, confidence, publish_time)
+pub fn read_feed_through_adapter(
+    feed_account: &AccountInfo,
+    entry: &OracleFeedEntry,
+    now: i64,
+    max_staleness: u64,
+) -> Result<(u64, u64, i64), ProgramError> {
+    if !feed_account.is_owned_by(&entry.feed_owner) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let feed_data = feed_account.try_borrow_data()?;
+
+    let (raw, (price, confidence)) = match entry.adapter_type {
+        ADAPTER_PYTH => {
+            let raw = PythAdapter.parse(&feed_data, entry)?;
+            PythAdapter.validate(&raw, entry, now, max_staleness)?;
+            (raw, PythAdapter.normalize(&raw)?)
+        },
+        ADAPTER_FIXED_LAYOUT => {
+            let raw = FixedLayoutAdapter.parse(&feed_data, entry)?;
+            FixedLayoutAdapter.validate(&raw, entry, now, max_staleness)?;
+            (raw, FixedLayoutAdapter.normalize(&raw)?)
+        },
+        _ => return Err(ProgramError::InvalidAccountData),
+    };
+
+    Ok((price, confidence, raw.publish_time))
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct OracleFeedEntry {
+    pub feed: Pubkey,
+    // program that owns the feed account
+    pub feed_owner: Pubkey,
+    pub adapter_type: u8,
+    // doppler source slot the feed writes into
+    pub source_id: u8,
+    // fixed layout only
+    pub price_width: u8,
+    pub exponent: i8,
+    pub price_offset: u16,
+    pub timestamp_offset: u16,
+    // 0 = no confidence check
+    pub max_confidence_bps: u16,
+    pub _padding: [u8; 6],
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct OracleAdapterRegistry {
+    pub doppler_oracle: Pubkey,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub entry_count: u8,
+    pub _padding: [u8; 5],
+    pub entries: [OracleFeedEntry; MAX_REGISTERED_FEEDS],
+}
+
+impl ProgramAccount for OracleAdapterRegistry {}
+
+impl OracleAdapterRegistry {
+    pub fn verify_address(
+        registry_account: &AccountInfo,
+        doppler_oracle: &Pubkey,
+    ) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[ORACLE_ADAPTER_REGISTRY_SEED, doppler_oracle.as_ref()],
+            &crate::ID,
+        );
+        if registry_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if !registry_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Ok(bump)
+    }
+
+    pub fn find(&self, feed: &Pubkey) -> Option<&OracleFeedEntry> {
+        self.entries[..self.entry_count as usize]
+            .iter()
+            .find(|entry| &entry.feed == feed)
+    }
+}
+
+
+// register (or replace) a feed account with its adapter type
+pub fn register_oracle_feed(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("registering oracle feed adapter");
+
+    // feed (32), feed owner (32), adapter type, source id, price width, exponent,
+    // price offset (2), timestamp offset (2), max confidence bps (2)
+    if data.len() < 74 {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol parameters, 1 = authority (payer), 2 = doppler oracle, 3 = adapter registry,
+    // 4 = system program
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let parameters = crate::parameters::read_protocol_parameters(&accounts[0])?;
+    parameters.require_authority(&accounts[1])?;
+
+    let entry = OracleFeedEntry {
+        feed: data[0..32].try_into().unwrap(),
+        feed_owner: data[32..64].try_into().unwrap(),
+        adapter_type: data[64],
+        source_id: data[65],
+        price_width: data[66],
+        exponent: data[67] as i8,
+        price_offset: u16::from_le_bytes(data[68..70].try_into().unwrap()),
+        timestamp_offset: u16::from_le_bytes(data[70..72].try_into().unwrap()),
+        max_confidence_bps: u16::from_le_bytes(data[72..74].try_into().unwrap()),
+        _padding: [0; 6],
+    };
+
+    if entry.adapter_type != ADAPTER_PYTH && entry.adapter_type != ADAPTER_FIXED_LAYOUT {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    if entry.source_id == 0 || entry.source_id as usize > MAX_PRICE_SOURCES {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    if entry.adapter_type == ADAPTER_FIXED_LAYOUT && entry.price_width != 8 && entry.price_width != 16 {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let doppler_oracle = accounts[2].key();
+    let registry_account = &accounts[3];
+    let (expected_address, registry_bump) = Pubkey::find_program_address(
+        &[ORACLE_ADAPTER_REGISTRY_SEED, doppler_oracle.as_ref()],
+        &crate::ID,
+    );
+    if registry_account.key() != &expected_address {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if registry_account.lamports() == 0 {
+        let bump_seed = [registry_bump];
+        crate::program_account::create_program_account(
+            &accounts[1],
+            registry_account,
+            OracleAdapterRegistry::LEN,
+            &[Seed::from(ORACLE_ADAPTER_REGISTRY_SEED), Seed::from(doppler_oracle.as_ref()), Seed::from(&bump_seed)],
+        )?;
+    } else if !registry_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut registry_data = registry_account.try_borrow_mut_data()?;
+    let registry = OracleAdapterRegistry::load_mut(&mut registry_data)?;
+    if registry.is_initialized == 0 {
+        registry.doppler_oracle = *doppler_oracle;
+        registry.bump = registry_bump;
+        registry.is_initialized = 1;
+    }
+
+    let count = registry.entry_count as usize;
+    match registry.entries[..count].iter().position(|existing| existing.feed == entry.feed) {
+        Some(index) => registry.entries[index] = entry,
+        None => {
+            if count >= MAX_REGISTERED_FEEDS {
+                msg!("adapter registry is full");
+                return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+            }
+            registry.entries[count] = entry;
+            registry.entry_count += 1;
+        },
+    }
+
+    msg!("feed registered with adapter type {} for source {}", entry.adapter_type, entry.source_id);
+
+    Ok(())
+}
+
+
+// generic price update for any registered feed
+pub fn update_price(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("updating price through oracle adapter");
+
+    // 0 = protocol parameters, 1 = doppler oracle, 2 = adapter registry,
+    // 3 = feed account, 4 = source price feed,
+    // first update of a source only: 5 = payer, 6 = system program
+    if accounts.len() < 5 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let parameters = crate::parameters::read_protocol_parameters(&accounts[0])?;
+    let doppler_oracle = accounts[1].key();
+    let feed_account = &accounts[3];
+
+    let entry = {
+        OracleAdapterRegistry::verify_address(&accounts[2], doppler_oracle)?;
+        let registry_data = accounts[2].try_borrow_data()?;
+        let registry = OracleAdapterRegistry::load(&registry_data)?;
+        *registry.find(feed_account.key()).ok_or(ProgramError::InvalidArgument)?
+    };
+
+    let clock = Clock::get()?;
+    let (price, confidence, publish_time) = read_feed_through_adapter(
+        feed_account,
+        &entry,
+        clock.unix_timestamp,
+        parameters.max_price_staleness,
+    )?;
+
+    let source_feed_account = &accounts[4];
+    let source_seed = [entry.source_id];
+    let (expected_address, source_feed_bump) = Pubkey::find_program_address(
+        &[SOURCE_PRICE_FEED_SEED, doppler_oracle.as_ref(), &source_seed],
+        &crate::ID,
+    );
+    if source_feed_account.key() != &expected_address {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if source_feed_account.lamports() == 0 {
+        let payer = accounts.get(5).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let bump_seed = [source_feed_bump];
+        crate::program_account::create_program_account(
+            payer,
+            source_feed_account,
+            SourcePriceFeed::LEN,
+            &[
+                Seed::from(SOURCE_PRICE_FEED_SEED),
+                Seed::from(doppler_oracle.as_ref()),
+                Seed::from(&source_seed),
+                Seed::from(&bump_seed),
+            ],
+        )?;
+    } else if !source_feed_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut source_feed_data = source_feed_account.try_borrow_mut_data()?;
+    let source_feed = SourcePriceFeed::load_mut(&mut source_feed_data)?;
+    if source_feed.is_initialized == 0 {
+        source_feed.doppler_oracle = *doppler_oracle;
+        source_feed.source_id = entry.source_id;
+        source_feed.bump = source_feed_bump;
+        source_feed.is_initialized = 1;
+    }
+
+    // never move a source back in time
+    if publish_time < source_feed.publish_time {
+        msg!("feed reading older than stored price");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    source_feed.price = price;
+    source_feed.confidence = confidence;
+    source_feed.publish_time = publish_time;
+    source_feed.last_update_slot = clock.slot;
+
+    msg!("source {} price: {}", entry.source_id, price);
+
+    Ok(())
+}
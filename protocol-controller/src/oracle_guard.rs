@@ -0,0 +1,434 @@
+// oracle guard
+// per-source price snapshots next to the doppler oracle, the record of the last
+// deviation breach that paused minting, the fallback price path and the master price
+// override used while in emergency mode
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    msg,
+    clock::Clock,
+    instruction::Seed,
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::ProgramAccount;
+
+
+pub const SOURCE_PRICE_FEED_SEED: &[u8] = b"doppler_source_feed";
+pub const ORACLE_GUARD_SEED: &[u8] = b"oracle_guard";
+
+// source ids follow the update instructions (35, 36, 37)
+pub const SOURCE_SWITCHBOARD: u8 = 1;
+pub const SOURCE_PYTH: u8 = 2;
+pub const SOURCE_CHAINLINK: u8 = 3;
+pub const MAX_PRICE_SOURCES: usize = 3;
+
+// breach kinds
+pub const DEVIATION_SOURCE_VS_AGGREGATE: u8 = 1;
+pub const DEVIATION_AGGREGATE_VS_PREVIOUS: u8 = 2;
+
+// the aggregate is only compared with a previous aggregate at most this many seconds older
+pub const MAX_AGGREGATE_COMPARISON_WINDOW: i64 = 300;
+
+// where a resolved price came from
+pub const PRICE_ORIGIN_OVERRIDE: u8 = 1;
+pub const PRICE_ORIGIN_PRIMARY: u8 = 2;
+pub const PRICE_ORIGIN_SECONDARY: u8 = 3;
+pub const PRICE_ORIGIN_LAST_GOOD_TWAP: u8 = 4;
+pub const PRICE_ORIGIN_AGGREGATE: u8 = 5;
+
+// ~1h of slots at 400ms
+pub const MAX_PRICE_OVERRIDE_SLOTS: u64 = 9_000;
+
+
+// latest normalized price from a single provider
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct SourcePriceFeed {
+    pub doppler_oracle: Pubkey,
+    pub source_id: u8,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 5],
+    // USD with 6 decimals
+    pub price: u64,
+    pub confidence: u64,
+    pub publish_time: i64,
+    pub last_update_slot: u64,
+}
+
+impl ProgramAccount for SourcePriceFeed {}
+
+impl SourcePriceFeed {
+    pub fn verify_address(
+        feed_account: &AccountInfo,
+        doppler_oracle: &Pubkey,
+        source_id: u8,
+    ) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[SOURCE_PRICE_FEED_SEED, doppler_oracle.as_ref(), &[source_id]],
+            &crate::ID,
+        );
+        if feed_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if !feed_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Ok(bump)
+    }
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct DeviationBreach {
+    pub slot: u64,
+    pub timestamp: i64,
+    // 0 for the aggregate itself
+    pub source_id: u8,
+    pub kind: u8,
+    pub _padding: [u8; 6],
+    pub observed_price: u64,
+    pub reference_price: u64,
+    pub deviation_bps: u64,
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct PriceOverride {
+    // zero when no override is set
+    pub price: u64,
+    pub set_slot: u64,
+    pub expiry_slot: u64,
+    pub set_by: Pubkey,
+    pub reason_code: u32,
+    pub _padding: [u8; 4],
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct OracleGuard {
+    pub doppler_oracle: Pubkey,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+    pub breach_count: u64,
+    pub last_breach: DeviationBreach,
+    pub override_count: u64,
+    pub price_override: PriceOverride,
+}
+
+impl ProgramAccount for OracleGuard {}
+
+impl OracleGuard {
+    pub fn verify_address(
+        guard_account: &AccountInfo,
+        doppler_oracle: &Pubkey,
+    ) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[ORACLE_GUARD_SEED, doppler_oracle.as_ref()],
+            &crate::ID,
+        );
+        if guard_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if !guard_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Ok(bump)
+    }
+
+    // the override price while it has not expired
+    pub fn active_override(&self, slot: u64) -> Option<u64> {
+        if self.price_override.price > 0 && slot <= self.price_override.expiry_slot {
+            Some(self.price_override.price)
+        } else {
+            None
+        }
+    }
+}
+
+
+// creates the oracle guard of a doppler oracle on its first write, returns the bump
+pub fn create_oracle_guard_if_missing(
+    guard_account: &AccountInfo,
+    doppler_oracle: &Pubkey,
+    payer: Option<&AccountInfo>,
+) -> Result<u8, ProgramError> {
+    let (expected_address, bump) = Pubkey::find_program_address(
+        &[ORACLE_GUARD_SEED, doppler_oracle.as_ref()],
+        &crate::ID,
+    );
+    if guard_account.key() != &expected_address {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if guard_account.lamports() == 0 {
+        let payer = payer.ok_or(ProgramError::NotEnoughAccountKeys)?;
+        let bump_seed = [bump];
+        crate::program_account::create_program_account(
+            payer,
+            guard_account,
+            OracleGuard::LEN,
+            &[Seed::from(ORACLE_GUARD_SEED), Seed::from(doppler_oracle.as_ref()), Seed::from(&bump_seed)],
+        )?;
+    } else if !guard_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(bump)
+}
+
+
+// absolute distance between two prices in bps of the reference
+pub fn deviation_bps(price: u64, reference_price: u64) -> u64 {
+    if reference_price == 0 {
+        return u64::MAX;
+    }
+    let difference = if price > reference_price {
+        price - reference_price
+    } else {
+        reference_price - price
+    };
+    ((difference as u128)
+        .saturating_mul(10_000u128)
+        .saturating_div(reference_price as u128))
+        .min(u64::MAX as u128) as u64
+}
+
+
+// the source price if the feed was published within max_staleness seconds
+fn read_fresh_source_price(
+    feed_account: &AccountInfo,
+    doppler_oracle: &Pubkey,
+    source_id: u8,
+    now: i64,
+    max_staleness: u64,
+) -> Result<Option<u64>, ProgramError> {
+    // a source that was never updated has no price, the account still has to be its feed
+    if feed_account.lamports() == 0 {
+        let (expected_address, _) = Pubkey::find_program_address(
+            &[SOURCE_PRICE_FEED_SEED, doppler_oracle.as_ref(), &[source_id]],
+            &crate::ID,
+        );
+        if feed_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        return Ok(None);
+    }
+    SourcePriceFeed::verify_address(feed_account, doppler_oracle, source_id)?;
+    let feed_data = feed_account.try_borrow_data()?;
+    let feed = SourcePriceFeed::load(&feed_data)?;
+
+    if feed.is_initialized == 0 || feed.price == 0 {
+        return Ok(None);
+    }
+    if now.saturating_sub(feed.publish_time) > max_staleness as i64 {
+        msg!("source {} is stale", source_id);
+        return Ok(None);
+    }
+    Ok(Some(feed.price))
+}
+
+
+// master override of the guard, if one is active
+fn read_active_override(
+    guard_account: &AccountInfo,
+    doppler_oracle: &Pubkey,
+    slot: u64,
+) -> Result<Option<u64>, ProgramError> {
+    // no guard yet means no override was ever set
+    if !guard_account.is_owned_by(&crate::ID) {
+        if guard_account.key()
+            != &Pubkey::find_program_address(&[ORACLE_GUARD_SEED, doppler_oracle.as_ref()], &crate::ID).0
+        {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        return Ok(None);
+    }
+    OracleGuard::verify_address(guard_account, doppler_oracle)?;
+    let guard_data = guard_account.try_borrow_data()?;
+    Ok(OracleGuard::load(&guard_data)?.active_override(slot))
+}
+
+
+// price for minting and redeeming: an active override, then the latest Doppler aggregate while it
+// is at most max_price_staleness old, and only past that the fallback path
+// accounts: oracle guard, primary feed, secondary feed, doppler price history
+pub fn resolve_doppler_price(
+    accounts: &[AccountInfo],
+    doppler_oracle: &Pubkey,
+    parameters: &crate::parameters::ProtocolParameters,
+    clock: &Clock,
+) -> Result<(u64, u8), ProgramError> {
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    parameters.require_doppler_oracle(doppler_oracle)?;
+
+    if let Some(price) = read_active_override(&accounts[0], doppler_oracle, clock.slot)? {
+        return Ok((price, PRICE_ORIGIN_OVERRIDE));
+    }
+
+    {
+        let price_history_account = &accounts[3];
+        crate::twap::DopplerPriceHistory::verify_address(price_history_account, doppler_oracle)?;
+        let history_data = price_history_account.try_borrow_data()?;
+        let history = crate::twap::DopplerPriceHistory::load(&history_data)?;
+        if let Some(latest) = history.latest() {
+            if clock.unix_timestamp.saturating_sub(latest.timestamp) <= parameters.max_price_staleness as i64 {
+                return Ok((latest.price, PRICE_ORIGIN_AGGREGATE));
+            }
+        }
+    }
+
+    msg!("doppler aggregate is stale, falling back");
+    resolve_fallback_price(accounts, doppler_oracle, parameters, clock)
+}
+
+
+// price path when the aggregate cannot be trusted or refreshed:
+// active master override, then primary source, then secondary source, then the 1h TWAP
+// ending at the last good aggregate while that is at most max_fallback_twap_age old
+// only for the pinned doppler oracle, every feed has to be its PDA, created or not
+// accounts: oracle guard, primary feed, secondary feed, doppler price history
+pub fn resolve_fallback_price(
+    accounts: &[AccountInfo],
+    doppler_oracle: &Pubkey,
+    parameters: &crate::parameters::ProtocolParameters,
+    clock: &Clock,
+) -> Result<(u64, u8), ProgramError> {
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    parameters.require_doppler_oracle(doppler_oracle)?;
+
+    if let Some(price) = read_active_override(&accounts[0], doppler_oracle, clock.slot)? {
+        return Ok((price, PRICE_ORIGIN_OVERRIDE));
+    }
+
+    let primary = read_fresh_source_price(
+        &accounts[1],
+        doppler_oracle,
+        parameters.primary_price_source as u8,
+        clock.unix_timestamp,
+        parameters.max_price_staleness,
+    )?;
+    if let Some(price) = primary {
+        return Ok((price, PRICE_ORIGIN_PRIMARY));
+    }
+
+    let secondary = read_fresh_source_price(
+        &accounts[2],
+        doppler_oracle,
+        parameters.secondary_price_source as u8,
+        clock.unix_timestamp,
+        parameters.max_price_staleness,
+    )?;
+    if let Some(price) = secondary {
+        return Ok((price, PRICE_ORIGIN_SECONDARY));
+    }
+
+    let price_history_account = &accounts[3];
+    crate::twap::DopplerPriceHistory::verify_address(price_history_account, doppler_oracle)?;
+    let history_data = price_history_account.try_borrow_data()?;
+    let history = crate::twap::DopplerPriceHistory::load(&history_data)?;
+    let last_good = history.latest().ok_or(ProgramError::UninitializedAccount)?;
+    if clock.unix_timestamp.saturating_sub(last_good.timestamp) > parameters.max_fallback_twap_age as i64 {
+        msg!("no fresh source and the last aggregate is older than {} seconds", parameters.max_fallback_twap_age);
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // a short history falls back to the last aggregate itself
+    let price = history
+        .twap(crate::twap::TWAP_WINDOW_1H, last_good.timestamp)
+        .unwrap_or(last_good.price);
+    Ok((price, PRICE_ORIGIN_LAST_GOOD_TWAP))
+}
+
+
+// master price override, only while the controller is in emergency mode
+// expires by itself after duration_slots; a zero duration clears it
+pub fn master_authority_override_oracle_price(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("master authority oracle price override");
+
+    // price (8), duration in slots (8), reason code (4)
+    if data.len() < 20 {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol controller, 1 = protocol parameters, 2 = master authority (payer),
+    // 3 = doppler oracle, 4 = oracle guard, 5 = system program
+    if accounts.len() < 5 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let override_price = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let duration_slots = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    let reason_code = u32::from_le_bytes(data[16..20].try_into().unwrap());
+
+    let parameters = crate::parameters::read_protocol_parameters(&accounts[1])?;
+    parameters.require_authority(&accounts[2])?;
+
+    crate::program_account::verify_protocol_controller(&accounts[0])?;
+
+    {
+        let controller_data = accounts[0].try_borrow_data()?;
+        if controller_data.len() < core::mem::size_of::<crate::state::ProtocolController>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let controller_state = bytemuck::from_bytes::<crate::state::ProtocolController>(
+            &controller_data[..core::mem::size_of::<crate::state::ProtocolController>()],
+        );
+        if !controller_state.emergency_mode {
+            msg!("price override is only allowed in emergency mode");
+            return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+        }
+    }
+
+    if duration_slots > MAX_PRICE_OVERRIDE_SLOTS || (duration_slots > 0 && override_price == 0) {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let doppler_oracle = accounts[3].key();
+    parameters.require_doppler_oracle(doppler_oracle)?;
+    let guard_account = &accounts[4];
+    let guard_bump = create_oracle_guard_if_missing(guard_account, doppler_oracle, Some(&accounts[2]))?;
+    let mut guard_data = guard_account.try_borrow_mut_data()?;
+    let guard = OracleGuard::load_mut(&mut guard_data)?;
+    if guard.is_initialized == 0 {
+        guard.doppler_oracle = *doppler_oracle;
+        guard.bump = guard_bump;
+        guard.is_initialized = 1;
+    }
+
+    let current_slot = Clock::get()?.slot;
+
+    if duration_slots == 0 {
+        guard.price_override = PriceOverride::zeroed();
+        msg!("price override cleared, reason code: {}", reason_code);
+        return Ok(());
+    }
+
+    guard.price_override = PriceOverride {
+        price: override_price,
+        set_slot: current_slot,
+        expiry_slot: current_slot.saturating_add(duration_slots),
+        set_by: *accounts[2].key(),
+        reason_code,
+        _padding: [0; 4],
+    };
+    guard.override_count = guard.override_count.saturating_add(1);
+
+    msg!("price override: {} until slot {}", override_price, guard.price_override.expiry_slot);
+    msg!("reason code: {}", reason_code);
+
+    Ok(())
+}
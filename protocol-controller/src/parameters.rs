@@ -0,0 +1,186 @@
+// protocol parameters
+// governed values written by update_protocol_parameters and read by the coordinators
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    msg,
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::ProgramAccount;
+
+
+pub const PROTOCOL_PARAMETERS_SEED: &[u8] = b"protocol_parameters";
+
+
+// defaults written at initialization
+pub const DEFAULT_MIN_COLLATERAL_RATIO_BPS: u64 = 10_500;
+pub const DEFAULT_REBALANCE_FREQUENCY: u64 = 3_600;
+pub const DEFAULT_YIELD_DISTRIBUTION_FREQUENCY: u64 = 86_400;
+pub const DEFAULT_EMERGENCY_THRESHOLD_BPS: u64 = 10_000;
+pub const DEFAULT_MAX_SOURCE_DEVIATION_BPS: u64 = 200;
+pub const DEFAULT_MAX_AGGREGATE_DEVIATION_BPS: u64 = 500;
+pub const DEFAULT_MAX_PRICE_STALENESS: u64 = 60;
+pub const DEFAULT_MAX_FALLBACK_TWAP_AGE: u64 = 900;
+pub const DEFAULT_TREASURY_FEE_BPS: u64 = 2_000;
+pub const DEFAULT_INSURANCE_FUND_BPS: u64 = 1_000;
+pub const DEFAULT_PSM_FEE_BPS: u64 = 10;
+pub const DEFAULT_PSM_EPOCH_CAP: u64 = 1_000_000_000_000;
+pub const DEFAULT_PSM_EPOCH_LENGTH: u64 = 86_400;
+pub const DEFAULT_LIQUIDITY_BUFFER_BPS: u64 = 1_000;
+pub const DEFAULT_LIQUID_MIN_BACKING_BPS: u64 = 1_000;
+pub const DEFAULT_FROZEN_MIN_BACKING_BPS: u64 = 200;
+pub const DEFAULT_LIQUIDATION_THRESHOLD_BPS: u64 = 10_000;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct ProtocolParameters {
+    pub authority: Pubkey,
+    // the only Doppler oracle the price paths accept, pinned at initialization
+    pub doppler_oracle: Pubkey,
+    pub is_initialized: u8,
+    pub bump: u8,
+    // set by an oracle deviation breach, stops minting only, cleared by governance
+    pub mint_paused: u8,
+    pub _padding: [u8; 5],
+
+    pub min_collateral_ratio_bps: u64,
+    // seconds
+    pub rebalance_frequency: u64,
+    pub yield_distribution_frequency: u64,
+    pub emergency_threshold: u64,
+
+    // oracle deviation bands
+    // max distance of a single source from the aggregate
+    pub max_source_deviation_bps: u64,
+    // max move of the aggregate against the previous aggregate
+    pub max_aggregate_deviation_bps: u64,
+
+    // oracle fallback order, primary then secondary source, then the last good TWAP
+    // seconds after which a source price counts as stale
+    pub max_price_staleness: u64,
+    pub primary_price_source: u64,
+    pub secondary_price_source: u64,
+    // seconds after the last aggregate past which the TWAP fallback is refused too
+    pub max_fallback_twap_age: u64,
+
+    // share of distributed yield taken as fee, split by the fee split config
+    pub treasury_fee_bps: u64,
+    // share of every harvest moved into the insurance fund
+    pub insurance_fund_bps: u64,
+
+    // USDC peg stability module
+    pub psm_fee_bps: u64,
+    // USDtx the PSM may mint per epoch
+    pub psm_epoch_cap: u64,
+    // seconds
+    pub psm_epoch_length: u64,
+
+    // share of each asset's collateral kept liquid in its escrow for redemptions
+    pub sol_liquidity_buffer_bps: u64,
+    pub usdc_liquidity_buffer_bps: u64,
+
+    // seconds after the last freeze before a position can be unfrozen, 0 = none
+    pub freeze_cooldown_seconds: u64,
+
+    // liquid collateral required per liquid and per frozen USDtx, frozen supply needs less
+    pub liquid_min_backing_bps: u64,
+    pub frozen_min_backing_bps: u64,
+
+    // collateral ratio under which liquidation_trigger pauses the protocol
+    pub liquidation_threshold_bps: u64,
+}
+
+impl ProgramAccount for ProtocolParameters {}
+
+impl ProtocolParameters {
+    pub fn verify_address(parameters_account: &AccountInfo) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[PROTOCOL_PARAMETERS_SEED],
+            &crate::ID,
+        );
+        if parameters_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if !parameters_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Ok(bump)
+    }
+
+    pub fn initialize(&mut self, authority: &Pubkey, doppler_oracle: &Pubkey, bump: u8) {
+        self.authority = *authority;
+        self.doppler_oracle = *doppler_oracle;
+        self.bump = bump;
+        self.min_collateral_ratio_bps = DEFAULT_MIN_COLLATERAL_RATIO_BPS;
+        self.rebalance_frequency = DEFAULT_REBALANCE_FREQUENCY;
+        self.yield_distribution_frequency = DEFAULT_YIELD_DISTRIBUTION_FREQUENCY;
+        self.emergency_threshold = DEFAULT_EMERGENCY_THRESHOLD_BPS;
+        self.max_source_deviation_bps = DEFAULT_MAX_SOURCE_DEVIATION_BPS;
+        self.max_aggregate_deviation_bps = DEFAULT_MAX_AGGREGATE_DEVIATION_BPS;
+        self.max_price_staleness = DEFAULT_MAX_PRICE_STALENESS;
+        self.primary_price_source = crate::oracle_guard::SOURCE_PYTH as u64;
+        self.secondary_price_source = crate::oracle_guard::SOURCE_SWITCHBOARD as u64;
+        self.max_fallback_twap_age = DEFAULT_MAX_FALLBACK_TWAP_AGE;
+        self.treasury_fee_bps = DEFAULT_TREASURY_FEE_BPS;
+        self.insurance_fund_bps = DEFAULT_INSURANCE_FUND_BPS;
+        self.psm_fee_bps = DEFAULT_PSM_FEE_BPS;
+        self.psm_epoch_cap = DEFAULT_PSM_EPOCH_CAP;
+        self.psm_epoch_length = DEFAULT_PSM_EPOCH_LENGTH;
+        self.sol_liquidity_buffer_bps = DEFAULT_LIQUIDITY_BUFFER_BPS;
+        self.usdc_liquidity_buffer_bps = DEFAULT_LIQUIDITY_BUFFER_BPS;
+        self.liquid_min_backing_bps = DEFAULT_LIQUID_MIN_BACKING_BPS;
+        self.frozen_min_backing_bps = DEFAULT_FROZEN_MIN_BACKING_BPS;
+        self.liquidation_threshold_bps = DEFAULT_LIQUIDATION_THRESHOLD_BPS;
+        self.is_initialized = 1;
+    }
+
+    pub fn liquidity_buffer_bps(&self, asset_type: u8) -> u64 {
+        if asset_type == crate::yield_accounting::ASSET_SOL {
+            self.sol_liquidity_buffer_bps
+        } else {
+            self.usdc_liquidity_buffer_bps
+        }
+    }
+
+    pub fn require_minting_open(&self) -> Result<(), ProgramError> {
+        if self.mint_paused != 0 {
+            msg!("minting paused after an oracle deviation breach");
+            return Err(crate::error::ProtocolControllerError::CoordinationOperationMismatch.into());
+        }
+        Ok(())
+    }
+
+    pub fn require_doppler_oracle(&self, doppler_oracle: &Pubkey) -> Result<(), ProgramError> {
+        if doppler_oracle != &self.doppler_oracle {
+            msg!("not the protocol's doppler oracle");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(())
+    }
+
+    pub fn require_authority(&self, authority_account: &AccountInfo) -> Result<(), ProgramError> {
+        if !authority_account.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if authority_account.key() != &self.authority {
+            return Err(ProgramError::IncorrectAuthority);
+        }
+        Ok(())
+    }
+}
+
+
+// copies the parameters out of the account after checking its address
+pub fn read_protocol_parameters(parameters_account: &AccountInfo) -> Result<ProtocolParameters, ProgramError> {
+    ProtocolParameters::verify_address(parameters_account)?;
+    let parameters_data = parameters_account.try_borrow_data()?;
+    let parameters = ProtocolParameters::load(&parameters_data)?;
+    if parameters.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(*parameters)
+}
@@ -0,0 +1,81 @@
+// zero-copy access to the controller's own PDA accounts
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    instruction::{Seed, Signer},
+    sysvars::{rent::Rent, Sysvar},
+};
+use bytemuck::Pod;
+
+
+pub trait ProgramAccount: Pod {
+    const LEN: usize = core::mem::size_of::<Self>();
+
+    fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        if data.len() < Self::LEN {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        bytemuck::try_from_bytes(&data[..Self::LEN]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        if data.len() < Self::LEN {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        bytemuck::try_from_bytes_mut(&mut data[..Self::LEN]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+}
+
+
+// the protocol controller PDA, returns its bump
+pub fn verify_protocol_controller(controller_account: &AccountInfo) -> Result<u8, ProgramError> {
+    let (expected_address, bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+    if controller_account.key() != &expected_address {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if !controller_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(bump)
+}
+
+
+// creates a PDA owned by this program, rent paid by the payer
+pub fn create_program_account(
+    payer: &AccountInfo,
+    new_account: &AccountInfo,
+    space: usize,
+    signer_seeds: &[Seed],
+) -> Result<(), ProgramError> {
+    let lamports = Rent::get()?.minimum_balance(space);
+
+    pinocchio_system::instructions::CreateAccount {
+        from: payer,
+        to: new_account,
+        lamports,
+        space: space as u64,
+        owner: &crate::ID,
+    }
+    .invoke_signed(&[Signer::from(signer_seeds)])
+}
+
+
+// closes a PDA owned by this program, its rent goes to the destination
+pub fn close_program_account(
+    account: &AccountInfo,
+    destination: &AccountInfo,
+) -> Result<(), ProgramError> {
+    let lamports = account.lamports();
+    *destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    *account.try_borrow_mut_lamports()? = 0;
+    account.try_borrow_mut_data()?.fill(0);
+    Ok(())
+}
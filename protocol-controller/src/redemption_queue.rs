@@ -0,0 +1,510 @@
+// redemption queue
+// burns the buffer cannot cover lock their USDtx and get a ticket, keepers unwind strategies
+// and fill tickets strictly in order, users claim the collateral of a filled ticket
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    msg,
+    clock::Clock,
+    sysvars::Sysvar,
+    instruction::{Seed, Signer},
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::{ProgramAccount, create_program_account, close_program_account};
+use crate::minting::{MintingState, recall_collateral_from_strategy};
+use crate::insurance::invoke_controller_transfer;
+use crate::yield_accounting::{token_account_amount, ASSET_SOL, ASSET_USDC};
+
+
+pub const REDEMPTION_QUEUE_SEED: &[u8] = b"redemption_queue";
+pub const REDEMPTION_TICKET_SEED: &[u8] = b"redemption_ticket";
+
+pub const TICKET_STATUS_PENDING: u8 = 1;
+pub const TICKET_STATUS_FILLED: u8 = 2;
+
+
+// one queue per collateral asset
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct RedemptionQueue {
+    // USDtx token account owned by the protocol controller PDA, holds locked USDtx
+    pub usdtx_escrow: Pubkey,
+    // collateral token account owned by the protocol controller PDA, holds filled tickets
+    pub claims_vault: Pubkey,
+    pub next_ticket_id: u64,
+    // oldest ticket not filled yet
+    pub head_ticket_id: u64,
+    pub pending_usdtx: u64,
+    pub pending_collateral: u64,
+    pub filled_unclaimed_collateral: u64,
+    pub total_filled_tickets: u64,
+    pub asset_type: u8,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 5],
+}
+
+impl ProgramAccount for RedemptionQueue {}
+
+impl RedemptionQueue {
+    pub fn verify_address(queue_account: &AccountInfo, asset_type: u8) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[REDEMPTION_QUEUE_SEED, &[asset_type]],
+            &crate::ID,
+        );
+        if queue_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if !queue_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Ok(bump)
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.head_ticket_id < self.next_ticket_id
+    }
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct RedemptionTicket {
+    pub owner: Pubkey,
+    pub ticket_id: u64,
+    pub usdtx_locked: u64,
+    // fixed when the ticket is issued
+    pub collateral_owed: u64,
+    pub psm_fee: u64,
+    pub created_at: i64,
+    pub filled_at: i64,
+    pub asset_type: u8,
+    pub status: u8,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 4],
+}
+
+impl ProgramAccount for RedemptionTicket {}
+
+impl RedemptionTicket {
+    pub fn verify_address(ticket_account: &AccountInfo, asset_type: u8, ticket_id: u64) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[REDEMPTION_TICKET_SEED, &[asset_type], &ticket_id.to_le_bytes()],
+            &crate::ID,
+        );
+        if ticket_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(bump)
+    }
+}
+
+
+// whether earlier tickets are still waiting, a new burn cannot skip them
+pub fn redemption_queue_pending(queue_account: &AccountInfo, asset_type: u8) -> Result<bool, ProgramError> {
+    RedemptionQueue::verify_address(queue_account, asset_type)?;
+    let queue_data = queue_account.try_borrow_data()?;
+    let queue = RedemptionQueue::load(&queue_data)?;
+    if queue.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(queue.has_pending())
+}
+
+
+// takes a settled redemption out of the controller totals
+pub fn record_controller_redemption(
+    protocol_controller: &AccountInfo,
+    asset_type: u8,
+    burned: u64,
+    collateral_out: u64,
+) -> Result<(), ProgramError> {
+    let mut controller_data = protocol_controller.try_borrow_mut_data()?;
+    if controller_data.len() >= std::mem::size_of::<crate::state::ProtocolController>() {
+        let controller_state = bytemuck::cast_mut::<crate::state::ProtocolController>(&mut controller_data);
+        controller_state.total_usdtx_burned = controller_state.total_usdtx_burned.saturating_add(burned);
+        if asset_type == ASSET_SOL {
+            controller_state.current_sol_tvl = controller_state.current_sol_tvl.saturating_sub(collateral_out);
+        } else {
+            controller_state.current_usdc_tvl = controller_state.current_usdc_tvl.saturating_sub(burned);
+        }
+    }
+    Ok(())
+}
+
+
+// locks the user's USDtx in the queue escrow and issues the next ticket, the user pays its rent
+// returns the ticket id
+pub fn enqueue_redemption(
+    queue_account: &AccountInfo,
+    ticket_account: &AccountInfo,
+    user: &AccountInfo,
+    user_usdtx_account: &AccountInfo,
+    usdtx_escrow: &AccountInfo,
+    asset_type: u8,
+    usdtx_amount: u64,
+    collateral_owed: u64,
+    psm_fee: u64,
+) -> Result<u64, ProgramError> {
+    RedemptionQueue::verify_address(queue_account, asset_type)?;
+    let mut queue_data = queue_account.try_borrow_mut_data()?;
+    let queue = RedemptionQueue::load_mut(&mut queue_data)?;
+    if queue.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if usdtx_escrow.key() != &queue.usdtx_escrow {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let ticket_id = queue.next_ticket_id;
+    let ticket_bump = RedemptionTicket::verify_address(ticket_account, asset_type, ticket_id)?;
+    if ticket_account.lamports() > 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    pinocchio_token::instructions::Transfer {
+        from: user_usdtx_account,
+        to: usdtx_escrow,
+        authority: user,
+        amount: usdtx_amount,
+    }
+    .invoke()?;
+
+    let asset_seed = [asset_type];
+    let ticket_id_seed = ticket_id.to_le_bytes();
+    let bump_seed = [ticket_bump];
+    create_program_account(
+        user,
+        ticket_account,
+        RedemptionTicket::LEN,
+        &[
+            Seed::from(REDEMPTION_TICKET_SEED),
+            Seed::from(&asset_seed),
+            Seed::from(&ticket_id_seed),
+            Seed::from(&bump_seed),
+        ],
+    )?;
+
+    let mut ticket_data = ticket_account.try_borrow_mut_data()?;
+    let ticket = RedemptionTicket::load_mut(&mut ticket_data)?;
+    ticket.owner = *user.key();
+    ticket.ticket_id = ticket_id;
+    ticket.usdtx_locked = usdtx_amount;
+    ticket.collateral_owed = collateral_owed;
+    ticket.psm_fee = psm_fee;
+    ticket.created_at = Clock::get()?.unix_timestamp;
+    ticket.asset_type = asset_type;
+    ticket.status = TICKET_STATUS_PENDING;
+    ticket.bump = ticket_bump;
+    ticket.is_initialized = 1;
+
+    queue.next_ticket_id = ticket_id.saturating_add(1);
+    queue.pending_usdtx = queue.pending_usdtx.saturating_add(usdtx_amount);
+    queue.pending_collateral = queue.pending_collateral.saturating_add(collateral_owed);
+
+    msg!("redemption ticket {} queued: {} USDtx for {} collateral", ticket_id, usdtx_amount, collateral_owed);
+
+    Ok(ticket_id)
+}
+
+
+// create the queue of an asset and pin its USDtx escrow and claims vault
+pub fn initialize_redemption_queue(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("initializing redemption queue");
+
+    if data.is_empty() || (data[0] != ASSET_SOL && data[0] != ASSET_USDC) {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    let asset_type = data[0];
+
+    // 0 = protocol parameters, 1 = authority (payer), 2 = redemption queue, 3 = minting state,
+    // 4 = USDtx queue escrow, 5 = claims vault, 6 = asset escrow (SOL escrow or USDC PSM vault),
+    // 7 = system program
+    if accounts.len() < 8 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let authority_account = &accounts[1];
+    crate::parameters::read_protocol_parameters(&accounts[0])?.require_authority(authority_account)?;
+
+    let queue_account = &accounts[2];
+    let (expected_address, queue_bump) = Pubkey::find_program_address(
+        &[REDEMPTION_QUEUE_SEED, &[asset_type]],
+        &crate::ID,
+    );
+    if queue_account.key() != &expected_address {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if queue_account.lamports() > 0 {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    // both accounts are controller-owned, the claims vault holds the asset escrow's mint
+    let (protocol_controller, _) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+    {
+        MintingState::verify_address(&accounts[3])?;
+        let minting_data = accounts[3].try_borrow_data()?;
+        let minting = MintingState::load(&minting_data)?;
+        if minting.is_initialized == 0 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let usdtx_escrow = pinocchio_token::state::TokenAccount::from_account_info(&accounts[4])?;
+        if usdtx_escrow.owner() != &protocol_controller || usdtx_escrow.mint() != &minting.usdtx_mint {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let asset_escrow = if asset_type == ASSET_SOL { &minting.sol_escrow } else { &minting.usdc_psm_vault };
+        if accounts[6].key() != asset_escrow {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let claims_vault = pinocchio_token::state::TokenAccount::from_account_info(&accounts[5])?;
+        let escrow = pinocchio_token::state::TokenAccount::from_account_info(&accounts[6])?;
+        if claims_vault.owner() != &protocol_controller {
+            return Err(ProgramError::IllegalOwner);
+        }
+        if claims_vault.mint() != escrow.mint() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    let asset_seed = [asset_type];
+    let bump_seed = [queue_bump];
+    create_program_account(
+        authority_account,
+        queue_account,
+        RedemptionQueue::LEN,
+        &[Seed::from(REDEMPTION_QUEUE_SEED), Seed::from(&asset_seed), Seed::from(&bump_seed)],
+    )?;
+
+    let mut queue_data = queue_account.try_borrow_mut_data()?;
+    let queue = RedemptionQueue::load_mut(&mut queue_data)?;
+    queue.usdtx_escrow = *accounts[4].key();
+    queue.claims_vault = *accounts[5].key();
+    queue.asset_type = asset_type;
+    queue.bump = queue_bump;
+    queue.is_initialized = 1;
+
+    Ok(())
+}
+
+
+// keeper: unwind from a strategy into the escrow, then fill tickets from the head while
+// the escrow covers them, each fill burns the locked USDtx and moves the collateral to the claims vault
+pub fn fill_redemption_tickets(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("filling redemption tickets");
+
+    // asset (1), strategy unwind amount (8), 0 fills from the escrow only
+    if data.len() < 9 || (data[0] != ASSET_SOL && data[0] != ASSET_USDC) {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+    let asset_type = data[0];
+    let unwind_amount = u64::from_le_bytes(data[1..9].try_into().unwrap());
+
+    // 0 = protocol controller, 1 = escrow (SOL escrow or USDC PSM vault), 2 = strategy registry,
+    // 3 = strategy manager program, 4 = strategy state, 5 = token program,
+    // 6 = minting state, 7 = redemption queue, 8 = claims vault, 9 = USDtx queue escrow,
+    // 10 = USDtx mint, 11.. = tickets from the head of the queue, in order
+    if accounts.len() < 12 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let protocol_controller_account = &accounts[0];
+    let escrow = &accounts[1];
+    let claims_vault = &accounts[8];
+    let usdtx_escrow = &accounts[9];
+    let usdtx_mint = &accounts[10];
+
+    let (_, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    MintingState::verify_address(&accounts[6])?;
+    {
+        let minting_data = accounts[6].try_borrow_data()?;
+        let minting = MintingState::load(&minting_data)?;
+        if asset_type == ASSET_SOL {
+            minting.verify_sol_accounts(usdtx_mint, escrow)?;
+        } else {
+            minting.verify_psm_accounts(usdtx_mint, escrow)?;
+        }
+    }
+
+    RedemptionQueue::verify_address(&accounts[7], asset_type)?;
+    let mut queue_data = accounts[7].try_borrow_mut_data()?;
+    let queue = RedemptionQueue::load_mut(&mut queue_data)?;
+    if queue.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    if claims_vault.key() != &queue.claims_vault || usdtx_escrow.key() != &queue.usdtx_escrow {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if !queue.has_pending() {
+        msg!("redemption queue empty");
+        return Ok(());
+    }
+
+    // anyone may keep the queue moving, the unwind never pulls more than the queue is owed
+    let unwind_amount = unwind_amount.min(queue.pending_collateral.saturating_sub(token_account_amount(escrow)?));
+    if unwind_amount > 0 {
+        recall_collateral_from_strategy(&accounts[0..6], asset_type, unwind_amount, protocol_controller_bump)?;
+    }
+
+    let bump_seed = [protocol_controller_bump];
+    let seeds = [
+        Seed::from(crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED),
+        Seed::from(&bump_seed),
+    ];
+
+    let now = Clock::get()?.unix_timestamp;
+    let mut filled_count = 0u64;
+
+    for ticket_account in accounts[11..].iter() {
+        RedemptionTicket::verify_address(ticket_account, asset_type, queue.head_ticket_id)?;
+        if !ticket_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut ticket_data = ticket_account.try_borrow_mut_data()?;
+        let ticket = RedemptionTicket::load_mut(&mut ticket_data)?;
+        if ticket.is_initialized == 0 || ticket.status != TICKET_STATUS_PENDING {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // strict FIFO, a ticket the escrow cannot cover blocks the ones behind it
+        if token_account_amount(escrow)? < ticket.collateral_owed {
+            msg!("escrow cannot cover ticket {}, stopping", ticket.ticket_id);
+            break;
+        }
+
+        pinocchio_token::instructions::Burn {
+            account: usdtx_escrow,
+            mint: usdtx_mint,
+            authority: protocol_controller_account,
+            amount: ticket.usdtx_locked,
+        }
+        .invoke_signed(&[Signer::from(&seeds)])?;
+
+        invoke_controller_transfer(
+            escrow,
+            claims_vault,
+            protocol_controller_account,
+            ticket.collateral_owed,
+            protocol_controller_bump,
+        )?;
+
+        {
+            let mut minting_data = accounts[6].try_borrow_mut_data()?;
+            MintingState::load_mut(&mut minting_data)?.record_redemption(
+                asset_type,
+                ticket.usdtx_locked,
+                ticket.collateral_owed,
+                ticket.psm_fee,
+            );
+        }
+        record_controller_redemption(protocol_controller_account, asset_type, ticket.usdtx_locked, ticket.collateral_owed)?;
+
+        ticket.status = TICKET_STATUS_FILLED;
+        ticket.filled_at = now;
+
+        queue.head_ticket_id = queue.head_ticket_id.saturating_add(1);
+        queue.pending_usdtx = queue.pending_usdtx.saturating_sub(ticket.usdtx_locked);
+        queue.pending_collateral = queue.pending_collateral.saturating_sub(ticket.collateral_owed);
+        queue.filled_unclaimed_collateral = queue.filled_unclaimed_collateral.saturating_add(ticket.collateral_owed);
+        queue.total_filled_tickets = queue.total_filled_tickets.saturating_add(1);
+        filled_count += 1;
+
+        if !queue.has_pending() {
+            break;
+        }
+    }
+
+    msg!("filled {} tickets, {} still queued", filled_count, queue.next_ticket_id - queue.head_ticket_id);
+
+    Ok(())
+}
+
+
+// owner claims the collateral of a filled ticket, the ticket closes and its rent goes back
+pub fn claim_redemption(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("claiming redemption");
+
+    // 0 = protocol controller, 1 = redemption queue, 2 = ticket, 3 = claims vault,
+    // 4 = owner (signer), 5 = owner collateral token account, 6 = token program
+    if accounts.len() < 7 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let owner = &accounts[4];
+    if !owner.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let ticket_account = &accounts[2];
+    if !ticket_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let ticket = {
+        let ticket_data = ticket_account.try_borrow_data()?;
+        *RedemptionTicket::load(&ticket_data)?
+    };
+    if ticket.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    RedemptionTicket::verify_address(ticket_account, ticket.asset_type, ticket.ticket_id)?;
+    if owner.key() != &ticket.owner {
+        return Err(ProgramError::IncorrectAuthority);
+    }
+    if ticket.status != TICKET_STATUS_FILLED {
+        msg!("ticket {} not filled yet", ticket.ticket_id);
+        return Err(crate::error::ProtocolControllerError::CoordinationOperationMismatch.into());
+    }
+
+    RedemptionQueue::verify_address(&accounts[1], ticket.asset_type)?;
+    let mut queue_data = accounts[1].try_borrow_mut_data()?;
+    let queue = RedemptionQueue::load_mut(&mut queue_data)?;
+    if accounts[3].key() != &queue.claims_vault {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let (_, protocol_controller_bump) = Pubkey::find_program_address(
+        &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+        &crate::ID,
+    );
+
+    invoke_controller_transfer(
+        &accounts[3],
+        &accounts[5],
+        &accounts[0],
+        ticket.collateral_owed,
+        protocol_controller_bump,
+    )?;
+
+    queue.filled_unclaimed_collateral = queue.filled_unclaimed_collateral.saturating_sub(ticket.collateral_owed);
+
+    close_program_account(ticket_account, owner)?;
+
+    msg!("ticket {} claimed: {} collateral", ticket.ticket_id, ticket.collateral_owed);
+
+    Ok(())
+}
@@ -0,0 +1,87 @@
+// freeze-based solvency
+// frozen USDtx cannot be redeemed instantly, so liquid and frozen supply are measured against
+// the collateral sitting liquid in the escrows with separate thresholds, the last report is kept
+// on-chain for risk dashboards
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::ProgramAccount;
+
+
+pub const SOLVENCY_REPORT_SEED: &[u8] = b"solvency_report";
+
+// status bits
+pub const SOLVENCY_LIQUID_BREACH: u8 = 1;
+pub const SOLVENCY_FROZEN_BREACH: u8 = 2;
+pub const SOLVENCY_UNDERCOLLATERALIZED: u8 = 4;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct SolvencyReport {
+    // USD values, 6 decimals
+    pub liquid_collateral_value: u64,
+    pub total_collateral_value: u64,
+    pub liquid_supply: u64,
+    pub frozen_supply: u64,
+    // liquid collateral over liquid supply
+    pub liquid_backing_ratio_bps: u64,
+    // liquid collateral left after the liquid requirement, over frozen supply
+    pub frozen_backing_ratio_bps: u64,
+    // all collateral over all supply
+    pub collateral_ratio_bps: u64,
+    // thresholds the ratios were checked against
+    pub liquid_min_backing_bps: u64,
+    pub frozen_min_backing_bps: u64,
+    pub sol_price_usd: u64,
+    pub last_check_timestamp: i64,
+    pub last_check_slot: u64,
+    pub check_count: u64,
+    pub status: u8,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 5],
+}
+
+impl ProgramAccount for SolvencyReport {}
+
+impl SolvencyReport {
+    pub fn verify_address(report_account: &AccountInfo) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[SOLVENCY_REPORT_SEED],
+            &crate::ID,
+        );
+        if report_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(bump)
+    }
+}
+
+
+// backing over supply in bps, no supply reads as fully backed
+pub fn backing_ratio_bps(backing_value: u64, supply: u64) -> u64 {
+    if supply == 0 {
+        return u64::MAX;
+    }
+    ((backing_value as u128).saturating_mul(10_000u128) / supply as u128).min(u64::MAX as u128) as u64
+}
+
+// liquid collateral the liquid supply does not need at its threshold goes to the frozen supply
+pub fn split_backing_ratios(
+    liquid_collateral_value: u64,
+    liquid_supply: u64,
+    frozen_supply: u64,
+    liquid_min_backing_bps: u64,
+) -> (u64, u64) {
+    let liquid_requirement = ((liquid_supply as u128) * (liquid_min_backing_bps as u128) / 10_000u128) as u64;
+    (
+        backing_ratio_bps(liquid_collateral_value, liquid_supply),
+        backing_ratio_bps(liquid_collateral_value.saturating_sub(liquid_requirement), frozen_supply),
+    )
+}
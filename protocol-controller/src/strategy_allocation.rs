@@ -0,0 +1,306 @@
+// strategy allocation table
+// target weights per asset set by update_strategy_weights, read by rebalance_all_strategies
+// deployed amounts live in each strategy's registry
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::{invoke_signed, slice_invoke_signed},
+    instruction::{AccountMeta, Instruction, Seed, Signer},
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::ProgramAccount;
+
+
+pub const STRATEGY_ALLOCATION_SEED: &[u8] = b"strategy_allocation";
+pub const MAX_STRATEGIES_PER_ASSET: usize = 8;
+
+// deposit / withdraw instructions on both strategy managers
+pub const STRATEGY_MANAGER_DEPOSIT_IX: u8 = 2;
+pub const STRATEGY_MANAGER_WITHDRAW_IX: u8 = 3;
+
+// protocol-specific accounts passed through on yield collection
+pub const MAX_PASSTHROUGH_ACCOUNTS: usize = 8;
+
+// position accounts of one external position (Drift user + spot market, Kamino reserve + cTokens)
+pub const MAX_POSITION_ACCOUNTS: usize = 2;
+
+// controller, strategy state, yields vault, token program, then position and passthrough accounts
+const MAX_COLLECT_ACCOUNTS: usize = 4 + MAX_POSITION_ACCOUNTS + MAX_PASSTHROUGH_ACCOUNTS;
+
+pub const DEFAULT_DRIFT_BAND_BPS: u16 = 500;
+
+// score haircut per risk tier above 1, applied in full to liquid-backed capital only
+pub const RISK_TIER_PENALTY_BPS: u64 = 2_000;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct StrategyAllocation {
+    // strategy state account inside its strategy manager
+    pub strategy_state: Pubkey,
+    pub strategy_program: Pubkey,
+    pub target_weight_bps: u16,
+}
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct StrategyAllocationTable {
+    pub last_rebalance_timestamp: i64,
+    pub rebalance_count: u64,
+    pub asset_type: u8,
+    pub strategy_count: u8,
+    // max distance of a strategy from its target before funds move
+    pub drift_band_bps: u16,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 2],
+    pub strategies: [StrategyAllocation; MAX_STRATEGIES_PER_ASSET],
+}
+
+impl ProgramAccount for StrategyAllocationTable {}
+
+impl StrategyAllocationTable {
+    pub fn verify_address(table_account: &AccountInfo, asset_type: u8) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[STRATEGY_ALLOCATION_SEED, &[asset_type]],
+            &crate::ID,
+        );
+        if table_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(bump)
+    }
+
+    pub fn is_due(&self, now: i64, frequency: u64) -> bool {
+        self.last_rebalance_timestamp == 0
+            || now.saturating_sub(self.last_rebalance_timestamp) >= frequency as i64
+    }
+}
+
+
+// target amount of a strategy for a total
+pub fn target_amount(total: u64, weight_bps: u16) -> u64 {
+    ((total as u128) * (weight_bps as u128) / 10_000u128) as u64
+}
+
+// distance between current and target as bps of the total
+pub fn allocation_drift_bps(current: u64, target: u64, total: u64) -> u64 {
+    if total == 0 {
+        return 0;
+    }
+    ((current.abs_diff(target) as u128) * 10_000u128 / total as u128) as u64
+}
+
+
+// realized APR weighted down by risk tier, frozen capital can take more risk than liquid capital
+pub fn risk_adjusted_score(apr_bps: u64, risk_tier: u8, liquid_share_bps: u64) -> u64 {
+    let penalty = (risk_tier.saturating_sub(1) as u64)
+        .saturating_mul(RISK_TIER_PENALTY_BPS)
+        .saturating_mul(liquid_share_bps.min(10_000))
+        / 10_000;
+    // a strategy without a harvest yet still gets a minimal score
+    apr_bps.max(1).saturating_mul(10_000u64.saturating_sub(penalty).max(1))
+}
+
+// target weights proportional to the scores, a strategy never above its cap weight,
+// what a capped strategy cannot take goes to the others by score
+pub fn score_weights(scores: &[u64], cap_weights: &[u64], weights: &mut [u16]) {
+    let count = scores.len();
+    let mut capped = [false; MAX_STRATEGIES_PER_ASSET];
+    let mut remaining: u64 = 10_000;
+
+    loop {
+        let open_score: u128 = (0..count).filter(|&i| !capped[i]).map(|i| scores[i] as u128).sum();
+        if open_score == 0 {
+            break;
+        }
+
+        let mut newly_capped = false;
+        for i in 0..count {
+            if capped[i] {
+                continue;
+            }
+            let weight = ((remaining as u128) * (scores[i] as u128) / open_score) as u64;
+            if weight > cap_weights[i] {
+                weights[i] = cap_weights[i] as u16;
+                remaining -= cap_weights[i];
+                capped[i] = true;
+                newly_capped = true;
+            }
+        }
+        if newly_capped {
+            continue;
+        }
+
+        let mut assigned = 0u64;
+        for i in 0..count {
+            if !capped[i] {
+                let weight = ((remaining as u128) * (scores[i] as u128) / open_score) as u64;
+                weights[i] = weight as u16;
+                assigned += weight;
+            }
+        }
+        remaining -= assigned;
+        break;
+    }
+
+    // rounding dust to the best scored strategies that still have room under their cap
+    while remaining > 0 {
+        let best = match (0..count)
+            .filter(|&i| scores[i] > 0 && (weights[i] as u64) < cap_weights[i])
+            .max_by_key(|&i| scores[i])
+        {
+            Some(best) => best,
+            None => break,
+        };
+        let added = remaining.min(cap_weights[best] - weights[best] as u64);
+        weights[best] += added as u16;
+        remaining -= added;
+    }
+
+    // only caps adding up to less than 10_000 leave something, the best scored strategy takes it
+    // and deposits still stop at each registry cap
+    if remaining > 0 {
+        if let Some(best) = (0..count).filter(|&i| scores[i] > 0).max_by_key(|&i| scores[i]) {
+            weights[best] += remaining as u16;
+        }
+    }
+}
+
+
+// moves current weights towards the targets by at most max_step bps per strategy,
+// every strategy moves by the same fraction of its distance so the total stays 10_000
+pub fn step_weights(current: &[u16], target: &[u16], max_step_bps: u16, next: &mut [u16]) {
+    let count = current.len();
+    let max_distance = (0..count).map(|i| current[i].abs_diff(target[i])).max().unwrap_or(0);
+    if max_distance <= max_step_bps {
+        next[..count].copy_from_slice(&target[..count]);
+        return;
+    }
+
+    let mut total: i64 = 0;
+    for i in 0..count {
+        let distance = target[i] as i64 - current[i] as i64;
+        next[i] = (current[i] as i64 + distance * max_step_bps as i64 / max_distance as i64) as u16;
+        total += next[i] as i64;
+    }
+
+    // rounding dust to the strategy furthest from its target in that direction
+    let residual = 10_000 - total;
+    if residual != 0 {
+        let pick = (0..count)
+            .filter(|&i| if residual > 0 { next[i] < target[i] } else { next[i] > target[i] })
+            .max_by_key(|&i| next[i].abs_diff(target[i]));
+        if let Some(i) = pick {
+            next[i] = (next[i] as i64 + residual) as u16;
+        }
+    }
+}
+
+
+// deposit or withdraw CPI into a strategy manager, signed by the protocol controller PDA
+// funds move between the strategy and the given controller-owned token account
+pub fn invoke_strategy_transfer(
+    instruction_id: u8,
+    strategy_program: &AccountInfo,
+    protocol_controller: &AccountInfo,
+    strategy_state: &AccountInfo,
+    controller_token_account: &AccountInfo,
+    token_program: &AccountInfo,
+    amount: u64,
+    protocol_controller_bump: u8,
+) -> Result<(), ProgramError> {
+    let account_metas = [
+        AccountMeta::readonly_signer(protocol_controller.key()),
+        AccountMeta::writable(strategy_state.key()),
+        AccountMeta::writable(controller_token_account.key()),
+        AccountMeta::readonly(token_program.key()),
+    ];
+
+    let mut instruction_data = [0u8; 9];
+    instruction_data[0] = instruction_id;
+    instruction_data[1..9].copy_from_slice(&amount.to_le_bytes());
+
+    let instruction = Instruction {
+        program_id: strategy_program.key(),
+        accounts: &account_metas,
+        data: &instruction_data,
+    };
+
+    let bump_seed = [protocol_controller_bump];
+    let seeds = [
+        Seed::from(crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED),
+        Seed::from(&bump_seed),
+    ];
+
+    invoke_signed(
+        &instruction,
+        &[protocol_controller, strategy_state, controller_token_account, token_program],
+        &[Signer::from(&seeds)],
+    )
+}
+
+
+// yield collection CPI into a strategy manager for one external position (Drift, Kamino)
+// position accounts follow the fixed ones, protocol-specific accounts are passed through last
+pub fn invoke_strategy_collect(
+    instruction_id: u8,
+    strategy_program: &AccountInfo,
+    protocol_controller: &AccountInfo,
+    strategy_state: &AccountInfo,
+    yields_vault: &AccountInfo,
+    token_program: &AccountInfo,
+    position_accounts: &[&AccountInfo],
+    passthrough_accounts: &[AccountInfo],
+    amount: u64,
+    protocol_controller_bump: u8,
+) -> Result<(), ProgramError> {
+    if position_accounts.len() > MAX_POSITION_ACCOUNTS || passthrough_accounts.len() > MAX_PASSTHROUGH_ACCOUNTS {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    // no heap in this program, fixed arrays filled up to account_count
+    let mut account_infos: [&AccountInfo; MAX_COLLECT_ACCOUNTS] = [protocol_controller; MAX_COLLECT_ACCOUNTS];
+    let mut account_metas: [AccountMeta; MAX_COLLECT_ACCOUNTS] =
+        core::array::from_fn(|_| AccountMeta::readonly(protocol_controller.key()));
+
+    account_infos[..4].copy_from_slice(&[protocol_controller, strategy_state, yields_vault, token_program]);
+    account_metas[0] = AccountMeta::readonly_signer(protocol_controller.key());
+    account_metas[1] = AccountMeta::writable(strategy_state.key());
+    account_metas[2] = AccountMeta::writable(yields_vault.key());
+    account_metas[3] = AccountMeta::readonly(token_program.key());
+    let mut account_count = 4;
+
+    for account in position_accounts {
+        account_infos[account_count] = account;
+        account_metas[account_count] = AccountMeta::writable(account.key());
+        account_count += 1;
+    }
+    for account in passthrough_accounts {
+        account_infos[account_count] = account;
+        account_metas[account_count] = AccountMeta::new(account.key(), account.is_writable(), account.is_signer());
+        account_count += 1;
+    }
+
+    let mut instruction_data = [0u8; 9];
+    instruction_data[0] = instruction_id;
+    instruction_data[1..9].copy_from_slice(&amount.to_le_bytes());
+
+    let instruction = Instruction {
+        program_id: strategy_program.key(),
+        accounts: &account_metas[..account_count],
+        data: &instruction_data,
+    };
+
+    let bump_seed = [protocol_controller_bump];
+    let seeds = [
+        Seed::from(crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED),
+        Seed::from(&bump_seed),
+    ];
+
+    slice_invoke_signed(&instruction, &account_infos[..account_count], &[Signer::from(&seeds)])
+}
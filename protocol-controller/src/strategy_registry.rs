@@ -0,0 +1,116 @@
+// strategy registry
+// one PDA per whitelisted strategy with its cap, risk tier, deployed principal and status
+// coordinators only send funds to active strategies that stay under their cap
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    msg,
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::ProgramAccount;
+
+
+pub const STRATEGY_REGISTRY_SEED: &[u8] = b"strategy_registry";
+
+pub const STRATEGY_STATUS_ACTIVE: u8 = 1;
+pub const STRATEGY_STATUS_PAUSED: u8 = 2;
+pub const STRATEGY_STATUS_WINDING_DOWN: u8 = 3;
+
+// 1 = lowest risk
+pub const MAX_RISK_TIER: u8 = 5;
+
+// pause_strategy modes
+pub const PAUSE_MODE_RESUME: u8 = 0;
+// no new deposits, capital stays deployed
+pub const PAUSE_MODE_SOFT: u8 = 1;
+// pulls capital back in steps over several transactions
+pub const PAUSE_MODE_WIND_DOWN: u8 = 2;
+// everything in one call during an emergency, a shortfall is recorded as unrecovered
+pub const PAUSE_MODE_FORCED_UNWIND: u8 = 3;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct StrategyRegistry {
+    // strategy state account inside its strategy manager
+    pub strategy_state: Pubkey,
+    pub strategy_program: Pubkey,
+    // principal limit, in the asset's native units
+    pub max_allocation: u64,
+    pub deployed_amount: u64,
+    pub last_harvest_timestamp: i64,
+    pub whitelisted_at: i64,
+    // wind-down progress, deployed amount when it started and what came back so far
+    pub wind_down_started_at: i64,
+    pub wind_down_initial_amount: u64,
+    pub wind_down_recovered: u64,
+    // deployed principal the strategy could not return on a forced unwind
+    pub unrecovered_amount: u64,
+    pub asset_type: u8,
+    pub risk_tier: u8,
+    pub status: u8,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 3],
+}
+
+impl ProgramAccount for StrategyRegistry {}
+
+impl StrategyRegistry {
+    pub fn verify_address(registry_account: &AccountInfo, strategy_state: &Pubkey) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[STRATEGY_REGISTRY_SEED, strategy_state.as_ref()],
+            &crate::ID,
+        );
+        if registry_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(bump)
+    }
+
+    pub fn is_wound_down(&self) -> bool {
+        self.status == STRATEGY_STATUS_WINDING_DOWN && self.deployed_amount == 0
+    }
+
+    // room left under the cap
+    pub fn available_capacity(&self) -> u64 {
+        self.max_allocation.saturating_sub(self.deployed_amount)
+    }
+
+    // refuses deposits into strategies that are not active or would go over their cap
+    pub fn require_deployable(&self, amount: u64) -> Result<(), ProgramError> {
+        if self.is_initialized == 0 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+        if self.status != STRATEGY_STATUS_ACTIVE {
+            msg!("strategy is not active (status {})", self.status);
+            return Err(crate::error::ProtocolControllerError::CoordinationOperationMismatch.into());
+        }
+        if amount > self.available_capacity() {
+            msg!("deposit {} over strategy cap, room left {}", amount, self.available_capacity());
+            return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+        }
+        Ok(())
+    }
+}
+
+
+// copies a registry out of its account after checking address and owner
+pub fn read_strategy_registry(
+    registry_account: &AccountInfo,
+    strategy_state: &Pubkey,
+) -> Result<StrategyRegistry, ProgramError> {
+    StrategyRegistry::verify_address(registry_account, strategy_state)?;
+    if !registry_account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let registry_data = registry_account.try_borrow_data()?;
+    let registry = StrategyRegistry::load(&registry_data)?;
+    if registry.is_initialized == 0 {
+        return Err(ProgramError::UninitializedAccount);
+    }
+    Ok(*registry)
+}
@@ -0,0 +1,139 @@
+// Thaler distribution state
+// yield becomes Thalers at $100 each, remainders under $100 carry forward to the next run
+// each run closes a TWAB period: the Thalers minted in it are shared by frozen balance·seconds
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    instruction::{Seed, Signer},
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::ProgramAccount;
+
+
+pub const THALER_DISTRIBUTION_SEED: &[u8] = b"thaler_distribution";
+
+// USDC (6 decimals) backing one Thaler
+pub const THALER_VALUE_USDC: u64 = 100_000_000;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct ThalerDistribution {
+    // pinned by the first distribution, which the protocol authority signs
+    pub thaler_mint: Pubkey,
+    pub distribution_vault: Pubkey,
+    // TWAB registry cumulative at the start and end of the last closed period
+    pub period_start_twab: u128,
+    pub period_end_twab: u128,
+    // USDC below one Thaler waiting for the next run
+    pub carry_forward_usdc: u64,
+    pub period_thalers: u64,
+    pub total_thalers_minted: u64,
+    // USDC taken from harvested yield, fees included
+    pub total_yield_distributed: u64,
+    pub distribution_count: u64,
+    pub last_distribution_timestamp: i64,
+    pub period_start_timestamp: i64,
+    pub period_end_timestamp: i64,
+    // highest period committed as a yield epoch
+    pub last_closed_epoch: u64,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+}
+
+impl ProgramAccount for ThalerDistribution {}
+
+impl ThalerDistribution {
+    pub fn verify_address(distribution_account: &AccountInfo) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[THALER_DISTRIBUTION_SEED],
+            &crate::ID,
+        );
+        if distribution_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if !distribution_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Ok(bump)
+    }
+
+    // Thaler base units minted for the last closed period
+    pub fn period_amount(&self, thaler_decimals: u8) -> Option<u64> {
+        self.period_thalers.checked_mul(10u64.checked_pow(thaler_decimals as u32)?)
+    }
+
+    pub fn verify_thaler_accounts(
+        &self,
+        thaler_mint: &AccountInfo,
+        distribution_vault: &AccountInfo,
+    ) -> Result<(), ProgramError> {
+        if thaler_mint.key() != &self.thaler_mint || distribution_vault.key() != &self.distribution_vault {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        Ok(())
+    }
+}
+
+
+// whole Thalers for a USDC amount, and the remainder to carry forward
+pub fn thalers_for_usdc(usdc_amount: u64) -> (u64, u64) {
+    (usdc_amount / THALER_VALUE_USDC, usdc_amount % THALER_VALUE_USDC)
+}
+
+
+// pays Thalers out of the distribution vault, which the protocol controller PDA owns
+pub fn invoke_thaler_transfer(
+    distribution_vault: &AccountInfo,
+    recipient: &AccountInfo,
+    protocol_controller: &AccountInfo,
+    amount: u64,
+    protocol_controller_bump: u8,
+) -> Result<(), ProgramError> {
+    let bump_seed = [protocol_controller_bump];
+    let seeds = [
+        Seed::from(crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED),
+        Seed::from(&bump_seed),
+    ];
+
+    pinocchio_token::instructions::Transfer {
+        from: distribution_vault,
+        to: recipient,
+        authority: protocol_controller,
+        amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])
+}
+
+
+// mints Thalers into the distribution vault, the protocol controller PDA is the mint authority
+pub fn invoke_thaler_mint(
+    thaler_mint: &AccountInfo,
+    distribution_vault: &AccountInfo,
+    protocol_controller: &AccountInfo,
+    thaler_count: u64,
+    protocol_controller_bump: u8,
+) -> Result<(), ProgramError> {
+    let decimals = pinocchio_token::state::Mint::from_account_info(thaler_mint)?.decimals();
+    let amount = thaler_count
+        .checked_mul(10u64.pow(decimals as u32))
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    let bump_seed = [protocol_controller_bump];
+    let seeds = [
+        Seed::from(crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED),
+        Seed::from(&bump_seed),
+    ];
+
+    pinocchio_token::instructions::MintTo {
+        mint: thaler_mint,
+        account: distribution_vault,
+        mint_authority: protocol_controller,
+        amount,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])
+}
@@ -0,0 +1,231 @@
+// TWAB (time-weighted average balance) registry
+// global balance·seconds of frozen USDtx, the denominator for Thaler allocation
+//
+// a user's share of an epoch needs their cumulative at the epoch bounds, which are Thaler period
+// ends; the ledger keeps one checkpoint per period boundary (changes within a period are merged)
+// and never drops one an unsettled epoch still needs, so every unsettled epoch is priced from at
+// most TWAB_CHECKPOINT_CAPACITY checkpoints; a balance change that would have to drop one is
+// refused until the user settles their oldest epochs
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    msg,
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::ProgramAccount;
+
+
+pub const TWAB_REGISTRY_SEED: &[u8] = b"twab_registry";
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct TwabRegistry {
+    // sum of frozen balance × seconds since the registry started
+    pub cumulative_balance_seconds: u128,
+    pub total_balance: u64,
+    pub staker_count: u64,
+    pub last_update_timestamp: i64,
+    // end of the last closed Thaler period, ledgers keep the checkpoint before every such boundary
+    pub last_period_end_timestamp: i64,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 14],
+}
+
+impl ProgramAccount for TwabRegistry {}
+
+impl TwabRegistry {
+    pub fn verify_address(registry_account: &AccountInfo) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[TWAB_REGISTRY_SEED],
+            &crate::ID,
+        );
+        if registry_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(bump)
+    }
+
+    // brings the cumulative total up to now with the current balance
+    pub fn accrue(&mut self, now: i64) {
+        if self.last_update_timestamp > 0 && now > self.last_update_timestamp {
+            let elapsed = (now - self.last_update_timestamp) as u128;
+            self.cumulative_balance_seconds = self
+                .cumulative_balance_seconds
+                .saturating_add((self.total_balance as u128).saturating_mul(elapsed));
+        }
+        if now > self.last_update_timestamp {
+            self.last_update_timestamp = now;
+        }
+    }
+}
+
+
+pub const TWAB_LEDGER_SEED: &[u8] = b"twab_ledger";
+pub const TWAB_CHECKPOINT_CAPACITY: usize = 16;
+
+
+// state right after a balance change
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct TwabCheckpoint {
+    pub cumulative_balance_seconds: u128,
+    pub timestamp: i64,
+    pub balance: u64,
+}
+
+
+// per-user frozen USDtx balance·seconds
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct TwabLedger {
+    pub cumulative_balance_seconds: u128,
+    pub owner: Pubkey,
+    pub balance: u64,
+    pub last_update_timestamp: i64,
+    // distribution count up to which Thalers were paid out, and the end of that period
+    pub last_settled_distribution: u64,
+    pub last_settled_timestamp: i64,
+    pub checkpoint_head: u32,
+    pub checkpoint_count: u32,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+    pub checkpoints: [TwabCheckpoint; TWAB_CHECKPOINT_CAPACITY],
+}
+
+impl ProgramAccount for TwabLedger {}
+
+impl TwabLedger {
+    pub fn verify_address(ledger_account: &AccountInfo, owner: &Pubkey) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[TWAB_LEDGER_SEED, owner.as_ref()],
+            &crate::ID,
+        );
+        if ledger_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(bump)
+    }
+
+    pub fn accrue(&mut self, now: i64) {
+        if self.last_update_timestamp > 0 && now > self.last_update_timestamp {
+            let elapsed = (now - self.last_update_timestamp) as u128;
+            self.cumulative_balance_seconds = self
+                .cumulative_balance_seconds
+                .saturating_add((self.balance as u128).saturating_mul(elapsed));
+        }
+        if now > self.last_update_timestamp {
+            self.last_update_timestamp = now;
+        }
+    }
+
+    // accrues, sets the new balance and checkpoints it
+    // last_period_end is the registry's last period boundary; the latest checkpoint is only kept
+    // when that boundary lies after it, otherwise no epoch bound needs it and the new one replaces it
+    pub fn set_balance(&mut self, new_balance: u64, now: i64, last_period_end: i64) -> Result<(), ProgramError> {
+        let latest = (self.checkpoint_head as usize + TWAB_CHECKPOINT_CAPACITY - 1) % TWAB_CHECKPOINT_CAPACITY;
+        let replaces_latest = self.checkpoint_count > 0
+            && (self.checkpoints[latest].timestamp == now || last_period_end < self.checkpoints[latest].timestamp);
+
+        // the oldest checkpoint goes only once the next one already covers the last settled boundary
+        if !replaces_latest && self.checkpoint_count as usize == TWAB_CHECKPOINT_CAPACITY {
+            let next_oldest = (self.checkpoint_head as usize + 1) % TWAB_CHECKPOINT_CAPACITY;
+            if self.checkpoints[next_oldest].timestamp > self.last_settled_timestamp {
+                msg!("TWAB history full, settle epoch {} first", self.last_settled_distribution + 1);
+                return Err(crate::error::ProtocolControllerError::CoordinationOperationMismatch.into());
+            }
+        }
+
+        self.accrue(now);
+        self.balance = new_balance;
+
+        let checkpoint = TwabCheckpoint {
+            cumulative_balance_seconds: self.cumulative_balance_seconds,
+            timestamp: now,
+            balance: new_balance,
+        };
+
+        if replaces_latest {
+            self.checkpoints[latest] = checkpoint;
+            return Ok(());
+        }
+
+        self.checkpoints[self.checkpoint_head as usize] = checkpoint;
+        self.checkpoint_head = ((self.checkpoint_head as usize + 1) % TWAB_CHECKPOINT_CAPACITY) as u32;
+        if (self.checkpoint_count as usize) < TWAB_CHECKPOINT_CAPACITY {
+            self.checkpoint_count += 1;
+        }
+        Ok(())
+    }
+
+    // with nothing frozen since the last settled boundary every later period is worth nothing,
+    // settlement moves on to the latest period end
+    pub fn skip_empty_periods(&mut self, distribution_count: u64, last_period_end: i64) {
+        if self.balance == 0
+            && self.last_update_timestamp <= self.last_settled_timestamp
+            && distribution_count > self.last_settled_distribution
+        {
+            self.last_settled_distribution = distribution_count;
+            self.last_settled_timestamp = last_period_end;
+        }
+    }
+
+    // cumulative balance·seconds at a point in time
+    // None when the point is older than the retained checkpoints
+    pub fn cumulative_at(&self, timestamp: i64) -> Option<u128> {
+        if timestamp >= self.last_update_timestamp {
+            let elapsed = timestamp.saturating_sub(self.last_update_timestamp).max(0) as u128;
+            return Some(
+                self.cumulative_balance_seconds
+                    .saturating_add((self.balance as u128).saturating_mul(elapsed)),
+            );
+        }
+
+        for i in 0..self.checkpoint_count as usize {
+            let index = (self.checkpoint_head as usize + TWAB_CHECKPOINT_CAPACITY - 1 - i) % TWAB_CHECKPOINT_CAPACITY;
+            let checkpoint = self.checkpoints[index];
+            if checkpoint.timestamp <= timestamp {
+                let elapsed = (timestamp - checkpoint.timestamp) as u128;
+                return Some(
+                    checkpoint.cumulative_balance_seconds
+                        .saturating_add((checkpoint.balance as u128).saturating_mul(elapsed)),
+                );
+            }
+        }
+
+        // before the first checkpoint the ledger held nothing, unless older ones were overwritten
+        if (self.checkpoint_count as usize) < TWAB_CHECKPOINT_CAPACITY {
+            Some(0)
+        } else {
+            None
+        }
+    }
+}
+
+
+// user part of an amount for the period [start, end]:
+// amount × user balance·seconds in the period / global balance·seconds in the period
+pub fn period_share(
+    ledger: &TwabLedger,
+    amount: u64,
+    period_start_timestamp: i64,
+    period_end_timestamp: i64,
+    global_period_twab: u128,
+) -> Option<u64> {
+    if global_period_twab == 0 {
+        return Some(0);
+    }
+    let user_start = ledger.cumulative_at(period_start_timestamp)?;
+    let user_end = ledger.cumulative_at(period_end_timestamp)?;
+    let user_period_twab = user_end.saturating_sub(user_start);
+
+    Some(
+        ((amount as u128).saturating_mul(user_period_twab) / global_period_twab)
+            .min(amount as u128) as u64,
+    )
+}
@@ -0,0 +1,201 @@
+// Doppler TWAP
+// ring buffer of aggregated prices, written by aggregate_oracle_prices
+// spot aggregates can be moved within a slot, so liquidation triggers and collateral-ratio
+// updates can read a time-weighted average over a window instead of the latest aggregate
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::ProgramAccount;
+
+
+pub const DOPPLER_PRICE_HISTORY_SEED: &[u8] = b"doppler_price_history";
+
+// 128 entries of at least 30s each reach back more than the 1h window
+pub const PRICE_HISTORY_CAPACITY: usize = 128;
+pub const MIN_OBSERVATION_INTERVAL: i64 = 30;
+
+pub const TWAP_WINDOW_5M: i64 = 300;
+pub const TWAP_WINDOW_1H: i64 = 3_600;
+pub const MAX_TWAP_WINDOW: i64 = 86_400;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct PriceObservation {
+    // USD price with 6 decimals
+    pub price: u64,
+    pub timestamp: i64,
+}
+
+
+// PDA derived from the doppler oracle account
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct DopplerPriceHistory {
+    pub doppler_oracle: Pubkey,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 6],
+    // next write position
+    pub head: u32,
+    pub count: u32,
+    pub last_update_slot: u64,
+    pub observations: [PriceObservation; PRICE_HISTORY_CAPACITY],
+}
+
+impl ProgramAccount for DopplerPriceHistory {}
+
+impl DopplerPriceHistory {
+    // checks the account is the history PDA for this doppler oracle, returns the bump
+    pub fn verify_address(
+        price_history_account: &AccountInfo,
+        doppler_oracle: &Pubkey,
+    ) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[DOPPLER_PRICE_HISTORY_SEED, doppler_oracle.as_ref()],
+            &crate::ID,
+        );
+        if price_history_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if !price_history_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Ok(bump)
+    }
+
+    fn latest_index(&self) -> usize {
+        (self.head as usize + PRICE_HISTORY_CAPACITY - 1) % PRICE_HISTORY_CAPACITY
+    }
+
+    pub fn latest(&self) -> Option<PriceObservation> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(self.observations[self.latest_index()])
+    }
+
+    // aggregate recorded before the latest one
+    pub fn previous(&self) -> Option<PriceObservation> {
+        if self.count < 2 {
+            return None;
+        }
+        let index = (self.head as usize + PRICE_HISTORY_CAPACITY - 2) % PRICE_HISTORY_CAPACITY;
+        Some(self.observations[index])
+    }
+
+    // one observation per MIN_OBSERVATION_INTERVAL bucket: an aggregate less than that after the
+    // latest observation replaces its price and keeps the bucket start, so per-slot aggregates
+    // cannot flush the buffer before the 1h window is covered
+    pub fn record(&mut self, price: u64, timestamp: i64, slot: u64) {
+        if self.count > 0 {
+            let latest = self.latest_index();
+            if timestamp < self.observations[latest].timestamp.saturating_add(MIN_OBSERVATION_INTERVAL) {
+                self.observations[latest].price = price;
+                self.last_update_slot = slot;
+                return;
+            }
+        }
+
+        self.observations[self.head as usize] = PriceObservation { price, timestamp };
+        self.head = ((self.head as usize + 1) % PRICE_HISTORY_CAPACITY) as u32;
+        if (self.count as usize) < PRICE_HISTORY_CAPACITY {
+            self.count += 1;
+        }
+        self.last_update_slot = slot;
+    }
+
+    // time-weighted average over [now - window, now]
+    // each observation holds until the next one, the latest holds until now
+    // returns None when the buffer does not reach back to the start of the window
+    pub fn twap(&self, window: i64, now: i64) -> Option<u64> {
+        if window <= 0 {
+            return self.latest().map(|observation| observation.price);
+        }
+
+        let window_start = now.saturating_sub(window);
+        let mut period_end = now;
+        let mut weighted_sum: u128 = 0;
+
+        for i in 0..self.count as usize {
+            let index = (self.head as usize + PRICE_HISTORY_CAPACITY - 1 - i) % PRICE_HISTORY_CAPACITY;
+            let observation = self.observations[index];
+
+            let period_start = observation.timestamp.max(window_start);
+            if period_end > period_start {
+                weighted_sum = weighted_sum.saturating_add(
+                    (observation.price as u128).saturating_mul((period_end - period_start) as u128),
+                );
+            }
+
+            if observation.timestamp <= window_start {
+                return Some((weighted_sum / window as u128) as u64);
+            }
+            period_end = observation.timestamp;
+        }
+
+        None
+    }
+}
+
+
+// price selection per call site
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PriceSource {
+    Spot,
+    Twap(i64),
+}
+
+impl PriceSource {
+    // 0 = spot, 1 = 5m TWAP, 2 = 1h TWAP, 3 = custom window (u32 seconds follows)
+    // returns the source and the number of bytes read
+    pub fn parse(data: &[u8]) -> Result<(Self, usize), ProgramError> {
+        if data.is_empty() {
+            return Ok((PriceSource::Spot, 0));
+        }
+
+        match data[0] {
+            0 => Ok((PriceSource::Spot, 1)),
+            1 => Ok((PriceSource::Twap(TWAP_WINDOW_5M), 1)),
+            2 => Ok((PriceSource::Twap(TWAP_WINDOW_1H), 1)),
+            3 => {
+                if data.len() < 5 {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                let window = u32::from_le_bytes(data[1..5].try_into().unwrap()) as i64;
+                if window == 0 || window > MAX_TWAP_WINDOW {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+                Ok((PriceSource::Twap(window), 5))
+            },
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+
+    // resolves the price for this source, spot is the fresh doppler aggregate
+    pub fn resolve(
+        &self,
+        spot_price: u64,
+        price_history_account: &AccountInfo,
+        doppler_oracle: &Pubkey,
+        now: i64,
+    ) -> Result<u64, ProgramError> {
+        match self {
+            PriceSource::Spot => Ok(spot_price),
+            PriceSource::Twap(window) => {
+                DopplerPriceHistory::verify_address(price_history_account, doppler_oracle)?;
+                let history_data = price_history_account.try_borrow_data()?;
+                let history = DopplerPriceHistory::load(&history_data)?;
+                if history.is_initialized == 0 {
+                    return Err(ProgramError::UninitializedAccount);
+                }
+                history.twap(*window, now).ok_or(ProgramError::InvalidAccountData)
+            },
+        }
+    }
+}
@@ -0,0 +1,170 @@
+// per-strategy yield accounting
+// one record per whitelisted strategy, keyed by its strategy state, with harvest totals,
+// timestamp and APR snapshot, created empty when the strategy is whitelisted
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    program::invoke_signed,
+    instruction::{AccountMeta, Instruction, Seed, Signer},
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::ProgramAccount;
+
+
+pub const STRATEGY_YIELD_RECORD_SEED: &[u8] = b"strategy_yield";
+
+// harvest instruction on both strategy managers
+pub const STRATEGY_MANAGER_HARVEST_IX: u8 = 4;
+
+pub const ASSET_SOL: u8 = 1;
+pub const ASSET_USDC: u8 = 2;
+
+pub const SECONDS_PER_YEAR: u64 = 31_536_000;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct StrategyYieldRecord {
+    // exchange rate of a position's receipt token at the last collection (Kamino cTokens), WAD scaled
+    pub position_rate_snapshot: u128,
+    // strategy manager program and asset of the strategy, whichever harvest or collection wrote first
+    pub strategy_program: Pubkey,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub asset_type: u8,
+    pub _padding: [u8; 5],
+    // USDC, 6 decimals
+    pub total_yield: u64,
+    pub last_yield: u64,
+    pub last_harvest_timestamp: i64,
+    // APR of the last harvest against the principal at that time
+    pub last_apr_bps: u64,
+    pub last_principal_usd: u64,
+    pub harvest_count: u64,
+    // records of a single external position: principal left in it after the last collection
+    // (cTokens for Kamino) and the position's own deposit counter at that point
+    pub position_principal: u64,
+    pub position_deposit_snapshot: i64,
+}
+
+impl ProgramAccount for StrategyYieldRecord {}
+
+impl StrategyYieldRecord {
+    pub fn verify_address(
+        record_account: &AccountInfo,
+        strategy_state: &Pubkey,
+    ) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[STRATEGY_YIELD_RECORD_SEED, strategy_state.as_ref()],
+            &crate::ID,
+        );
+        if record_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if !record_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        Ok(bump)
+    }
+
+    // same fields from every writer, taken from the strategy's registry
+    pub fn initialize(&mut self, strategy_program: &Pubkey, asset_type: u8, bump: u8) {
+        self.strategy_program = *strategy_program;
+        self.asset_type = asset_type;
+        self.bump = bump;
+        self.is_initialized = 1;
+    }
+
+    // a never-harvested strategy is always due
+    pub fn is_due(&self, now: i64, frequency: u64) -> bool {
+        self.last_harvest_timestamp == 0
+            || now.saturating_sub(self.last_harvest_timestamp) >= frequency as i64
+    }
+
+    pub fn record_harvest(&mut self, yield_amount: u64, principal_usd: u64, now: i64) {
+        let elapsed = if self.last_harvest_timestamp == 0 {
+            0
+        } else {
+            now.saturating_sub(self.last_harvest_timestamp) as u64
+        };
+
+        self.last_apr_bps = annualized_rate_bps(yield_amount, principal_usd, elapsed);
+        self.last_yield = yield_amount;
+        self.last_principal_usd = principal_usd;
+        self.total_yield = self.total_yield.saturating_add(yield_amount);
+        self.last_harvest_timestamp = now;
+        self.harvest_count = self.harvest_count.saturating_add(1);
+    }
+}
+
+
+// yield over elapsed seconds, annualized in bps of the principal
+pub fn annualized_rate_bps(yield_amount: u64, principal: u64, elapsed: u64) -> u64 {
+    if principal == 0 || elapsed == 0 {
+        return 0;
+    }
+    ((yield_amount as u128)
+        .saturating_mul(SECONDS_PER_YEAR as u128)
+        .saturating_mul(10_000u128)
+        / (principal as u128).saturating_mul(elapsed as u128))
+        .min(u64::MAX as u128) as u64
+}
+
+
+pub fn token_account_amount(token_account: &AccountInfo) -> Result<u64, ProgramError> {
+    let token = pinocchio_token::state::TokenAccount::from_account_info(token_account)?;
+    Ok(token.amount())
+}
+
+// yield is measured on the vault balance, so it has to be the controller's USDC account
+pub fn verify_yields_vault(
+    yields_vault: &AccountInfo,
+    protocol_controller: &Pubkey,
+    usdc_mint: &Pubkey,
+) -> Result<(), ProgramError> {
+    let vault = pinocchio_token::state::TokenAccount::from_account_info(yields_vault)?;
+    if vault.owner() != protocol_controller || vault.mint() != usdc_mint {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+
+// harvest CPI into a single strategy manager, signed by the protocol controller PDA
+// yield lands in the yields vault
+pub fn invoke_strategy_harvest(
+    strategy_program: &AccountInfo,
+    protocol_controller: &AccountInfo,
+    strategy_state: &AccountInfo,
+    yields_vault: &AccountInfo,
+    token_program: &AccountInfo,
+    protocol_controller_bump: u8,
+) -> Result<(), ProgramError> {
+    let account_metas = [
+        AccountMeta::readonly_signer(protocol_controller.key()),
+        AccountMeta::writable(strategy_state.key()),
+        AccountMeta::writable(yields_vault.key()),
+        AccountMeta::readonly(token_program.key()),
+    ];
+
+    let instruction = Instruction {
+        program_id: strategy_program.key(),
+        accounts: &account_metas,
+        data: &[STRATEGY_MANAGER_HARVEST_IX],
+    };
+
+    let bump_seed = [protocol_controller_bump];
+    let seeds = [
+        Seed::from(crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED),
+        Seed::from(&bump_seed),
+    ];
+
+    invoke_signed(
+        &instruction,
+        &[protocol_controller, strategy_state, yields_vault, token_program],
+        &[Signer::from(&seeds)],
+    )
+}
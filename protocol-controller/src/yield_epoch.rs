@@ -0,0 +1,280 @@
+// yield epochs
+// every closed Thaler period becomes an epoch with a merkle root of per-user entitlements,
+// users pull their Thalers with a proof instead of the keeper pushing to every freezer
+
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    msg,
+    clock::Clock,
+    instruction::Seed,
+};
+use bytemuck::{Pod, Zeroable};
+
+use crate::program_account::{ProgramAccount, create_program_account};
+use crate::thaler_distribution::{ThalerDistribution, invoke_thaler_transfer};
+
+
+pub const YIELD_EPOCH_SEED: &[u8] = b"yield_epoch";
+
+// one claim bit per leaf
+pub const MAX_EPOCH_LEAVES: usize = 8_192;
+pub const MAX_PROOF_DEPTH: usize = 14;
+
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct YieldEpoch {
+    // TWAB registry cumulative at the epoch bounds
+    pub start_twab: u128,
+    pub end_twab: u128,
+    pub merkle_root: [u8; 32],
+    pub epoch: u64,
+    // Thaler base units
+    pub total_amount: u64,
+    pub claimed_amount: u64,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+    pub closed_at: i64,
+    pub leaf_count: u32,
+    pub is_initialized: u8,
+    pub bump: u8,
+    pub _padding: [u8; 10],
+    pub claimed_bitmap: [u8; MAX_EPOCH_LEAVES / 8],
+}
+
+impl ProgramAccount for YieldEpoch {}
+
+impl YieldEpoch {
+    pub fn verify_address(epoch_account: &AccountInfo, epoch: u64) -> Result<u8, ProgramError> {
+        let (expected_address, bump) = Pubkey::find_program_address(
+            &[YIELD_EPOCH_SEED, &epoch.to_le_bytes()],
+            &crate::ID,
+        );
+        if epoch_account.key() != &expected_address {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        Ok(bump)
+    }
+
+    pub fn is_claimed(&self, leaf_index: u32) -> bool {
+        self.claimed_bitmap[leaf_index as usize / 8] & (1 << (leaf_index % 8)) != 0
+    }
+
+    pub fn set_claimed(&mut self, leaf_index: u32) {
+        self.claimed_bitmap[leaf_index as usize / 8] |= 1 << (leaf_index % 8);
+    }
+}
+
+
+// close the last Thaler period as an epoch and commit its entitlement root
+// the keeper builds the tree off-chain from the period and the TWAB ledgers
+// (see merkle::entitlements_for_period), every ledger gets a leaf, zero amounts included
+pub fn close_yield_epoch(
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("closing yield epoch");
+
+    // merkle root (32), leaf count (4)
+    if data.len() < 36 {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol parameters, 1 = authority (payer), 2 = thaler distribution,
+    // 3 = thaler mint, 4 = yield epoch, 5 = system program
+    if accounts.len() < 6 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let merkle_root: [u8; 32] = data[0..32].try_into().unwrap();
+    let leaf_count = u32::from_le_bytes(data[32..36].try_into().unwrap());
+    if leaf_count == 0 || leaf_count as usize > MAX_EPOCH_LEAVES {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let authority_account = &accounts[1];
+    crate::parameters::read_protocol_parameters(&accounts[0])?.require_authority(authority_account)?;
+
+    ThalerDistribution::verify_address(&accounts[2])?;
+    let mut distribution_data = accounts[2].try_borrow_mut_data()?;
+    let distribution = ThalerDistribution::load_mut(&mut distribution_data)?;
+
+    // one epoch per closed Thaler period, in order
+    let epoch = distribution.distribution_count;
+    if epoch == 0 || distribution.last_closed_epoch >= epoch {
+        msg!("no open Thaler period to close");
+        return Err(crate::error::ProtocolControllerError::CoordinationOperationMismatch.into());
+    }
+    if accounts[3].key() != &distribution.thaler_mint {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let decimals = pinocchio_token::state::Mint::from_account_info(&accounts[3])?.decimals();
+    let total_amount = distribution.period_amount(decimals).ok_or(ProgramError::ArithmeticOverflow)?;
+
+    let epoch_account = &accounts[4];
+    let epoch_bump = YieldEpoch::verify_address(epoch_account, epoch)?;
+    let epoch_seed = epoch.to_le_bytes();
+    let bump_seed = [epoch_bump];
+    create_program_account(
+        authority_account,
+        epoch_account,
+        YieldEpoch::LEN,
+        &[Seed::from(YIELD_EPOCH_SEED), Seed::from(&epoch_seed), Seed::from(&bump_seed)],
+    )?;
+
+    let mut epoch_data = epoch_account.try_borrow_mut_data()?;
+    let yield_epoch = YieldEpoch::load_mut(&mut epoch_data)?;
+    yield_epoch.start_twab = distribution.period_start_twab;
+    yield_epoch.end_twab = distribution.period_end_twab;
+    yield_epoch.merkle_root = merkle_root;
+    yield_epoch.epoch = epoch;
+    yield_epoch.total_amount = total_amount;
+    yield_epoch.start_timestamp = distribution.period_start_timestamp;
+    yield_epoch.end_timestamp = distribution.period_end_timestamp;
+    yield_epoch.closed_at = Clock::get()?.unix_timestamp;
+    yield_epoch.leaf_count = leaf_count;
+    yield_epoch.bump = epoch_bump;
+    yield_epoch.is_initialized = 1;
+
+    distribution.last_closed_epoch = epoch;
+
+    msg!("epoch {} closed with {} entitlements", epoch, leaf_count);
+    msg!("epoch Thalers (base units): {}", total_amount);
+
+    Ok(())
+}
+
+
+// pull a user's Thalers for an epoch with a merkle proof
+// claims go in strict epoch order, only the epoch right after the ledger's last settled one,
+// so settling it never skips an earlier epoch that is still unclaimed; a zero leaf only moves
+// the ledger past the epoch
+pub fn claim_epoch_thalers(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> Result<(), ProgramError> {
+    msg!("claiming epoch Thalers");
+
+    // epoch (8), leaf index (4), amount (8), proof length (1), proof (32 each)
+    if data.len() < 21 {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    // 0 = protocol controller, 1 = user, 2 = thaler distribution, 3 = yield epoch,
+    // 4 = thaler distribution vault, 5 = user Thaler token account, 6 = token program,
+    // 7 = user TWAB ledger
+    if accounts.len() < 8 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+
+    let epoch = u64::from_le_bytes(data[0..8].try_into().unwrap());
+    let leaf_index = u32::from_le_bytes(data[8..12].try_into().unwrap());
+    let amount = u64::from_le_bytes(data[12..20].try_into().unwrap());
+    let proof_length = data[20] as usize;
+
+    if proof_length > MAX_PROOF_DEPTH || data.len() < 21 + 32 * proof_length {
+        return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+    }
+
+    let mut proof = [[0u8; 32]; MAX_PROOF_DEPTH];
+    for (i, node) in proof.iter_mut().take(proof_length).enumerate() {
+        node.copy_from_slice(&data[21 + 32 * i..53 + 32 * i]);
+    }
+
+    let user_account = &accounts[1];
+    if !user_account.is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let distribution = {
+        ThalerDistribution::verify_address(&accounts[2])?;
+        let distribution_data = accounts[2].try_borrow_data()?;
+        *ThalerDistribution::load(&distribution_data)?
+    };
+    if accounts[4].key() != &distribution.distribution_vault {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    {
+        let recipient = pinocchio_token::state::TokenAccount::from_account_info(&accounts[5])?;
+        if recipient.owner() != user_account.key() || recipient.mint() != &distribution.thaler_mint {
+            return Err(ProgramError::InvalidAccountData);
+        }
+    }
+
+    // an unfreeze may already have settled this epoch from the ledger
+    crate::twab::TwabLedger::verify_address(&accounts[7], user_account.key())?;
+    if !accounts[7].is_owned_by(&crate::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let mut ledger_data = accounts[7].try_borrow_mut_data()?;
+    let ledger = crate::twab::TwabLedger::load_mut(&mut ledger_data)?;
+    if epoch <= ledger.last_settled_distribution {
+        msg!("epoch {} already settled", epoch);
+        return Err(ProgramError::InvalidArgument);
+    }
+    if epoch > ledger.last_settled_distribution + 1 {
+        msg!("epoch {} has to be claimed first", ledger.last_settled_distribution + 1);
+        return Err(crate::error::ProtocolControllerError::CoordinationOperationMismatch.into());
+    }
+
+    {
+        let epoch_account = &accounts[3];
+        YieldEpoch::verify_address(epoch_account, epoch)?;
+        if !epoch_account.is_owned_by(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        let mut epoch_data = epoch_account.try_borrow_mut_data()?;
+        let yield_epoch = YieldEpoch::load_mut(&mut epoch_data)?;
+
+        if leaf_index >= yield_epoch.leaf_count {
+            return Err(crate::error::ProtocolControllerError::ParameterValidationFailed.into());
+        }
+        if yield_epoch.is_claimed(leaf_index) {
+            msg!("entitlement already claimed");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let leaf = crate::merkle::entitlement_leaf(epoch, leaf_index, user_account.key(), amount);
+        if !crate::merkle::verify_proof(leaf, &proof[..proof_length], &yield_epoch.merkle_root) {
+            msg!("invalid merkle proof");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let claimed_amount = yield_epoch.claimed_amount
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        if claimed_amount > yield_epoch.total_amount {
+            return Err(ProgramError::InsufficientFunds);
+        }
+
+        yield_epoch.set_claimed(leaf_index);
+        yield_epoch.claimed_amount = claimed_amount;
+        ledger.last_settled_timestamp = yield_epoch.end_timestamp;
+    }
+
+    ledger.last_settled_distribution = epoch;
+
+    if amount > 0 {
+        let (_, protocol_controller_bump) = Pubkey::find_program_address(
+            &[crate::constants::pda_seeds::PROTOCOL_CONTROLLER_SEED],
+            &crate::ID,
+        );
+
+        invoke_thaler_transfer(
+            &accounts[4],
+            &accounts[5],
+            &accounts[0],
+            amount,
+            protocol_controller_bump,
+        )?;
+    }
+
+    msg!("epoch {} claim: {} Thaler base units", epoch, amount);
+
+    Ok(())
+}
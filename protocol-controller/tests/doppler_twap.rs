@@ -0,0 +1,56 @@
+// Doppler price history
+// aggregates every slot have to leave enough history for the 5 min and 1h TWAPs
+
+use bytemuck::Zeroable;
+
+use protocol_controller::{
+    DopplerPriceHistory, MIN_OBSERVATION_INTERVAL, PRICE_HISTORY_CAPACITY, TWAP_WINDOW_1H, TWAP_WINDOW_5M,
+};
+
+// 400ms slots, timestamps in whole seconds
+fn record_every_slot(history: &mut DopplerPriceHistory, from_slot: u64, slots: u64, price: u64) -> i64 {
+    let mut timestamp = 0;
+    for slot in from_slot..from_slot + slots {
+        timestamp = (slot * 2 / 5) as i64;
+        history.record(price, timestamp, slot);
+    }
+    timestamp
+}
+
+
+#[test]
+fn per_slot_aggregates_keep_the_hour_window() {
+    let mut history = DopplerPriceHistory::zeroed();
+    // a little more than an hour of slots
+    let now = record_every_slot(&mut history, 0, 9_100, 150_000_000);
+
+    assert!((history.count as usize) < PRICE_HISTORY_CAPACITY);
+    assert_eq!(history.twap(TWAP_WINDOW_5M, now), Some(150_000_000));
+    assert_eq!(history.twap(TWAP_WINDOW_1H, now), Some(150_000_000));
+}
+
+#[test]
+fn aggregates_within_a_bucket_replace_the_latest_price() {
+    let mut history = DopplerPriceHistory::zeroed();
+    history.record(100, 1_000, 1);
+    history.record(110, 1_000 + MIN_OBSERVATION_INTERVAL - 1, 2);
+
+    assert_eq!(history.count, 1);
+    let latest = history.latest().unwrap();
+    assert_eq!((latest.price, latest.timestamp), (110, 1_000));
+
+    history.record(120, 1_000 + MIN_OBSERVATION_INTERVAL, 3);
+    assert_eq!(history.count, 2);
+    assert_eq!(history.previous().unwrap().price, 110);
+}
+
+#[test]
+fn full_buffer_reaches_back_past_the_hour() {
+    let mut history = DopplerPriceHistory::zeroed();
+    record_every_slot(&mut history, 0, 30_000, 100);
+    let now = record_every_slot(&mut history, 30_000, 1_000, 200);
+
+    assert_eq!(history.count as usize, PRICE_HISTORY_CAPACITY);
+    let twap = history.twap(TWAP_WINDOW_1H, now).unwrap();
+    assert!(twap > 100 && twap < 200);
+}
@@ -0,0 +1,333 @@
+// collect_drift_usdc_yields_to_vault (16)
+// Drift layouts are mocked byte for byte, the strategy manager's withdrawal is the drift-stand-in
+// program (tests/programs/drift-stand-in), built with cargo build-sbf before running these
+
+use mollusk::{result::InstructionResult, Mollusk};
+use solana_account::Account;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+
+use protocol_controller::{
+    drift_principal, drift_spot_deposit, split_realized_yield, verify_vault_delta,
+    ProgramAccount, StrategyRegistry, StrategyYieldRecord, PROTOCOL_PARAMETERS_SEED,
+    DRIFT_PROGRAM_ID, DRIFT_SPOT_MARKET_CUMULATIVE_DEPOSIT_INTEREST_OFFSET, DRIFT_SPOT_MARKET_DECIMALS_OFFSET,
+    DRIFT_SPOT_MARKET_MARKET_INDEX_OFFSET, DRIFT_SPOT_MARKET_MINT_OFFSET, DRIFT_SPOT_POSITION_BALANCE_TYPE_OFFSET,
+    DRIFT_SPOT_POSITION_CUMULATIVE_DEPOSITS_OFFSET, DRIFT_SPOT_POSITION_MARKET_INDEX_OFFSET,
+    DRIFT_SPOT_POSITION_SIZE, DRIFT_USER_AUTHORITY_OFFSET, DRIFT_USER_SPOT_POSITIONS_OFFSET,
+    DRIFT_USDC_MARKET_INDEX, STRATEGY_REGISTRY_SEED, STRATEGY_STATUS_ACTIVE, STRATEGY_YIELD_RECORD_SEED,
+    ASSET_SOL,
+};
+
+const INITIALIZE_PROTOCOL: u8 = 0;
+const COLLECT_DRIFT_YIELD: u8 = 16;
+const WHITELIST_STRATEGY: u8 = 50;
+
+const DRIFT_USER_LEN: usize = 4376;
+const DRIFT_SPOT_MARKET_LEN: usize = 776;
+
+// $10,000 deposited at an interest index of 1.0, now at 1.05
+const PRINCIPAL: u64 = 10_000_000_000;
+const SCALED_BALANCE: u64 = 10_000_000_000_000;
+const INTEREST_AT_DEPOSIT: u128 = 10_000_000_000;
+const INTEREST_NOW: u128 = 10_500_000_000;
+const EXPECTED_YIELD: u64 = 500_000_000;
+
+
+fn drift_user(authority: &[u8; 32], scaled_balance: u64, cumulative_deposits: i64, balance_type: u8) -> Vec<u8> {
+    let mut data = vec![0u8; DRIFT_USER_LEN];
+    data[DRIFT_USER_AUTHORITY_OFFSET..DRIFT_USER_AUTHORITY_OFFSET + 32].copy_from_slice(authority);
+
+    // the USDC position sits in the second slot, the first one holds SOL
+    let sol_position = DRIFT_USER_SPOT_POSITIONS_OFFSET;
+    data[sol_position..sol_position + 8].copy_from_slice(&42u64.to_le_bytes());
+    data[sol_position + DRIFT_SPOT_POSITION_MARKET_INDEX_OFFSET..sol_position + DRIFT_SPOT_POSITION_MARKET_INDEX_OFFSET + 2]
+        .copy_from_slice(&1u16.to_le_bytes());
+
+    let position = DRIFT_USER_SPOT_POSITIONS_OFFSET + DRIFT_SPOT_POSITION_SIZE;
+    data[position..position + 8].copy_from_slice(&scaled_balance.to_le_bytes());
+    data[position + DRIFT_SPOT_POSITION_CUMULATIVE_DEPOSITS_OFFSET..position + DRIFT_SPOT_POSITION_CUMULATIVE_DEPOSITS_OFFSET + 8]
+        .copy_from_slice(&cumulative_deposits.to_le_bytes());
+    data[position + DRIFT_SPOT_POSITION_MARKET_INDEX_OFFSET..position + DRIFT_SPOT_POSITION_MARKET_INDEX_OFFSET + 2]
+        .copy_from_slice(&DRIFT_USDC_MARKET_INDEX.to_le_bytes());
+    data[position + DRIFT_SPOT_POSITION_BALANCE_TYPE_OFFSET] = balance_type;
+    data
+}
+
+fn drift_spot_market(market_index: u16, cumulative_deposit_interest: u128) -> Vec<u8> {
+    let mut data = vec![0u8; DRIFT_SPOT_MARKET_LEN];
+    data[DRIFT_SPOT_MARKET_CUMULATIVE_DEPOSIT_INTEREST_OFFSET..DRIFT_SPOT_MARKET_CUMULATIVE_DEPOSIT_INTEREST_OFFSET + 16]
+        .copy_from_slice(&cumulative_deposit_interest.to_le_bytes());
+    data[DRIFT_SPOT_MARKET_DECIMALS_OFFSET..DRIFT_SPOT_MARKET_DECIMALS_OFFSET + 4].copy_from_slice(&6u32.to_le_bytes());
+    data[DRIFT_SPOT_MARKET_MARKET_INDEX_OFFSET..DRIFT_SPOT_MARKET_MARKET_INDEX_OFFSET + 2]
+        .copy_from_slice(&market_index.to_le_bytes());
+    data
+}
+
+// the yields vault is checked against the spot market's mint
+fn usdc_spot_market(usdc_mint: &Pubkey) -> Vec<u8> {
+    let mut data = drift_spot_market(DRIFT_USDC_MARKET_INDEX, INTEREST_NOW);
+    data[DRIFT_SPOT_MARKET_MINT_OFFSET..DRIFT_SPOT_MARKET_MINT_OFFSET + 32].copy_from_slice(usdc_mint.as_ref());
+    data
+}
+
+
+#[test]
+fn reads_usdc_deposit_from_drift_layouts() {
+    let user = drift_user(&[7u8; 32], SCALED_BALANCE, PRINCIPAL as i64, 0);
+
+    let at_deposit = drift_spot_deposit(&user, &drift_spot_market(0, INTEREST_AT_DEPOSIT), 0).unwrap();
+    assert_eq!(at_deposit.token_amount, PRINCIPAL);
+    assert_eq!(at_deposit.cumulative_deposits, PRINCIPAL as i64);
+
+    let now = drift_spot_deposit(&user, &drift_spot_market(0, INTEREST_NOW), 0).unwrap();
+    assert_eq!(now.token_amount, PRINCIPAL + EXPECTED_YIELD);
+}
+
+#[test]
+fn rejects_spot_market_of_another_index() {
+    let user = drift_user(&[7u8; 32], SCALED_BALANCE, PRINCIPAL as i64, 0);
+    assert!(drift_spot_deposit(&user, &drift_spot_market(1, INTEREST_NOW), 0).is_err());
+}
+
+#[test]
+fn borrow_position_holds_no_yield() {
+    let user = drift_user(&[7u8; 32], SCALED_BALANCE, 0, 1);
+    let deposit = drift_spot_deposit(&user, &drift_spot_market(0, INTEREST_NOW), 0).unwrap();
+    assert_eq!(deposit.token_amount, 0);
+}
+
+#[test]
+fn principal_follows_deposits_and_withdrawals() {
+    assert_eq!(drift_principal(PRINCIPAL, 10_000, 12_000), PRINCIPAL + 2_000);
+    assert_eq!(drift_principal(PRINCIPAL, 10_000, 7_000), PRINCIPAL - 3_000);
+    assert_eq!(drift_principal(1_000, 10_000, 0), 0);
+}
+
+#[test]
+fn splits_yield_from_principal() {
+    assert_eq!(split_realized_yield(PRINCIPAL + EXPECTED_YIELD, PRINCIPAL), (EXPECTED_YIELD, PRINCIPAL));
+    // a position under water has nothing to collect
+    assert_eq!(split_realized_yield(PRINCIPAL - 1, PRINCIPAL), (0, PRINCIPAL - 1));
+}
+
+#[test]
+fn vault_delta_has_to_match_yield() {
+    assert_eq!(verify_vault_delta(100, 600, 500).unwrap(), 500);
+    assert!(verify_vault_delta(100, 500, 500).is_err());
+    assert!(verify_vault_delta(100, 700, 500).is_err());
+    assert!(verify_vault_delta(600, 100, 0).is_err());
+}
+
+
+// mollusk: controller → drift-stand-in (in place of the SOL strategy manager) → token program
+// the registry and yield record come from initialize_protocol and whitelist_strategy
+
+struct Setup {
+    mollusk: Mollusk,
+    instructions: Vec<Instruction>,
+    accounts: Vec<(Pubkey, Account)>,
+    yields_vault: Pubkey,
+    registry: Pubkey,
+    record: Pubkey,
+    controller: Pubkey,
+}
+
+fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    Account {
+        lamports: 2_039_280,
+        data,
+        owner: spl_token::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn program_account(owner: &Pubkey, data: Vec<u8>) -> Account {
+    Account {
+        lamports: 10_000_000,
+        data,
+        owner: *owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn setup(reserve_balance: u64, drift_user_authority: Option<Pubkey>) -> Setup {
+    let program_id = Pubkey::new_from_array(protocol_controller::ID);
+    let strategy_program = Pubkey::new_from_array(sol_strategy_manager::ID);
+    let drift_program = Pubkey::new_from_array(DRIFT_PROGRAM_ID);
+
+    let mut mollusk = Mollusk::new(&program_id, "protocol_controller");
+    mollusk.add_program(&strategy_program, "drift_stand_in", &mollusk::program::loader_keys::LOADER_V3);
+    mollusk_svm_programs_token::token::add_program(&mut mollusk);
+    let (system_program, system_program_account) = mollusk::program::keyed_account_for_system_program();
+
+    // same seed as constants::pda_seeds::PROTOCOL_CONTROLLER_SEED
+    let (controller, _) = Pubkey::find_program_address(&[b"protocol_controller"], &program_id);
+    let (parameters, _) = Pubkey::find_program_address(&[PROTOCOL_PARAMETERS_SEED], &program_id);
+    let authority = Pubkey::new_unique();
+    let strategy_state = Pubkey::new_unique();
+    let (registry, _) =
+        Pubkey::find_program_address(&[STRATEGY_REGISTRY_SEED, strategy_state.as_ref()], &program_id);
+    let (record, _) =
+        Pubkey::find_program_address(&[STRATEGY_YIELD_RECORD_SEED, strategy_state.as_ref()], &program_id);
+    let (stand_in_authority, _) = Pubkey::find_program_address(&[b"drift_stand_in"], &strategy_program);
+
+    let usdc_mint = Pubkey::new_unique();
+    let drift_user_key = Pubkey::new_unique();
+    let spot_market_key = Pubkey::new_unique();
+    let yields_vault = Pubkey::new_unique();
+    let reserve = Pubkey::new_unique();
+
+    let drift_authority = drift_user_authority.unwrap_or(strategy_state);
+
+    // initialize_protocol does not create the controller account yet
+    let accounts = vec![
+        (controller, program_account(&program_id, vec![0u8; std::mem::size_of::<protocol_controller::ProtocolController>()])),
+        (authority, Account { lamports: 10_000_000_000, ..Account::default() }),
+        (parameters, Account::default()),
+        (system_program, system_program_account),
+        (registry, Account::default()),
+        (record, Account::default()),
+        (strategy_program, mollusk::program::create_program_account_loader_v3(&strategy_program)),
+        (strategy_state, program_account(&strategy_program, vec![0u8; 64])),
+        (drift_user_key, program_account(&drift_program, drift_user(&drift_authority.to_bytes(), SCALED_BALANCE, PRINCIPAL as i64, 0))),
+        (spot_market_key, program_account(&drift_program, usdc_spot_market(&usdc_mint))),
+        (yields_vault, token_account(&usdc_mint, &controller, 0)),
+        mollusk_svm_programs_token::token::keyed_account(),
+        (reserve, token_account(&usdc_mint, &stand_in_authority, reserve_balance)),
+        (stand_in_authority, Account::default()),
+    ];
+
+    // program addresses (5 × 32), controller, authority, parameters, system program, then unused slots
+    let mut initialize_protocol_accounts = vec![
+        AccountMeta::new(controller, false),
+        AccountMeta::new(authority, true),
+        AccountMeta::new(parameters, false),
+        AccountMeta::new_readonly(system_program, false),
+    ];
+    initialize_protocol_accounts.extend((0..6).map(|_| AccountMeta::new_readonly(system_program, false)));
+    let mut initialize_protocol_data = vec![INITIALIZE_PROTOCOL];
+    initialize_protocol_data.extend_from_slice(&[0u8; 192]);
+
+    // strategy state, asset, risk tier, cap
+    let mut whitelist_data = vec![WHITELIST_STRATEGY];
+    whitelist_data.extend_from_slice(strategy_state.as_ref());
+    whitelist_data.extend_from_slice(&[ASSET_SOL, 2]);
+    whitelist_data.extend_from_slice(&u64::MAX.to_le_bytes());
+
+    let whitelist = Instruction::new_with_bytes(
+        program_id,
+        &whitelist_data,
+        vec![
+            AccountMeta::new_readonly(parameters, false),
+            AccountMeta::new(authority, true),
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(strategy_program, false),
+            AccountMeta::new_readonly(system_program, false),
+            AccountMeta::new(record, false),
+        ],
+    );
+
+    let collect = Instruction::new_with_bytes(
+        program_id,
+        &[COLLECT_DRIFT_YIELD],
+        vec![
+            AccountMeta::new(controller, false),
+            AccountMeta::new(registry, false),
+            AccountMeta::new(record, false),
+            AccountMeta::new_readonly(strategy_program, false),
+            AccountMeta::new(strategy_state, false),
+            AccountMeta::new(drift_user_key, false),
+            AccountMeta::new(spot_market_key, false),
+            AccountMeta::new(yields_vault, false),
+            AccountMeta::new_readonly(spl_token::ID, false),
+            AccountMeta::new(reserve, false),
+            AccountMeta::new_readonly(stand_in_authority, false),
+        ],
+    );
+
+    let instructions = vec![
+        Instruction::new_with_bytes(program_id, &initialize_protocol_data, initialize_protocol_accounts),
+        whitelist,
+        collect,
+    ];
+
+    Setup { mollusk, instructions, accounts, yields_vault, registry, record, controller }
+}
+
+impl Setup {
+    fn run(&self) -> InstructionResult {
+        self.mollusk.process_instruction_chain(&self.instructions, &self.accounts)
+    }
+}
+
+fn account<'a>(accounts: &'a [(Pubkey, Account)], key: &Pubkey) -> &'a Account {
+    &accounts.iter().find(|(k, _)| k == key).unwrap().1
+}
+
+
+#[test]
+fn collects_yield_and_leaves_principal_in_drift() {
+    let setup = setup(PRINCIPAL * 2, None);
+    let result = setup.run();
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+
+    let registry = StrategyRegistry::load(&account(&result.resulting_accounts, &setup.registry).data).unwrap();
+    assert_eq!(registry.status, STRATEGY_STATUS_ACTIVE);
+
+    let vault = spl_token::state::Account::unpack(&account(&result.resulting_accounts, &setup.yields_vault).data).unwrap();
+    assert_eq!(vault.amount, EXPECTED_YIELD);
+
+    let record_data = &account(&result.resulting_accounts, &setup.record).data;
+    let record = StrategyYieldRecord::load(record_data).unwrap();
+    assert_eq!(record.total_yield, EXPECTED_YIELD);
+    assert_eq!(record.last_yield, EXPECTED_YIELD);
+    assert_eq!(record.position_principal, PRINCIPAL);
+    assert_eq!(record.harvest_count, 1);
+    // same fields harvest_all_yield writes for the SOL strategy
+    assert_eq!(record.strategy_program, sol_strategy_manager::ID);
+    assert_eq!(record.asset_type, ASSET_SOL);
+
+    let controller_data = &account(&result.resulting_accounts, &setup.controller).data;
+    let controller = bytemuck::from_bytes::<protocol_controller::ProtocolController>(controller_data);
+    assert_eq!(controller.total_yield_harvested, EXPECTED_YIELD);
+}
+
+#[test]
+fn fails_when_vault_delta_falls_short_of_yield() {
+    // the stand-in pays out what its reserve holds, less than the realized yield
+    let setup = setup(EXPECTED_YIELD / 2, None);
+    let result = setup.run();
+    assert!(result.program_result.is_err());
+}
+
+#[test]
+fn refuses_drift_user_of_another_strategy() {
+    let setup = setup(PRINCIPAL * 2, Some(Pubkey::new_unique()));
+    let result = setup.run();
+    assert!(result.program_result.is_err());
+}
+
+#[test]
+fn refuses_a_yields_vault_the_controller_does_not_own() {
+    let mut setup = setup(PRINCIPAL * 2, None);
+    let yields_vault = setup.yields_vault;
+    let vault = setup.accounts.iter_mut().find(|(key, _)| *key == yields_vault).unwrap();
+    let mint = spl_token::state::Account::unpack(&vault.1.data).unwrap().mint;
+    vault.1 = token_account(&mint, &Pubkey::new_unique(), 0);
+
+    assert!(setup.run().program_result.is_err());
+}
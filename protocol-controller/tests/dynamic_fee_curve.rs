@@ -0,0 +1,109 @@
+// dynamic fee curve
+// fees interpolate along the collateral ratio curve, the flow term charges the side of the net flow,
+// a master override replaces both until it expires
+
+use bytemuck::Zeroable;
+
+use protocol_controller::{
+    curve_fees, dynamic_fees, flow_fee_adjustment, is_valid_fee_curve, DynamicFeeCurve, FeeCurvePoint,
+    MAX_DYNAMIC_FEE_BPS, MAX_FEE_CURVE_POINTS,
+};
+
+
+fn point(collateral_ratio_bps: u64, mint_fee_bps: u16, redeem_fee_bps: u16) -> FeeCurvePoint {
+    FeeCurvePoint { collateral_ratio_bps, mint_fee_bps, redeem_fee_bps, _padding: [0; 4] }
+}
+
+fn curve() -> [FeeCurvePoint; 3] {
+    [point(10_000, 100, 300), point(15_000, 30, 50), point(20_000, 10, 10)]
+}
+
+
+#[test]
+fn accepts_an_ordered_falling_curve() {
+    assert!(is_valid_fee_curve(&curve()));
+    assert!(is_valid_fee_curve(&[point(10_000, 0, 0)]));
+}
+
+#[test]
+fn rejects_malformed_curves() {
+    assert!(!is_valid_fee_curve(&[]));
+    assert!(!is_valid_fee_curve(&[point(10_000, 10, 10); MAX_FEE_CURVE_POINTS + 1]));
+    // ratios not strictly increasing
+    assert!(!is_valid_fee_curve(&[point(15_000, 30, 50), point(15_000, 10, 10)]));
+    // fee rising with the ratio
+    assert!(!is_valid_fee_curve(&[point(10_000, 30, 50), point(15_000, 40, 10)]));
+    // over the hard bound
+    assert!(!is_valid_fee_curve(&[point(10_000, MAX_DYNAMIC_FEE_BPS + 1, 10)]));
+}
+
+#[test]
+fn flat_outside_the_curve() {
+    assert_eq!(curve_fees(&curve(), 9_000), (100, 300));
+    assert_eq!(curve_fees(&curve(), 25_000), (10, 10));
+}
+
+#[test]
+fn interpolates_between_points() {
+    assert_eq!(curve_fees(&curve(), 12_500), (65, 175));
+    assert_eq!(curve_fees(&curve(), 15_000), (30, 50));
+}
+
+#[test]
+fn flow_term_charges_the_side_of_the_flow() {
+    // 10% of TVL minted on net, 500 bps per 100%
+    assert_eq!(flow_fee_adjustment(1_000, 10_000, 500), (50, 0));
+    assert_eq!(flow_fee_adjustment(-1_000, 10_000, 500), (0, 50));
+    assert_eq!(flow_fee_adjustment(0, 10_000, 500), (0, 0));
+    assert_eq!(flow_fee_adjustment(1_000, 0, 500), (0, 0));
+    assert_eq!(flow_fee_adjustment(1_000_000, 10_000, 10_000), (MAX_DYNAMIC_FEE_BPS, 0));
+}
+
+#[test]
+fn dynamic_fees_stay_within_the_bound() {
+    assert_eq!(dynamic_fees(&curve(), 25_000, 1_000, 10_000, 500), (60, 10));
+
+    let steep = [point(10_000, MAX_DYNAMIC_FEE_BPS, MAX_DYNAMIC_FEE_BPS)];
+    assert_eq!(
+        dynamic_fees(&steep, 10_000, -1_000, 10_000, 500),
+        (MAX_DYNAMIC_FEE_BPS, MAX_DYNAMIC_FEE_BPS)
+    );
+}
+
+
+fn curve_account(mint_fee_bps: u16, redeem_fee_bps: u16) -> DynamicFeeCurve {
+    let mut curve = DynamicFeeCurve::zeroed();
+    curve.mint_fee_bps = mint_fee_bps;
+    curve.redeem_fee_bps = redeem_fee_bps;
+    curve.is_initialized = 1;
+    curve
+}
+
+#[test]
+fn curve_fees_without_an_override() {
+    let curve = curve_account(30, 50);
+    assert_eq!(curve.active_override(100), None);
+    assert_eq!(curve.effective_fees(100), (30, 50));
+}
+
+#[test]
+fn override_replaces_the_curve_until_expiry() {
+    let mut curve = curve_account(30, 50);
+    curve.fee_override.mint_fee_bps = 5;
+    curve.fee_override.redeem_fee_bps = 500;
+    curve.fee_override.set_slot = 100;
+    curve.fee_override.expiry_slot = 200;
+
+    assert_eq!(curve.active_override(150), Some((5, 500)));
+    assert_eq!(curve.effective_fees(150), (5, 500));
+    // the expiry slot itself is still overridden
+    assert_eq!(curve.effective_fees(200), (5, 500));
+    assert_eq!(curve.effective_fees(201), (30, 50));
+}
+
+#[test]
+fn zero_override_fees_still_apply() {
+    let mut curve = curve_account(30, 50);
+    curve.fee_override.expiry_slot = 200;
+    assert_eq!(curve.effective_fees(150), (0, 0));
+}
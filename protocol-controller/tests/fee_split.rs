@@ -0,0 +1,160 @@
+// treasury fee split and update_fee_recipients (25)
+// the fee is split by weight across the configured recipients, the last one takes the rounding
+// remainder; weights add up to 10_000 bps and recipients that stay keep their cumulative totals
+
+use bytemuck::Zeroable;
+use mollusk::{result::InstructionResult, Mollusk};
+use solana_account::Account;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+use protocol_controller::{
+    FeeRecipient, FeeSplitConfig, ProgramAccount, FEE_RECIPIENT_INSURANCE, FEE_RECIPIENT_REFERRER,
+    FEE_RECIPIENT_TREASURY, FEE_SPLIT_SEED, PROTOCOL_PARAMETERS_SEED,
+};
+
+const INITIALIZE_PROTOCOL: u8 = 0;
+const UPDATE_FEE_RECIPIENTS: u8 = 25;
+
+
+fn recipient(token_account: [u8; 32], weight_bps: u16, kind: u8) -> FeeRecipient {
+    let mut recipient = FeeRecipient::zeroed();
+    recipient.token_account = token_account;
+    recipient.weight_bps = weight_bps;
+    recipient.kind = kind;
+    recipient
+}
+
+fn config(recipients: &[FeeRecipient]) -> FeeSplitConfig {
+    let mut config = FeeSplitConfig::zeroed();
+    config.recipients[..recipients.len()].copy_from_slice(recipients);
+    config.recipient_count = recipients.len() as u8;
+    config.is_initialized = 1;
+    config
+}
+
+
+#[test]
+fn splits_the_fee_by_weight() {
+    let config = config(&[
+        recipient([1; 32], 7_000, FEE_RECIPIENT_TREASURY),
+        recipient([2; 32], 2_000, FEE_RECIPIENT_INSURANCE),
+        recipient([3; 32], 1_000, FEE_RECIPIENT_REFERRER),
+    ]);
+    assert_eq!(config.split(1_000_000), [700_000, 200_000, 100_000, 0]);
+}
+
+#[test]
+fn last_recipient_takes_the_rounding_remainder() {
+    let config = config(&[
+        recipient([1; 32], 3_333, FEE_RECIPIENT_TREASURY),
+        recipient([2; 32], 3_333, FEE_RECIPIENT_INSURANCE),
+        recipient([3; 32], 3_334, FEE_RECIPIENT_REFERRER),
+    ]);
+    let amounts = config.split(100);
+    assert_eq!(amounts, [33, 33, 34, 0]);
+    assert_eq!(amounts.iter().sum::<u64>(), 100);
+}
+
+
+// mollusk: initialize_protocol, then update_fee_recipients over an existing config
+
+fn update_data(recipients: &[([u8; 32], u16, u8)]) -> Vec<u8> {
+    let mut data = vec![UPDATE_FEE_RECIPIENTS, recipients.len() as u8];
+    for (token_account, weight_bps, kind) in recipients {
+        data.extend_from_slice(token_account);
+        data.extend_from_slice(&weight_bps.to_le_bytes());
+        data.push(*kind);
+    }
+    data
+}
+
+fn update(existing: FeeSplitConfig, recipients: &[([u8; 32], u16, u8)]) -> (InstructionResult, Pubkey) {
+    let program_id = Pubkey::new_from_array(protocol_controller::ID);
+    let mollusk = Mollusk::new(&program_id, "protocol_controller");
+    let (system_program, system_program_account) = mollusk::program::keyed_account_for_system_program();
+
+    // same seed as constants::pda_seeds::PROTOCOL_CONTROLLER_SEED
+    let (controller, _) = Pubkey::find_program_address(&[b"protocol_controller"], &program_id);
+    let (parameters, _) = Pubkey::find_program_address(&[PROTOCOL_PARAMETERS_SEED], &program_id);
+    let (fee_split, _) = Pubkey::find_program_address(&[FEE_SPLIT_SEED], &program_id);
+    let authority = Pubkey::new_unique();
+
+    let accounts = vec![
+        (controller, Account::default()),
+        (authority, Account { lamports: 10_000_000_000, ..Account::default() }),
+        (parameters, Account::default()),
+        (
+            fee_split,
+            Account {
+                lamports: 10_000_000,
+                data: bytemuck::bytes_of(&existing).to_vec(),
+                owner: program_id,
+                executable: false,
+                rent_epoch: 0,
+            },
+        ),
+        (system_program, system_program_account),
+    ];
+
+    let mut initialize_data = vec![INITIALIZE_PROTOCOL];
+    initialize_data.extend_from_slice(&[0u8; 192]);
+    let mut initialize_accounts = vec![
+        AccountMeta::new(controller, false),
+        AccountMeta::new(authority, true),
+        AccountMeta::new(parameters, false),
+        AccountMeta::new_readonly(system_program, false),
+    ];
+    initialize_accounts.extend((0..6).map(|_| AccountMeta::new_readonly(system_program, false)));
+
+    let instructions = [
+        Instruction::new_with_bytes(program_id, &initialize_data, initialize_accounts),
+        Instruction::new_with_bytes(
+            program_id,
+            &update_data(recipients),
+            vec![
+                AccountMeta::new_readonly(parameters, false),
+                AccountMeta::new(authority, true),
+                AccountMeta::new(fee_split, false),
+                AccountMeta::new_readonly(system_program, false),
+            ],
+        ),
+    ];
+    (mollusk.process_instruction_chain(&instructions, &accounts), fee_split)
+}
+
+
+#[test]
+fn recipients_that_stay_keep_their_cumulative_totals() {
+    let mut treasury = recipient([1; 32], 10_000, FEE_RECIPIENT_TREASURY);
+    treasury.cumulative_paid = 500_000;
+
+    let (result, fee_split) = update(
+        config(&[treasury]),
+        &[([1; 32], 8_000, FEE_RECIPIENT_TREASURY), ([2; 32], 2_000, FEE_RECIPIENT_INSURANCE)],
+    );
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+
+    let data = &result.resulting_accounts.iter().find(|(key, _)| key == &fee_split).unwrap().1.data;
+    let config = FeeSplitConfig::load(data).unwrap();
+    assert_eq!(config.recipient_count, 2);
+    assert_eq!((config.recipients[0].weight_bps, config.recipients[0].cumulative_paid), (8_000, 500_000));
+    assert_eq!((config.recipients[1].weight_bps, config.recipients[1].cumulative_paid), (2_000, 0));
+}
+
+#[test]
+fn refuses_weights_that_do_not_add_up() {
+    let (result, _) = update(
+        FeeSplitConfig::zeroed(),
+        &[([1; 32], 8_000, FEE_RECIPIENT_TREASURY), ([2; 32], 1_000, FEE_RECIPIENT_INSURANCE)],
+    );
+    assert!(result.program_result.is_err());
+}
+
+#[test]
+fn refuses_an_unknown_recipient_kind() {
+    let (result, _) = update(FeeSplitConfig::zeroed(), &[([1; 32], 10_000, FEE_RECIPIENT_REFERRER + 1)]);
+    assert!(result.program_result.is_err());
+}
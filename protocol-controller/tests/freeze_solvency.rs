@@ -0,0 +1,29 @@
+// freeze-aware solvency
+// the liquid supply is backed first up to its threshold, what is left backs the frozen supply
+
+use protocol_controller::{backing_ratio_bps, split_backing_ratios};
+
+
+#[test]
+fn backing_over_supply() {
+    assert_eq!(backing_ratio_bps(1_100, 1_000), 11_000);
+    assert_eq!(backing_ratio_bps(0, 1_000), 0);
+    // no supply reads as fully backed
+    assert_eq!(backing_ratio_bps(0, 0), u64::MAX);
+}
+
+#[test]
+fn frozen_supply_gets_what_the_liquid_threshold_leaves() {
+    // 1_000 liquid at 10% needs 100, the other 1_400 back the frozen 1_000
+    assert_eq!(split_backing_ratios(1_500, 1_000, 1_000, 1_000), (15_000, 14_000));
+}
+
+#[test]
+fn frozen_supply_is_unbacked_under_the_liquid_threshold() {
+    assert_eq!(split_backing_ratios(50, 1_000, 500, 1_000), (500, 0));
+}
+
+#[test]
+fn no_liquid_supply_leaves_everything_to_the_frozen_supply() {
+    assert_eq!(split_backing_ratios(1_000, 0, 1_000, 1_000), (u64::MAX, 10_000));
+}
@@ -0,0 +1,41 @@
+// freeze state sync
+// per-user disagreements between the controller position, the TWAB ledger and the USDtx lock,
+// repairs follow the USDtx lock
+
+use protocol_controller::{
+    freeze_discrepancy, repaired_frozen_amount, SYNC_LEDGER_MISMATCH, SYNC_OVER_TOKEN_BALANCE,
+    SYNC_TOKEN_FLAG_MISMATCH, SYNC_UNREPAIRABLE,
+};
+
+
+#[test]
+fn agreeing_states_report_nothing() {
+    assert_eq!(freeze_discrepancy(100, 100, true, 100), 0);
+    assert_eq!(freeze_discrepancy(0, 0, false, 100), 0);
+}
+
+#[test]
+fn reports_each_disagreement() {
+    assert_eq!(freeze_discrepancy(100, 90, true, 100), SYNC_LEDGER_MISMATCH);
+    assert_eq!(freeze_discrepancy(100, 100, false, 100), SYNC_TOKEN_FLAG_MISMATCH);
+    assert_eq!(freeze_discrepancy(150, 150, true, 100), SYNC_OVER_TOKEN_BALANCE);
+    assert_eq!(
+        freeze_discrepancy(100, 0, false, 50),
+        SYNC_LEDGER_MISMATCH | SYNC_TOKEN_FLAG_MISMATCH | SYNC_OVER_TOKEN_BALANCE
+    );
+}
+
+#[test]
+fn locked_account_without_a_position_is_unrepairable() {
+    assert_eq!(freeze_discrepancy(0, 0, true, 50), SYNC_TOKEN_FLAG_MISMATCH | SYNC_UNREPAIRABLE);
+    assert_eq!(repaired_frozen_amount(0, true, 50), None);
+}
+
+#[test]
+fn repairs_follow_the_usdtx_lock() {
+    // unlocked, nothing frozen
+    assert_eq!(repaired_frozen_amount(100, false, 100), Some(0));
+    // locked, never above the token balance
+    assert_eq!(repaired_frozen_amount(80, true, 100), Some(80));
+    assert_eq!(repaired_frozen_amount(150, true, 100), Some(100));
+}
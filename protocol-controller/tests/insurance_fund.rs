@@ -0,0 +1,49 @@
+// insurance fund loss history
+// every socialized loss is kept in a ring buffer of the last LOSS_EVENT_CAPACITY events, the
+// running totals and loss_event_count cover the full history
+
+use bytemuck::Zeroable;
+
+use protocol_controller::{InsuranceFund, LossEvent, LOSS_EVENT_CAPACITY};
+
+
+fn loss(timestamp: i64, loss_amount: u64, covered_by_fund: u64, covered_by_pending_yield: u64) -> LossEvent {
+    let mut event = LossEvent::zeroed();
+    event.timestamp = timestamp;
+    event.loss_amount = loss_amount;
+    event.covered_by_fund = covered_by_fund;
+    event.covered_by_pending_yield = covered_by_pending_yield;
+    event.uncovered = loss_amount - covered_by_fund - covered_by_pending_yield;
+    event
+}
+
+
+#[test]
+fn records_what_the_fund_drew_and_what_stayed_uncovered() {
+    let mut fund = InsuranceFund::zeroed();
+    fund.record_loss(loss(1, 1_000_000, 600_000, 300_000));
+    fund.record_loss(loss(2, 500_000, 500_000, 0));
+
+    assert_eq!(fund.loss_event_count, 2);
+    assert_eq!(fund.event_head, 2);
+    // pending yield is not drawn from the fund
+    assert_eq!(fund.total_drawn, 1_100_000);
+    assert_eq!(fund.total_uncovered, 100_000);
+    assert_eq!(fund.loss_events[0].timestamp, 1);
+    assert_eq!(fund.loss_events[1].timestamp, 2);
+}
+
+#[test]
+fn oldest_events_are_overwritten() {
+    let mut fund = InsuranceFund::zeroed();
+    for timestamp in 0..(LOSS_EVENT_CAPACITY as i64 + 2) {
+        fund.record_loss(loss(timestamp, 10, 10, 0));
+    }
+
+    assert_eq!(fund.loss_event_count, LOSS_EVENT_CAPACITY as u64 + 2);
+    assert_eq!(fund.event_head, 2);
+    assert_eq!(fund.loss_events[0].timestamp, LOSS_EVENT_CAPACITY as i64);
+    assert_eq!(fund.loss_events[1].timestamp, LOSS_EVENT_CAPACITY as i64 + 1);
+    assert_eq!(fund.loss_events[2].timestamp, 2);
+    assert_eq!(fund.total_drawn, 10 * (LOSS_EVENT_CAPACITY as u64 + 2));
+}
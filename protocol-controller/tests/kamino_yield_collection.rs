@@ -0,0 +1,129 @@
+// collect_kamino_usdc_yields_to_vault (19)
+// interest accounting against a mocked Kamino reserve account layout
+
+use protocol_controller::{
+    accrued_interest, interest_redemption, kamino_exchange_rate, read_kamino_reserve, verify_redeemed_delta,
+    KAMINO_FRACTION_BITS, KAMINO_RESERVE_AVAILABLE_AMOUNT_OFFSET, KAMINO_RESERVE_BORROWED_AMOUNT_SF_OFFSET,
+    KAMINO_RESERVE_COLLATERAL_MINT_OFFSET, KAMINO_RESERVE_COLLATERAL_SUPPLY_OFFSET,
+    KAMINO_RESERVE_LAST_UPDATE_SLOT_OFFSET, KAMINO_RESERVE_LAST_UPDATE_STALE_OFFSET,
+    KAMINO_RESERVE_LIQUIDITY_MINT_OFFSET, KAMINO_RESERVE_PROTOCOL_FEES_SF_OFFSET, RATE_WAD,
+};
+
+const KAMINO_RESERVE_LEN: usize = 8624;
+
+const USDC_MINT: [u8; 32] = [1u8; 32];
+const CTOKEN_MINT: [u8; 32] = [2u8; 32];
+
+
+struct MockReserve {
+    available: u64,
+    borrowed: u64,
+    protocol_fees: u64,
+    collateral_supply: u64,
+    slot: u64,
+    stale: bool,
+}
+
+impl MockReserve {
+    fn data(&self) -> Vec<u8> {
+        let mut data = vec![0u8; KAMINO_RESERVE_LEN];
+        data[KAMINO_RESERVE_LAST_UPDATE_SLOT_OFFSET..KAMINO_RESERVE_LAST_UPDATE_SLOT_OFFSET + 8]
+            .copy_from_slice(&self.slot.to_le_bytes());
+        data[KAMINO_RESERVE_LAST_UPDATE_STALE_OFFSET] = self.stale as u8;
+        data[KAMINO_RESERVE_LIQUIDITY_MINT_OFFSET..KAMINO_RESERVE_LIQUIDITY_MINT_OFFSET + 32].copy_from_slice(&USDC_MINT);
+        data[KAMINO_RESERVE_AVAILABLE_AMOUNT_OFFSET..KAMINO_RESERVE_AVAILABLE_AMOUNT_OFFSET + 8]
+            .copy_from_slice(&self.available.to_le_bytes());
+        data[KAMINO_RESERVE_BORROWED_AMOUNT_SF_OFFSET..KAMINO_RESERVE_BORROWED_AMOUNT_SF_OFFSET + 16]
+            .copy_from_slice(&((self.borrowed as u128) << KAMINO_FRACTION_BITS).to_le_bytes());
+        data[KAMINO_RESERVE_PROTOCOL_FEES_SF_OFFSET..KAMINO_RESERVE_PROTOCOL_FEES_SF_OFFSET + 16]
+            .copy_from_slice(&((self.protocol_fees as u128) << KAMINO_FRACTION_BITS).to_le_bytes());
+        data[KAMINO_RESERVE_COLLATERAL_MINT_OFFSET..KAMINO_RESERVE_COLLATERAL_MINT_OFFSET + 32].copy_from_slice(&CTOKEN_MINT);
+        data[KAMINO_RESERVE_COLLATERAL_SUPPLY_OFFSET..KAMINO_RESERVE_COLLATERAL_SUPPLY_OFFSET + 8]
+            .copy_from_slice(&self.collateral_supply.to_le_bytes());
+        data
+    }
+}
+
+// 1.5M USDC lent out of 1.0M available + 0.5M borrowed, against 1.25M cTokens: rate 1.2
+fn reserve() -> MockReserve {
+    MockReserve {
+        available: 1_000_000_000_000,
+        borrowed: 500_000_000_000,
+        protocol_fees: 0,
+        collateral_supply: 1_250_000_000_000,
+        slot: 300,
+        stale: false,
+    }
+}
+
+
+#[test]
+fn exchange_rate_from_reserve_liquidity_and_ctoken_supply() {
+    let rate = kamino_exchange_rate(&reserve().data()).unwrap();
+    assert_eq!(rate, RATE_WAD * 12 / 10);
+}
+
+#[test]
+fn protocol_fees_are_not_depositor_liquidity() {
+    let with_fees = MockReserve { protocol_fees: 250_000_000_000, ..reserve() };
+    let rate = kamino_exchange_rate(&with_fees.data()).unwrap();
+    assert_eq!(rate, RATE_WAD);
+}
+
+#[test]
+fn empty_reserve_rates_one_to_one() {
+    let empty = MockReserve { available: 0, borrowed: 0, collateral_supply: 0, ..reserve() };
+    assert_eq!(kamino_exchange_rate(&empty.data()).unwrap(), RATE_WAD);
+}
+
+#[test]
+fn reads_mints_and_refresh_state() {
+    let view = read_kamino_reserve(&MockReserve { stale: true, ..reserve() }.data()).unwrap();
+    assert_eq!(view.liquidity_mint, USDC_MINT);
+    assert_eq!(view.collateral_mint, CTOKEN_MINT);
+    assert_eq!(view.last_update_slot, 300);
+    assert!(view.is_stale);
+
+    assert!(read_kamino_reserve(&[0u8; 64]).is_err());
+}
+
+#[test]
+fn interest_from_rate_move_since_snapshot() {
+    let snapshot = RATE_WAD * 12 / 10;
+    let now = kamino_exchange_rate(&MockReserve { borrowed: 512_500_000_000, ..reserve() }.data()).unwrap();
+    assert_eq!(now, RATE_WAD * 121 / 100);
+
+    // 10,000 cTokens × 0.01 = $100
+    assert_eq!(accrued_interest(10_000_000_000, 10_000_000_000, snapshot, now), 100_000_000);
+    // a falling rate is no interest
+    assert_eq!(accrued_interest(10_000_000_000, 10_000_000_000, now, snapshot), 0);
+}
+
+#[test]
+fn ctokens_added_after_snapshot_earn_nothing_yet() {
+    let snapshot = RATE_WAD;
+    let now = RATE_WAD * 101 / 100;
+    assert_eq!(
+        accrued_interest(20_000_000_000, 10_000_000_000, snapshot, now),
+        accrued_interest(10_000_000_000, 10_000_000_000, snapshot, now),
+    );
+}
+
+#[test]
+fn redeems_only_the_interest_portion() {
+    let rate = RATE_WAD * 121 / 100;
+    let (ctokens, liquidity) = interest_redemption(100_000_000, rate);
+    assert_eq!(ctokens, 82_644_628);
+    assert!(liquidity <= 100_000_000);
+    assert!(100_000_000 - liquidity <= 1);
+
+    assert_eq!(interest_redemption(0, rate), (0, 0));
+}
+
+#[test]
+fn vault_delta_within_redeem_rounding() {
+    assert_eq!(verify_redeemed_delta(1_000, 1_099, 100).unwrap(), 99);
+    assert_eq!(verify_redeemed_delta(1_000, 1_102, 100).unwrap(), 102);
+    assert!(verify_redeemed_delta(1_000, 1_090, 100).is_err());
+    assert!(verify_redeemed_delta(1_000, 900, 0).is_err());
+}
@@ -0,0 +1,42 @@
+// liquidity buffer
+// a share of each asset's collateral stays liquid, redemptions take from it before the strategies
+
+use bytemuck::Zeroable;
+
+use protocol_controller::{
+    liquidity_buffer_target, redemption_sources, sol_lamports_for_usd, MintingState, ASSET_SOL, ASSET_USDC,
+    LAMPORTS_PER_SOL,
+};
+
+
+#[test]
+fn buffer_is_a_share_of_the_collateral() {
+    assert_eq!(liquidity_buffer_target(1_000_000, 1_000), 100_000);
+    assert_eq!(liquidity_buffer_target(1_000_000, 0), 0);
+    // never more than the collateral itself
+    assert_eq!(liquidity_buffer_target(1_000_000, 20_000), 1_000_000);
+}
+
+#[test]
+fn redemptions_take_from_the_buffer_first() {
+    assert_eq!(redemption_sources(100, 40), (40, 0));
+    assert_eq!(redemption_sources(100, 250), (100, 150));
+    assert_eq!(redemption_sources(0, 5), (0, 5));
+}
+
+#[test]
+fn prices_usd_back_into_lamports() {
+    assert_eq!(sol_lamports_for_usd(150_000_000, 150_000_000), LAMPORTS_PER_SOL);
+    assert_eq!(sol_lamports_for_usd(75_000_000, 150_000_000), LAMPORTS_PER_SOL / 2);
+    assert_eq!(sol_lamports_for_usd(75_000_000, 0), 0);
+}
+
+#[test]
+fn collateral_by_asset() {
+    let mut minting = MintingState::zeroed();
+    minting.sol_collateral_lamports = 7;
+    minting.psm_usdc_collateral = 11;
+
+    assert_eq!(minting.collateral_of(ASSET_SOL), 7);
+    assert_eq!(minting.collateral_of(ASSET_USDC), 11);
+}
@@ -0,0 +1,282 @@
+// oracle adapters and the generic update_price (70)
+// every adapter parses its provider layout, rejects stale, non-positive or too uncertain readings
+// and rescales to 6 decimals; register_oracle_feed (71) maps a feed to its adapter and source slot
+
+use bytemuck::Zeroable;
+use mollusk::{result::InstructionResult, Mollusk};
+use solana_account::Account;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+use protocol_controller::{
+    FixedLayoutAdapter, OracleAdapter, OracleFeedEntry, ProgramAccount, PythAdapter, SourcePriceFeed,
+    ADAPTER_FIXED_LAYOUT, ADAPTER_PYTH, ORACLE_ADAPTER_REGISTRY_SEED, PROTOCOL_PARAMETERS_SEED,
+    SOURCE_PRICE_FEED_SEED, SOURCE_SWITCHBOARD,
+};
+
+const INITIALIZE_PROTOCOL: u8 = 0;
+const UPDATE_PRICE: u8 = 70;
+const REGISTER_ORACLE_FEED: u8 = 71;
+
+const NOW: i64 = 1_700_000_000;
+const MAX_STALENESS: u64 = 60;
+
+// fixed layout feed: price at 8 (i64, 8 decimals), timestamp at 16
+const PRICE_OFFSET: u16 = 8;
+const TIMESTAMP_OFFSET: u16 = 16;
+
+
+fn fixed_layout_entry() -> OracleFeedEntry {
+    let mut entry = OracleFeedEntry::zeroed();
+    entry.adapter_type = ADAPTER_FIXED_LAYOUT;
+    entry.source_id = SOURCE_SWITCHBOARD;
+    entry.price_width = 8;
+    entry.exponent = -8;
+    entry.price_offset = PRICE_OFFSET;
+    entry.timestamp_offset = TIMESTAMP_OFFSET;
+    entry
+}
+
+fn fixed_layout_feed(price: i64, timestamp: i64) -> Vec<u8> {
+    let mut data = vec![0u8; 32];
+    data[PRICE_OFFSET as usize..PRICE_OFFSET as usize + 8].copy_from_slice(&price.to_le_bytes());
+    data[TIMESTAMP_OFFSET as usize..TIMESTAMP_OFFSET as usize + 8].copy_from_slice(&timestamp.to_le_bytes());
+    data
+}
+
+// pyth v2 price account, trading
+fn pyth_feed(price: i64, confidence: u64, exponent: i32, publish_time: i64) -> Vec<u8> {
+    let mut data = vec![0u8; 240];
+    data[0..4].copy_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    data[8..12].copy_from_slice(&3u32.to_le_bytes());
+    data[20..24].copy_from_slice(&exponent.to_le_bytes());
+    data[96..104].copy_from_slice(&publish_time.to_le_bytes());
+    data[208..216].copy_from_slice(&price.to_le_bytes());
+    data[216..224].copy_from_slice(&confidence.to_le_bytes());
+    data[224..228].copy_from_slice(&1u32.to_le_bytes());
+    data
+}
+
+
+#[test]
+fn fixed_layout_reads_and_rescales_to_six_decimals() {
+    let entry = fixed_layout_entry();
+    let raw = FixedLayoutAdapter.parse(&fixed_layout_feed(15_000_000_000, NOW), &entry).unwrap();
+    FixedLayoutAdapter.validate(&raw, &entry, NOW, MAX_STALENESS).unwrap();
+    assert_eq!(FixedLayoutAdapter.normalize(&raw).unwrap(), (150_000_000, 0));
+    assert_eq!(raw.publish_time, NOW);
+}
+
+#[test]
+fn fixed_layout_refuses_a_short_feed() {
+    let entry = fixed_layout_entry();
+    assert!(FixedLayoutAdapter.parse(&[0u8; 20], &entry).is_err());
+}
+
+#[test]
+fn pyth_reads_price_confidence_and_exponent() {
+    let entry = OracleFeedEntry::zeroed();
+    let raw = PythAdapter.parse(&pyth_feed(1_500_000, 1_000, -4, NOW), &entry).unwrap();
+    PythAdapter.validate(&raw, &entry, NOW, MAX_STALENESS).unwrap();
+    // 4 decimals scale up to 6
+    assert_eq!(PythAdapter.normalize(&raw).unwrap(), (150_000_000, 100_000));
+}
+
+#[test]
+fn pyth_refuses_a_price_that_is_not_trading() {
+    let mut data = pyth_feed(1_500_000, 1_000, -4, NOW);
+    data[224..228].copy_from_slice(&0u32.to_le_bytes());
+    assert!(PythAdapter.parse(&data, &OracleFeedEntry::zeroed()).is_err());
+}
+
+#[test]
+fn refuses_stale_and_non_positive_readings() {
+    let entry = fixed_layout_entry();
+    let stale = FixedLayoutAdapter.parse(&fixed_layout_feed(15_000_000_000, NOW - MAX_STALENESS as i64 - 1), &entry).unwrap();
+    assert!(FixedLayoutAdapter.validate(&stale, &entry, NOW, MAX_STALENESS).is_err());
+
+    let negative = FixedLayoutAdapter.parse(&fixed_layout_feed(-1, NOW), &entry).unwrap();
+    assert!(FixedLayoutAdapter.validate(&negative, &entry, NOW, MAX_STALENESS).is_err());
+}
+
+#[test]
+fn refuses_a_confidence_interval_over_the_entry_bound() {
+    let mut entry = OracleFeedEntry::zeroed();
+    entry.max_confidence_bps = 50;
+    // 1% wide
+    let raw = PythAdapter.parse(&pyth_feed(1_500_000, 15_000, -4, NOW), &entry).unwrap();
+    assert!(PythAdapter.validate(&raw, &entry, NOW, MAX_STALENESS).is_err());
+
+    entry.max_confidence_bps = 100;
+    PythAdapter.validate(&raw, &entry, NOW, MAX_STALENESS).unwrap();
+}
+
+
+// mollusk: initialize_protocol, register a fixed-layout feed, then update_price through it
+
+struct Setup {
+    mollusk: Mollusk,
+    accounts: Vec<(Pubkey, Account)>,
+    controller: Pubkey,
+    authority: Pubkey,
+    parameters: Pubkey,
+    doppler_oracle: Pubkey,
+    registry: Pubkey,
+    feed: Pubkey,
+    feed_owner: Pubkey,
+    source_feed: Pubkey,
+    system_program: Pubkey,
+}
+
+fn setup(feed_data: Vec<u8>) -> Setup {
+    let program_id = Pubkey::new_from_array(protocol_controller::ID);
+    let mut mollusk = Mollusk::new(&program_id, "protocol_controller");
+    mollusk.sysvars.clock.unix_timestamp = NOW;
+    let (system_program, system_program_account) = mollusk::program::keyed_account_for_system_program();
+
+    // same seed as constants::pda_seeds::PROTOCOL_CONTROLLER_SEED
+    let (controller, _) = Pubkey::find_program_address(&[b"protocol_controller"], &program_id);
+    let (parameters, _) = Pubkey::find_program_address(&[PROTOCOL_PARAMETERS_SEED], &program_id);
+    let authority = Pubkey::new_unique();
+    let doppler_oracle = Pubkey::new_unique();
+    let (registry, _) =
+        Pubkey::find_program_address(&[ORACLE_ADAPTER_REGISTRY_SEED, doppler_oracle.as_ref()], &program_id);
+    let (source_feed, _) = Pubkey::find_program_address(
+        &[SOURCE_PRICE_FEED_SEED, doppler_oracle.as_ref(), &[SOURCE_SWITCHBOARD]],
+        &program_id,
+    );
+    let feed = Pubkey::new_unique();
+    let feed_owner = Pubkey::new_unique();
+
+    let accounts = vec![
+        (controller, Account::default()),
+        (authority, Account { lamports: 10_000_000_000, ..Account::default() }),
+        (parameters, Account::default()),
+        (doppler_oracle, Account::default()),
+        (registry, Account::default()),
+        (feed, Account { lamports: 1_000_000, data: feed_data, owner: feed_owner, ..Account::default() }),
+        (source_feed, Account::default()),
+        (system_program, system_program_account),
+    ];
+
+    Setup {
+        mollusk,
+        accounts,
+        controller,
+        authority,
+        parameters,
+        doppler_oracle,
+        registry,
+        feed,
+        feed_owner,
+        source_feed,
+        system_program,
+    }
+}
+
+impl Setup {
+    fn initialize_protocol(&self) -> Instruction {
+        let mut data = vec![INITIALIZE_PROTOCOL];
+        data.extend_from_slice(&[0u8; 160]);
+        data.extend_from_slice(self.doppler_oracle.as_ref());
+
+        let mut accounts = vec![
+            AccountMeta::new(self.controller, false),
+            AccountMeta::new(self.authority, true),
+            AccountMeta::new(self.parameters, false),
+            AccountMeta::new_readonly(self.system_program, false),
+        ];
+        accounts.extend((0..6).map(|_| AccountMeta::new_readonly(self.system_program, false)));
+        Instruction::new_with_bytes(Pubkey::new_from_array(protocol_controller::ID), &data, accounts)
+    }
+
+    fn register(&self, feed: &Pubkey) -> Instruction {
+        let entry = fixed_layout_entry();
+        let mut data = vec![REGISTER_ORACLE_FEED];
+        data.extend_from_slice(feed.as_ref());
+        data.extend_from_slice(self.feed_owner.as_ref());
+        data.extend_from_slice(&[entry.adapter_type, entry.source_id, entry.price_width, entry.exponent as u8]);
+        data.extend_from_slice(&entry.price_offset.to_le_bytes());
+        data.extend_from_slice(&entry.timestamp_offset.to_le_bytes());
+        data.extend_from_slice(&entry.max_confidence_bps.to_le_bytes());
+
+        Instruction::new_with_bytes(
+            Pubkey::new_from_array(protocol_controller::ID),
+            &data,
+            vec![
+                AccountMeta::new_readonly(self.parameters, false),
+                AccountMeta::new(self.authority, true),
+                AccountMeta::new_readonly(self.doppler_oracle, false),
+                AccountMeta::new(self.registry, false),
+                AccountMeta::new_readonly(self.system_program, false),
+            ],
+        )
+    }
+
+    fn update(&self) -> Instruction {
+        Instruction::new_with_bytes(
+            Pubkey::new_from_array(protocol_controller::ID),
+            &[UPDATE_PRICE],
+            vec![
+                AccountMeta::new_readonly(self.parameters, false),
+                AccountMeta::new_readonly(self.doppler_oracle, false),
+                AccountMeta::new_readonly(self.registry, false),
+                AccountMeta::new_readonly(self.feed, false),
+                AccountMeta::new(self.source_feed, false),
+                AccountMeta::new(self.authority, true),
+                AccountMeta::new_readonly(self.system_program, false),
+            ],
+        )
+    }
+
+    fn run(&self, registered_feed: &Pubkey) -> InstructionResult {
+        self.mollusk.process_instruction_chain(
+            &[self.initialize_protocol(), self.register(registered_feed), self.update()],
+            &self.accounts,
+        )
+    }
+}
+
+fn account<'a>(accounts: &'a [(Pubkey, Account)], key: &Pubkey) -> &'a Account {
+    &accounts.iter().find(|(k, _)| k == key).unwrap().1
+}
+
+
+#[test]
+fn update_price_writes_the_registered_source() {
+    let setup = setup(fixed_layout_feed(15_000_000_000, NOW));
+    let result = setup.run(&setup.feed);
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+
+    let source_feed = *SourcePriceFeed::load(&account(&result.resulting_accounts, &setup.source_feed).data).unwrap();
+    assert_eq!(source_feed.doppler_oracle, setup.doppler_oracle.to_bytes());
+    assert_eq!(source_feed.source_id, SOURCE_SWITCHBOARD);
+    assert_eq!(source_feed.price, 150_000_000);
+    assert_eq!(source_feed.publish_time, NOW);
+}
+
+#[test]
+fn update_price_refuses_an_unregistered_feed() {
+    let setup = setup(fixed_layout_feed(15_000_000_000, NOW));
+    let result = setup.run(&Pubkey::new_unique());
+    assert!(result.program_result.is_err());
+}
+
+#[test]
+fn update_price_refuses_a_stale_feed() {
+    let setup = setup(fixed_layout_feed(15_000_000_000, NOW - 3_600));
+    let result = setup.run(&setup.feed);
+    assert!(result.program_result.is_err());
+}
+
+#[test]
+fn register_refuses_an_unknown_adapter_type() {
+    let setup = setup(fixed_layout_feed(15_000_000_000, NOW));
+    let mut register = setup.register(&setup.feed);
+    // adapter type follows the feed and its owner
+    register.data[1 + 64] = ADAPTER_PYTH + 10;
+    let result = setup.mollusk.process_instruction_chain(&[setup.initialize_protocol(), register], &setup.accounts);
+    assert!(result.program_result.is_err());
+}
@@ -0,0 +1,194 @@
+// validate_price_deviations (31)
+// an aggregate move over the band against a recent previous aggregate pauses minting and puts the
+// controller in emergency mode (type 2); only the pinned Doppler oracle is checked
+
+use bytemuck::Zeroable;
+use mollusk::{result::InstructionResult, Mollusk};
+use solana_account::Account;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+use protocol_controller::{
+    deviation_bps, DopplerPriceHistory, OracleGuard, ProgramAccount, ProtocolController, ProtocolParameters,
+    DOPPLER_PRICE_HISTORY_SEED, MAX_AGGREGATE_COMPARISON_WINDOW, ORACLE_GUARD_SEED, PROTOCOL_PARAMETERS_SEED,
+    SOURCE_PRICE_FEED_SEED, SOURCE_PYTH,
+};
+
+const INITIALIZE_PROTOCOL: u8 = 0;
+const VALIDATE_PRICE_DEVIATIONS: u8 = 31;
+
+const PRICE: u64 = 150_000_000;
+
+
+struct Setup {
+    mollusk: Mollusk,
+    accounts: Vec<(Pubkey, Account)>,
+    controller: Pubkey,
+    authority: Pubkey,
+    parameters: Pubkey,
+    doppler_oracle: Pubkey,
+    system_program: Pubkey,
+}
+
+fn program_account(data: Vec<u8>) -> Account {
+    Account {
+        lamports: 10_000_000,
+        data,
+        owner: Pubkey::new_from_array(protocol_controller::ID),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn history_account(doppler_oracle: &Pubkey, aggregates: &[(u64, i64)]) -> Account {
+    let mut history = DopplerPriceHistory::zeroed();
+    history.doppler_oracle = doppler_oracle.to_bytes();
+    history.is_initialized = 1;
+    for (slot, (price, timestamp)) in aggregates.iter().enumerate() {
+        history.record(*price, *timestamp, slot as u64);
+    }
+    program_account(bytemuck::bytes_of(&history).to_vec())
+}
+
+fn setup() -> Setup {
+    let program_id = Pubkey::new_from_array(protocol_controller::ID);
+    let mollusk = Mollusk::new(&program_id, "protocol_controller");
+    let (system_program, system_program_account) = mollusk::program::keyed_account_for_system_program();
+
+    // same seed as constants::pda_seeds::PROTOCOL_CONTROLLER_SEED
+    let (controller, _) = Pubkey::find_program_address(&[b"protocol_controller"], &program_id);
+    let (parameters, _) = Pubkey::find_program_address(&[PROTOCOL_PARAMETERS_SEED], &program_id);
+    let authority = Pubkey::new_unique();
+    let doppler_oracle = Pubkey::new_unique();
+
+    let accounts = vec![
+        (controller, program_account(vec![0u8; std::mem::size_of::<ProtocolController>()])),
+        (authority, Account { lamports: 10_000_000_000, ..Account::default() }),
+        (parameters, Account::default()),
+        (doppler_oracle, Account::default()),
+        (system_program, system_program_account),
+    ];
+
+    Setup { mollusk, accounts, controller, authority, parameters, doppler_oracle, system_program }
+}
+
+impl Setup {
+    fn initialize_protocol(&self) -> Instruction {
+        let mut data = vec![INITIALIZE_PROTOCOL];
+        data.extend_from_slice(&[0u8; 160]);
+        data.extend_from_slice(self.doppler_oracle.as_ref());
+
+        let mut accounts = vec![
+            AccountMeta::new(self.controller, false),
+            AccountMeta::new(self.authority, true),
+            AccountMeta::new(self.parameters, false),
+            AccountMeta::new_readonly(self.system_program, false),
+        ];
+        accounts.extend((0..6).map(|_| AccountMeta::new_readonly(self.system_program, false)));
+        Instruction::new_with_bytes(Pubkey::new_from_array(protocol_controller::ID), &data, accounts)
+    }
+
+    // validates the history of a Doppler oracle against one never-updated source
+    fn validate(&self, doppler_oracle: &Pubkey, aggregates: &[(u64, i64)]) -> InstructionResult {
+        let program_id = Pubkey::new_from_array(protocol_controller::ID);
+        let (history, _) =
+            Pubkey::find_program_address(&[DOPPLER_PRICE_HISTORY_SEED, doppler_oracle.as_ref()], &program_id);
+        let (guard, _) = Pubkey::find_program_address(&[ORACLE_GUARD_SEED, doppler_oracle.as_ref()], &program_id);
+        let (feed, _) = Pubkey::find_program_address(
+            &[SOURCE_PRICE_FEED_SEED, doppler_oracle.as_ref(), &[SOURCE_PYTH]],
+            &program_id,
+        );
+
+        let mut accounts = self.accounts.clone();
+        accounts.extend([
+            (*doppler_oracle, Account::default()),
+            (history, history_account(doppler_oracle, aggregates)),
+            (guard, Account::default()),
+            (feed, Account::default()),
+        ]);
+
+        let validate = Instruction::new_with_bytes(
+            program_id,
+            &[VALIDATE_PRICE_DEVIATIONS, 1],
+            vec![
+                AccountMeta::new(self.controller, false),
+                AccountMeta::new(self.parameters, false),
+                AccountMeta::new_readonly(*doppler_oracle, false),
+                AccountMeta::new_readonly(history, false),
+                AccountMeta::new(guard, false),
+                AccountMeta::new_readonly(feed, false),
+                AccountMeta::new(self.authority, true),
+                AccountMeta::new_readonly(self.system_program, false),
+            ],
+        );
+        self.mollusk.process_instruction_chain(&[self.initialize_protocol(), validate], &accounts)
+    }
+}
+
+fn account<'a>(accounts: &'a [(Pubkey, Account)], key: &Pubkey) -> &'a Account {
+    &accounts.iter().find(|(k, _)| k == key).unwrap().1
+}
+
+fn mint_paused(result: &InstructionResult, parameters: &Pubkey) -> u8 {
+    ProtocolParameters::load(&account(&result.resulting_accounts, parameters).data).unwrap().mint_paused
+}
+
+fn emergency_mode(result: &InstructionResult, controller: &Pubkey) -> bool {
+    let data = &account(&result.resulting_accounts, controller).data;
+    bytemuck::from_bytes::<ProtocolController>(&data[..std::mem::size_of::<ProtocolController>()]).emergency_mode
+}
+
+
+#[test]
+fn deviation_is_measured_against_the_reference() {
+    assert_eq!(deviation_bps(105, 100), 500);
+    assert_eq!(deviation_bps(95, 100), 500);
+    assert_eq!(deviation_bps(100, 0), u64::MAX);
+}
+
+#[test]
+fn aggregate_jump_pauses_minting_in_emergency_mode() {
+    let setup = setup();
+    // 6% within a minute, over the 5% default band
+    let result = setup.validate(&setup.doppler_oracle, &[(PRICE, 1_000), (PRICE * 106 / 100, 1_060)]);
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+
+    assert_eq!(mint_paused(&result, &setup.parameters), 1);
+    assert!(emergency_mode(&result, &setup.controller));
+
+    let program_id = Pubkey::new_from_array(protocol_controller::ID);
+    let (guard, _) = Pubkey::find_program_address(&[ORACLE_GUARD_SEED, setup.doppler_oracle.as_ref()], &program_id);
+    let guard = *OracleGuard::load(&account(&result.resulting_accounts, &guard).data).unwrap();
+    assert_eq!(guard.breach_count, 1);
+    assert_eq!(guard.last_breach.deviation_bps, 600);
+}
+
+#[test]
+fn move_within_the_band_keeps_minting_open() {
+    let setup = setup();
+    let result = setup.validate(&setup.doppler_oracle, &[(PRICE, 1_000), (PRICE * 102 / 100, 1_060)]);
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+
+    assert_eq!(mint_paused(&result, &setup.parameters), 0);
+    assert!(!emergency_mode(&result, &setup.controller));
+}
+
+#[test]
+fn move_over_a_longer_stretch_is_no_breach() {
+    let setup = setup();
+    let later = 1_000 + MAX_AGGREGATE_COMPARISON_WINDOW + 1;
+    let result = setup.validate(&setup.doppler_oracle, &[(PRICE, 1_000), (PRICE * 106 / 100, later)]);
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+
+    assert_eq!(mint_paused(&result, &setup.parameters), 0);
+}
+
+#[test]
+fn refuses_another_oracle() {
+    let setup = setup();
+    let other_oracle = Pubkey::new_unique();
+    let result = setup.validate(&other_oracle, &[(PRICE, 1_000), (PRICE * 106 / 100, 1_060)]);
+    assert!(result.program_result.is_err());
+}
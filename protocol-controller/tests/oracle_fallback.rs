@@ -0,0 +1,214 @@
+// oracle fallback hierarchy through get_doppler_aggregated_price (38)
+// override, then the latest aggregate while it is fresh, then a fresh primary source, then a fresh
+// secondary source, then the 1h TWAP while the last aggregate is recent enough; feeds have to be
+// the pinned oracle's PDAs, created or not
+
+use bytemuck::Zeroable;
+use mollusk::{result::InstructionResult, Mollusk};
+use solana_account::Account;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+use protocol_controller::{
+    DopplerPriceHistory, SourcePriceFeed, DEFAULT_MAX_FALLBACK_TWAP_AGE, DEFAULT_MAX_PRICE_STALENESS,
+    DOPPLER_PRICE_HISTORY_SEED, ORACLE_GUARD_SEED, PRICE_ORIGIN_AGGREGATE, PRICE_ORIGIN_LAST_GOOD_TWAP, PRICE_ORIGIN_PRIMARY,
+    PRICE_ORIGIN_SECONDARY, PROTOCOL_PARAMETERS_SEED, SOURCE_PRICE_FEED_SEED, SOURCE_PYTH, SOURCE_SWITCHBOARD,
+};
+
+const INITIALIZE_PROTOCOL: u8 = 0;
+const GET_DOPPLER_AGGREGATED_PRICE: u8 = 38;
+
+const NOW: i64 = 1_700_000_000;
+const PRIMARY_PRICE: u64 = 150_000_000;
+const SECONDARY_PRICE: u64 = 149_000_000;
+const AGGREGATE_PRICE: u64 = 148_000_000;
+
+
+fn program_account(data: Vec<u8>) -> Account {
+    Account {
+        lamports: 10_000_000,
+        data,
+        owner: Pubkey::new_from_array(protocol_controller::ID),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn feed_account(doppler_oracle: &Pubkey, source_id: u8, price: u64, publish_time: i64) -> Account {
+    let mut feed = SourcePriceFeed::zeroed();
+    feed.doppler_oracle = doppler_oracle.to_bytes();
+    feed.source_id = source_id;
+    feed.is_initialized = 1;
+    feed.price = price;
+    feed.publish_time = publish_time;
+    program_account(bytemuck::bytes_of(&feed).to_vec())
+}
+
+fn history_account(doppler_oracle: &Pubkey, last_aggregate: i64) -> Account {
+    let mut history = DopplerPriceHistory::zeroed();
+    history.doppler_oracle = doppler_oracle.to_bytes();
+    history.is_initialized = 1;
+    history.record(AGGREGATE_PRICE, last_aggregate, 1);
+    program_account(bytemuck::bytes_of(&history).to_vec())
+}
+
+fn feed_address(doppler_oracle: &Pubkey, source_id: u8) -> Pubkey {
+    Pubkey::find_program_address(
+        &[SOURCE_PRICE_FEED_SEED, doppler_oracle.as_ref(), &[source_id]],
+        &Pubkey::new_from_array(protocol_controller::ID),
+    )
+    .0
+}
+
+
+struct Feeds {
+    primary: (Pubkey, Account),
+    secondary: (Pubkey, Account),
+    last_aggregate: i64,
+}
+
+// fresh sources behind an aggregate that went stale
+fn fresh(doppler_oracle: &Pubkey) -> Feeds {
+    Feeds {
+        primary: (feed_address(doppler_oracle, SOURCE_PYTH), feed_account(doppler_oracle, SOURCE_PYTH, PRIMARY_PRICE, NOW)),
+        secondary: (
+            feed_address(doppler_oracle, SOURCE_SWITCHBOARD),
+            feed_account(doppler_oracle, SOURCE_SWITCHBOARD, SECONDARY_PRICE, NOW),
+        ),
+        last_aggregate: STALE,
+    }
+}
+
+const STALE: i64 = NOW - DEFAULT_MAX_PRICE_STALENESS as i64 - 1;
+
+// initialize_protocol pinned to pinned_oracle, then the price of doppler_oracle
+fn resolve(pinned_oracle: &Pubkey, doppler_oracle: &Pubkey, feeds: Feeds) -> InstructionResult {
+    let program_id = Pubkey::new_from_array(protocol_controller::ID);
+    let mut mollusk = Mollusk::new(&program_id, "protocol_controller");
+    mollusk.sysvars.clock.unix_timestamp = NOW;
+    let (system_program, system_program_account) = mollusk::program::keyed_account_for_system_program();
+
+    let (controller, _) = Pubkey::find_program_address(&[b"protocol_controller"], &program_id);
+    let (parameters, _) = Pubkey::find_program_address(&[PROTOCOL_PARAMETERS_SEED], &program_id);
+    let (guard, _) = Pubkey::find_program_address(&[ORACLE_GUARD_SEED, doppler_oracle.as_ref()], &program_id);
+    let (history, _) =
+        Pubkey::find_program_address(&[DOPPLER_PRICE_HISTORY_SEED, doppler_oracle.as_ref()], &program_id);
+    let authority = Pubkey::new_unique();
+
+    let accounts = vec![
+        (controller, Account::default()),
+        (authority, Account { lamports: 10_000_000_000, ..Account::default() }),
+        (parameters, Account::default()),
+        (*doppler_oracle, Account::default()),
+        (guard, Account::default()),
+        feeds.primary.clone(),
+        feeds.secondary.clone(),
+        (history, history_account(doppler_oracle, feeds.last_aggregate)),
+        (system_program, system_program_account),
+    ];
+
+    let mut initialize_data = vec![INITIALIZE_PROTOCOL];
+    initialize_data.extend_from_slice(&[0u8; 160]);
+    initialize_data.extend_from_slice(pinned_oracle.as_ref());
+    let mut initialize_accounts = vec![
+        AccountMeta::new(controller, false),
+        AccountMeta::new(authority, true),
+        AccountMeta::new(parameters, false),
+        AccountMeta::new_readonly(system_program, false),
+    ];
+    initialize_accounts.extend((0..6).map(|_| AccountMeta::new_readonly(system_program, false)));
+
+    let instructions = [
+        Instruction::new_with_bytes(program_id, &initialize_data, initialize_accounts),
+        Instruction::new_with_bytes(
+            program_id,
+            &[GET_DOPPLER_AGGREGATED_PRICE],
+            vec![
+                AccountMeta::new_readonly(parameters, false),
+                AccountMeta::new_readonly(*doppler_oracle, false),
+                AccountMeta::new_readonly(guard, false),
+                AccountMeta::new_readonly(feeds.primary.0, false),
+                AccountMeta::new_readonly(feeds.secondary.0, false),
+                AccountMeta::new_readonly(history, false),
+            ],
+        ),
+    ];
+    mollusk.process_instruction_chain(&instructions, &accounts)
+}
+
+fn price_and_origin(result: &InstructionResult) -> (u64, u8) {
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+    let data = &result.return_data;
+    (u64::from_le_bytes(data[0..8].try_into().unwrap()), data[8])
+}
+
+
+#[test]
+fn fresh_aggregate_comes_first() {
+    let oracle = Pubkey::new_unique();
+    let mut feeds = fresh(&oracle);
+    feeds.last_aggregate = NOW;
+    assert_eq!(price_and_origin(&resolve(&oracle, &oracle, feeds)), (AGGREGATE_PRICE, PRICE_ORIGIN_AGGREGATE));
+}
+
+#[test]
+fn fresh_primary_comes_first_past_a_stale_aggregate() {
+    let oracle = Pubkey::new_unique();
+    assert_eq!(price_and_origin(&resolve(&oracle, &oracle, fresh(&oracle))), (PRIMARY_PRICE, PRICE_ORIGIN_PRIMARY));
+}
+
+#[test]
+fn stale_primary_falls_back_to_the_secondary() {
+    let oracle = Pubkey::new_unique();
+    let mut feeds = fresh(&oracle);
+    feeds.primary.1 = feed_account(&oracle, SOURCE_PYTH, PRIMARY_PRICE, STALE);
+    assert_eq!(price_and_origin(&resolve(&oracle, &oracle, feeds)), (SECONDARY_PRICE, PRICE_ORIGIN_SECONDARY));
+}
+
+#[test]
+fn never_updated_primary_falls_back_to_the_secondary() {
+    let oracle = Pubkey::new_unique();
+    let mut feeds = fresh(&oracle);
+    feeds.primary.1 = Account::default();
+    assert_eq!(price_and_origin(&resolve(&oracle, &oracle, feeds)), (SECONDARY_PRICE, PRICE_ORIGIN_SECONDARY));
+}
+
+#[test]
+fn stale_sources_fall_back_to_the_last_good_twap() {
+    let oracle = Pubkey::new_unique();
+    let mut feeds = fresh(&oracle);
+    feeds.primary.1 = feed_account(&oracle, SOURCE_PYTH, PRIMARY_PRICE, STALE);
+    feeds.secondary.1 = feed_account(&oracle, SOURCE_SWITCHBOARD, SECONDARY_PRICE, STALE);
+    assert_eq!(
+        price_and_origin(&resolve(&oracle, &oracle, feeds)),
+        (AGGREGATE_PRICE, PRICE_ORIGIN_LAST_GOOD_TWAP)
+    );
+}
+
+#[test]
+fn refuses_a_twap_past_its_max_age() {
+    let oracle = Pubkey::new_unique();
+    let mut feeds = fresh(&oracle);
+    feeds.primary.1 = feed_account(&oracle, SOURCE_PYTH, PRIMARY_PRICE, STALE);
+    feeds.secondary.1 = feed_account(&oracle, SOURCE_SWITCHBOARD, SECONDARY_PRICE, STALE);
+    feeds.last_aggregate = NOW - DEFAULT_MAX_FALLBACK_TWAP_AGE as i64 - 1;
+    assert!(resolve(&oracle, &oracle, feeds).program_result.is_err());
+}
+
+#[test]
+fn refuses_an_account_that_is_not_the_feed() {
+    let oracle = Pubkey::new_unique();
+    let mut feeds = fresh(&oracle);
+    // skipping the primary with any other account is not possible
+    feeds.primary = (Pubkey::new_unique(), Account::default());
+    assert!(resolve(&oracle, &oracle, feeds).program_result.is_err());
+}
+
+#[test]
+fn refuses_an_oracle_that_is_not_pinned() {
+    let pinned = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    assert!(resolve(&pinned, &other, fresh(&other)).program_result.is_err());
+}
@@ -0,0 +1,58 @@
+// Drift stand-in
+// answers the strategy manager's collect-yield instruction by paying USDC out of its own reserve,
+// never more than the reserve holds, so tests can make it under-deliver
+
+use pinocchio::{
+    account_info::AccountInfo,
+    entrypoint,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+entrypoint!(process_instruction);
+
+pub const STAND_IN_AUTHORITY_SEED: &[u8] = b"drift_stand_in";
+pub const COLLECT_DRIFT_YIELD_IX: u8 = 5;
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> Result<(), ProgramError> {
+    if instruction_data.len() < 9 || instruction_data[0] != COLLECT_DRIFT_YIELD_IX {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let amount = u64::from_le_bytes(instruction_data[1..9].try_into().unwrap());
+
+    // 0 = protocol controller (signer), 1 = strategy state, 2 = yields vault, 3 = token program,
+    // 4 = Drift user, 5 = Drift spot market, 6 = USDC reserve, 7 = reserve authority
+    if accounts.len() < 8 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    if !accounts[0].is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (authority, bump) = Pubkey::find_program_address(&[STAND_IN_AUTHORITY_SEED], program_id);
+    if accounts[7].key() != &authority {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let reserve_balance = pinocchio_token::state::TokenAccount::from_account_info(&accounts[6])?.amount();
+    let paid = amount.min(reserve_balance);
+    if paid == 0 {
+        return Ok(());
+    }
+
+    let bump_seed = [bump];
+    let seeds = [Seed::from(STAND_IN_AUTHORITY_SEED), Seed::from(&bump_seed)];
+
+    pinocchio_token::instructions::Transfer {
+        from: &accounts[6],
+        to: &accounts[2],
+        authority: &accounts[7],
+        amount: paid,
+    }
+    .invoke_signed(&[Signer::from(&seeds)])
+}
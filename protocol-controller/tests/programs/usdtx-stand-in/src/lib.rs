@@ -0,0 +1,37 @@
+// USDtx stand-in
+// answers the USDtx token program's freeze / unfreeze for yield with the signer checks only,
+// the lock itself is left to the real program
+
+use pinocchio::{
+    account_info::AccountInfo,
+    entrypoint,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+entrypoint!(process_instruction);
+
+pub const FREEZE_FOR_YIELD_IX: u8 = 4;
+pub const UNFREEZE_FROM_YIELD_IX: u8 = 5;
+
+pub fn process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> Result<(), ProgramError> {
+    if instruction_data.len() < 9
+        || (instruction_data[0] != FREEZE_FOR_YIELD_IX && instruction_data[0] != UNFREEZE_FROM_YIELD_IX)
+    {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // 0 = protocol controller (signer), 1 = user (signer), 2 = user USDtx account, 3 = USDtx mint
+    if accounts.len() < 4 {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    if !accounts[0].is_signer() || !accounts[1].is_signer() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    Ok(())
+}
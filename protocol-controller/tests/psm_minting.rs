@@ -0,0 +1,48 @@
+// USDC peg stability module
+// 1:1 minus the fee, capped per epoch, the cap usage resets when the epoch moves
+
+use bytemuck::Zeroable;
+
+use protocol_controller::{psm_mint_amounts, psm_utilization_bps, MintingState};
+
+const EPOCH_LENGTH: u64 = 86_400;
+const EPOCH_CAP: u64 = 1_000;
+
+
+#[test]
+fn mints_one_to_one_minus_the_fee() {
+    assert_eq!(psm_mint_amounts(1_000_000, 10), (1_000, 999_000));
+    assert_eq!(psm_mint_amounts(1_000_000, 0), (0, 1_000_000));
+    assert_eq!(psm_mint_amounts(1_000_000, 10_000), (1_000_000, 0));
+}
+
+#[test]
+fn utilization_is_bounded() {
+    assert_eq!(psm_utilization_bps(250, 1_000), 2_500);
+    assert_eq!(psm_utilization_bps(1_500, 1_000), 10_000);
+    assert_eq!(psm_utilization_bps(0, 0), 10_000);
+}
+
+#[test]
+fn reserves_up_to_the_epoch_cap() {
+    let mut minting = MintingState::zeroed();
+
+    minting.reserve_psm_capacity(600, 100, EPOCH_LENGTH, EPOCH_CAP).unwrap();
+    assert_eq!((minting.psm_epoch_minted, minting.psm_utilization_bps), (600, 6_000));
+
+    // over the cap, nothing is reserved
+    assert!(minting.reserve_psm_capacity(500, 200, EPOCH_LENGTH, EPOCH_CAP).is_err());
+    assert_eq!(minting.psm_epoch_minted, 600);
+
+    minting.reserve_psm_capacity(400, 300, EPOCH_LENGTH, EPOCH_CAP).unwrap();
+    assert_eq!((minting.psm_epoch_minted, minting.psm_utilization_bps), (1_000, 10_000));
+}
+
+#[test]
+fn next_epoch_starts_from_zero() {
+    let mut minting = MintingState::zeroed();
+    minting.reserve_psm_capacity(EPOCH_CAP, 100, EPOCH_LENGTH, EPOCH_CAP).unwrap();
+
+    minting.reserve_psm_capacity(500, EPOCH_LENGTH as i64 + 1, EPOCH_LENGTH, EPOCH_CAP).unwrap();
+    assert_eq!((minting.psm_epoch, minting.psm_epoch_minted), (1, 500));
+}
@@ -0,0 +1,46 @@
+// redemption queue
+// tickets are pending between the head and the next ticket id, filled redemptions leave the bucket
+
+use bytemuck::Zeroable;
+
+use protocol_controller::{MintingState, RedemptionQueue, ASSET_SOL, ASSET_USDC};
+
+
+#[test]
+fn pending_until_the_head_catches_up() {
+    let mut queue = RedemptionQueue::zeroed();
+    assert!(!queue.has_pending());
+
+    queue.next_ticket_id = 3;
+    queue.head_ticket_id = 1;
+    assert!(queue.has_pending());
+
+    queue.head_ticket_id = 3;
+    assert!(!queue.has_pending());
+}
+
+#[test]
+fn psm_redemption_keeps_the_fee_in_the_vault() {
+    let mut minting = MintingState::zeroed();
+    minting.psm_backed_supply = 1_000;
+    minting.psm_usdc_collateral = 1_000;
+
+    minting.record_redemption(ASSET_USDC, 400, 396, 4);
+    assert_eq!(minting.psm_backed_supply, 600);
+    assert_eq!(minting.psm_usdc_collateral, 600);
+    assert_eq!(minting.psm_fees_collected, 4);
+}
+
+#[test]
+fn sol_redemption_takes_the_lamports_paid_out() {
+    let mut minting = MintingState::zeroed();
+    minting.sol_backed_supply = 1_000;
+    minting.sol_collateral_lamports = 5_000;
+
+    minting.record_redemption(ASSET_SOL, 400, 2_000, 0);
+    assert_eq!((minting.sol_backed_supply, minting.sol_collateral_lamports), (600, 3_000));
+
+    // never below zero
+    minting.record_redemption(ASSET_SOL, 1_000, 4_000, 0);
+    assert_eq!((minting.sol_backed_supply, minting.sol_collateral_lamports), (0, 0));
+}
@@ -0,0 +1,28 @@
+// SOL to USDtx pricing
+// lamports are valued through the Doppler price and minted at the minimum collateral ratio
+
+use protocol_controller::{sol_value_usd, usdtx_for_collateral, LAMPORTS_PER_SOL};
+
+// $150 with 6 decimals
+const SOL_PRICE: u64 = 150_000_000;
+
+
+#[test]
+fn values_lamports_at_the_sol_price() {
+    assert_eq!(sol_value_usd(LAMPORTS_PER_SOL, SOL_PRICE), SOL_PRICE);
+    assert_eq!(sol_value_usd(2_500_000_000, SOL_PRICE), 375_000_000);
+    // under a micro dollar rounds down
+    assert_eq!(sol_value_usd(1, SOL_PRICE), 0);
+}
+
+#[test]
+fn value_saturates_instead_of_overflowing() {
+    assert_eq!(sol_value_usd(u64::MAX, u64::MAX), u64::MAX);
+}
+
+#[test]
+fn mints_at_the_collateral_ratio() {
+    assert_eq!(usdtx_for_collateral(375_000_000, 15_000), 250_000_000);
+    assert_eq!(usdtx_for_collateral(375_000_000, 10_000), 375_000_000);
+    assert_eq!(usdtx_for_collateral(375_000_000, 0), 0);
+}
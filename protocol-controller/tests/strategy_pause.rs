@@ -0,0 +1,137 @@
+// pause_strategy (52) status changes
+// a soft pause stops deposits and can be resumed, a wind-down has to bring everything back
+// before the strategy takes deposits again
+
+use bytemuck::Zeroable;
+use mollusk::{result::InstructionResult, Mollusk};
+use solana_account::Account;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+use protocol_controller::{
+    ProgramAccount, StrategyRegistry, PAUSE_MODE_FORCED_UNWIND, PAUSE_MODE_RESUME, PAUSE_MODE_SOFT,
+    PROTOCOL_PARAMETERS_SEED, STRATEGY_REGISTRY_SEED, STRATEGY_STATUS_ACTIVE, STRATEGY_STATUS_PAUSED,
+    STRATEGY_STATUS_WINDING_DOWN,
+};
+
+const INITIALIZE_PROTOCOL: u8 = 0;
+const PAUSE_STRATEGY: u8 = 52;
+
+
+fn registry(status: u8, deployed_amount: u64) -> StrategyRegistry {
+    let mut registry = StrategyRegistry::zeroed();
+    registry.strategy_state = [7; 32];
+    registry.max_allocation = 1_000_000;
+    registry.deployed_amount = deployed_amount;
+    registry.risk_tier = 1;
+    registry.status = status;
+    registry.is_initialized = 1;
+    registry
+}
+
+
+#[test]
+fn wound_down_once_nothing_is_deployed() {
+    assert!(registry(STRATEGY_STATUS_WINDING_DOWN, 0).is_wound_down());
+    assert!(!registry(STRATEGY_STATUS_WINDING_DOWN, 1).is_wound_down());
+    // a paused strategy with nothing deployed has not been wound down
+    assert!(!registry(STRATEGY_STATUS_PAUSED, 0).is_wound_down());
+}
+
+
+// mollusk: initialize_protocol, then pause_strategy over an existing registry
+
+fn pause(existing: StrategyRegistry, pause_mode: u8) -> (InstructionResult, Pubkey) {
+    let program_id = Pubkey::new_from_array(protocol_controller::ID);
+    let mollusk = Mollusk::new(&program_id, "protocol_controller");
+    let (system_program, system_program_account) = mollusk::program::keyed_account_for_system_program();
+
+    // same seed as constants::pda_seeds::PROTOCOL_CONTROLLER_SEED
+    let (controller, _) = Pubkey::find_program_address(&[b"protocol_controller"], &program_id);
+    let (parameters, _) = Pubkey::find_program_address(&[PROTOCOL_PARAMETERS_SEED], &program_id);
+    let (registry, _) =
+        Pubkey::find_program_address(&[STRATEGY_REGISTRY_SEED, existing.strategy_state.as_ref()], &program_id);
+    let authority = Pubkey::new_unique();
+
+    let accounts = vec![
+        (controller, Account::default()),
+        (authority, Account { lamports: 10_000_000_000, ..Account::default() }),
+        (parameters, Account::default()),
+        (
+            registry,
+            Account {
+                lamports: 10_000_000,
+                data: bytemuck::bytes_of(&existing).to_vec(),
+                owner: program_id,
+                executable: false,
+                rent_epoch: 0,
+            },
+        ),
+        (system_program, system_program_account),
+    ];
+
+    let mut initialize_data = vec![INITIALIZE_PROTOCOL];
+    initialize_data.extend_from_slice(&[0u8; 192]);
+    let mut initialize_accounts = vec![
+        AccountMeta::new(controller, false),
+        AccountMeta::new(authority, true),
+        AccountMeta::new(parameters, false),
+        AccountMeta::new_readonly(system_program, false),
+    ];
+    initialize_accounts.extend((0..6).map(|_| AccountMeta::new_readonly(system_program, false)));
+
+    let instructions = [
+        Instruction::new_with_bytes(program_id, &initialize_data, initialize_accounts),
+        Instruction::new_with_bytes(
+            program_id,
+            &[PAUSE_STRATEGY, pause_mode],
+            vec![
+                AccountMeta::new_readonly(parameters, false),
+                AccountMeta::new_readonly(authority, true),
+                AccountMeta::new(registry, false),
+            ],
+        ),
+    ];
+    (mollusk.process_instruction_chain(&instructions, &accounts), registry)
+}
+
+fn status_after(result: &InstructionResult, registry: &Pubkey) -> u8 {
+    let data = &result.resulting_accounts.iter().find(|(key, _)| key == registry).unwrap().1.data;
+    StrategyRegistry::load(data).unwrap().status
+}
+
+
+#[test]
+fn soft_pause_and_resume() {
+    let (result, registry_key) = pause(registry(STRATEGY_STATUS_ACTIVE, 500_000), PAUSE_MODE_SOFT);
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+    assert_eq!(status_after(&result, &registry_key), STRATEGY_STATUS_PAUSED);
+
+    let (result, registry_key) = pause(registry(STRATEGY_STATUS_PAUSED, 500_000), PAUSE_MODE_RESUME);
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+    assert_eq!(status_after(&result, &registry_key), STRATEGY_STATUS_ACTIVE);
+}
+
+#[test]
+fn resume_waits_for_the_wind_down() {
+    let (result, _) = pause(registry(STRATEGY_STATUS_WINDING_DOWN, 1), PAUSE_MODE_RESUME);
+    assert!(result.program_result.is_err());
+
+    let (result, registry_key) = pause(registry(STRATEGY_STATUS_WINDING_DOWN, 0), PAUSE_MODE_RESUME);
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+    assert_eq!(status_after(&result, &registry_key), STRATEGY_STATUS_ACTIVE);
+}
+
+#[test]
+fn soft_pause_does_not_interrupt_a_wind_down() {
+    let (result, _) = pause(registry(STRATEGY_STATUS_WINDING_DOWN, 1), PAUSE_MODE_SOFT);
+    assert!(result.program_result.is_err());
+}
+
+#[test]
+fn refuses_an_unknown_mode() {
+    let (result, _) = pause(registry(STRATEGY_STATUS_ACTIVE, 0), PAUSE_MODE_FORCED_UNWIND + 1);
+    assert!(result.program_result.is_err());
+}
@@ -0,0 +1,49 @@
+// rebalancing toward target weights
+// a strategy is only moved once its drift from the target leaves the band, and a table is
+// rebalanced at most once per rebalance frequency
+
+use bytemuck::Zeroable;
+
+use protocol_controller::{
+    allocation_drift_bps, target_amount, StrategyAllocationTable, DEFAULT_DRIFT_BAND_BPS,
+};
+
+const DAY: i64 = 86_400;
+
+
+#[test]
+fn target_is_the_weighted_share_of_the_total() {
+    assert_eq!(target_amount(1_000_000, 2_500), 250_000);
+    assert_eq!(target_amount(1_000_000, 10_000), 1_000_000);
+    assert_eq!(target_amount(999, 3_333), 332);
+    // no u64 overflow on large totals
+    assert_eq!(target_amount(u64::MAX, 5_000), u64::MAX / 2);
+}
+
+#[test]
+fn drift_is_measured_against_the_total() {
+    // 40% deployed against a 30% target
+    assert_eq!(allocation_drift_bps(400_000, 300_000, 1_000_000), 1_000);
+    assert_eq!(allocation_drift_bps(300_000, 400_000, 1_000_000), 1_000);
+    assert_eq!(allocation_drift_bps(300_000, 300_000, 1_000_000), 0);
+    assert_eq!(allocation_drift_bps(10, 0, 0), 0);
+}
+
+#[test]
+fn small_drift_stays_inside_the_band() {
+    let total = 1_000_000;
+    let target = target_amount(total, 5_000);
+
+    assert!(allocation_drift_bps(target + 50_000, target, total) <= DEFAULT_DRIFT_BAND_BPS as u64);
+    assert!(allocation_drift_bps(target + 50_100, target, total) > DEFAULT_DRIFT_BAND_BPS as u64);
+}
+
+#[test]
+fn rebalance_waits_for_the_frequency() {
+    let mut table = StrategyAllocationTable::zeroed();
+    assert!(table.is_due(DAY, DAY as u64));
+
+    table.last_rebalance_timestamp = DAY;
+    assert!(!table.is_due(2 * DAY - 1, DAY as u64));
+    assert!(table.is_due(2 * DAY, DAY as u64));
+}
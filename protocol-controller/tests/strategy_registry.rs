@@ -0,0 +1,43 @@
+// per-strategy registry
+// deposits only go into active strategies and never past the strategy's cap
+
+use bytemuck::Zeroable;
+
+use protocol_controller::{
+    StrategyRegistry, STRATEGY_STATUS_ACTIVE, STRATEGY_STATUS_PAUSED, STRATEGY_STATUS_WINDING_DOWN,
+};
+
+
+fn registry(status: u8, max_allocation: u64, deployed_amount: u64) -> StrategyRegistry {
+    let mut registry = StrategyRegistry::zeroed();
+    registry.status = status;
+    registry.max_allocation = max_allocation;
+    registry.deployed_amount = deployed_amount;
+    registry.is_initialized = 1;
+    registry
+}
+
+
+#[test]
+fn capacity_is_the_room_left_under_the_cap() {
+    assert_eq!(registry(STRATEGY_STATUS_ACTIVE, 1_000, 400).available_capacity(), 600);
+    // a lowered cap leaves no room, never an underflow
+    assert_eq!(registry(STRATEGY_STATUS_ACTIVE, 300, 400).available_capacity(), 0);
+}
+
+#[test]
+fn deposits_fill_the_cap_and_no_further() {
+    let registry = registry(STRATEGY_STATUS_ACTIVE, 1_000, 400);
+    assert!(registry.require_deployable(600).is_ok());
+    assert!(registry.require_deployable(601).is_err());
+}
+
+#[test]
+fn only_active_strategies_take_deposits() {
+    assert!(registry(STRATEGY_STATUS_PAUSED, 1_000, 0).require_deployable(1).is_err());
+    assert!(registry(STRATEGY_STATUS_WINDING_DOWN, 1_000, 0).require_deployable(1).is_err());
+
+    let mut uninitialized = registry(STRATEGY_STATUS_ACTIVE, 1_000, 0);
+    uninitialized.is_initialized = 0;
+    assert!(uninitialized.require_deployable(1).is_err());
+}
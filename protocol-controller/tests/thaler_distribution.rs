@@ -0,0 +1,24 @@
+// Thaler amounts of a distribution
+// each Thaler is backed by $100 of yield, what does not make a whole Thaler is carried forward
+// into the next distribution instead of being lost
+
+use protocol_controller::{thalers_for_usdc, THALER_VALUE_USDC};
+
+
+#[test]
+fn whole_thalers_and_the_remainder() {
+    assert_eq!(thalers_for_usdc(1_050_000_000), (10, 50_000_000));
+    assert_eq!(thalers_for_usdc(THALER_VALUE_USDC), (1, 0));
+    assert_eq!(thalers_for_usdc(THALER_VALUE_USDC - 1), (0, THALER_VALUE_USDC - 1));
+    assert_eq!(thalers_for_usdc(0), (0, 0));
+}
+
+#[test]
+fn carried_remainders_add_up_to_a_thaler() {
+    // $60 distributable twice, the first run mints nothing
+    let (minted, carry_forward) = thalers_for_usdc(60_000_000);
+    assert_eq!((minted, carry_forward), (0, 60_000_000));
+
+    let (minted, carry_forward) = thalers_for_usdc(60_000_000 + carry_forward);
+    assert_eq!((minted, carry_forward), (1, 20_000_000));
+}
@@ -0,0 +1,96 @@
+// TWAB ledger
+// balance·seconds accrue between changes, one checkpoint is kept per Thaler period boundary and
+// none an unsettled epoch still needs is dropped
+
+use bytemuck::Zeroable;
+
+use protocol_controller::{TwabLedger, TwabRegistry, TWAB_CHECKPOINT_CAPACITY};
+
+
+fn ledger() -> TwabLedger {
+    let mut ledger = TwabLedger::zeroed();
+    ledger.is_initialized = 1;
+    ledger
+}
+
+
+#[test]
+fn registry_accrues_its_total_balance() {
+    let mut registry = TwabRegistry::zeroed();
+    registry.accrue(100);
+    assert_eq!(registry.cumulative_balance_seconds, 0);
+
+    registry.total_balance = 500;
+    registry.accrue(110);
+    assert_eq!(registry.cumulative_balance_seconds, 5_000);
+    // time never runs backwards
+    registry.accrue(105);
+    assert_eq!(registry.cumulative_balance_seconds, 5_000);
+    assert_eq!(registry.last_update_timestamp, 110);
+}
+
+#[test]
+fn ledger_prices_a_period_boundary() {
+    let mut ledger = ledger();
+    ledger.set_balance(100, 10, 0).unwrap();
+    // a period ended at 15, then the balance changed
+    ledger.set_balance(300, 20, 15).unwrap();
+
+    assert_eq!(ledger.cumulative_at(5), Some(0));
+    assert_eq!(ledger.cumulative_at(15), Some(500));
+    assert_eq!(ledger.cumulative_at(20), Some(1_000));
+    assert_eq!(ledger.cumulative_at(30), Some(4_000));
+}
+
+#[test]
+fn changes_within_a_period_share_a_checkpoint() {
+    let mut ledger = ledger();
+    ledger.set_balance(100, 110, 100).unwrap();
+    ledger.set_balance(200, 120, 100).unwrap();
+    ledger.set_balance(50, 130, 100).unwrap();
+    assert_eq!(ledger.checkpoint_count, 1);
+
+    // the next boundary is priced from the merged checkpoint
+    assert_eq!(ledger.cumulative_at(200), Some(100 * 10 + 200 * 10 + 50 * 70));
+}
+
+#[test]
+fn full_history_waits_for_settlement() {
+    let mut ledger = ledger();
+    // one change in each of the following periods, boundaries at 100, 200, ...
+    for i in 1..=TWAB_CHECKPOINT_CAPACITY as i64 {
+        ledger.set_balance(100, 100 * i + 10, 100 * i).unwrap();
+    }
+    assert_eq!(ledger.checkpoint_count as usize, TWAB_CHECKPOINT_CAPACITY);
+
+    let next = 100 * (TWAB_CHECKPOINT_CAPACITY as i64 + 1);
+    assert!(ledger.set_balance(200, next + 10, next).is_err());
+
+    // once the first epochs are settled the oldest checkpoint is no longer needed
+    ledger.last_settled_distribution = 3;
+    ledger.last_settled_timestamp = 300;
+    ledger.set_balance(200, next + 10, next).unwrap();
+    assert_eq!(ledger.cumulative_at(300), Some(100 * 190));
+    assert_eq!(ledger.cumulative_at(200), None);
+}
+
+#[test]
+fn skips_periods_the_ledger_held_nothing_in() {
+    let mut empty = ledger();
+    empty.skip_empty_periods(4, 400);
+    assert_eq!((empty.last_settled_distribution, empty.last_settled_timestamp), (4, 400));
+
+    // frozen after the last settled boundary, that period is still owed
+    let mut unfrozen = ledger();
+    unfrozen.set_balance(100, 410, 400).unwrap();
+    unfrozen.set_balance(0, 450, 400).unwrap();
+    unfrozen.last_settled_distribution = 4;
+    unfrozen.last_settled_timestamp = 400;
+    unfrozen.skip_empty_periods(5, 500);
+    assert_eq!(unfrozen.last_settled_distribution, 4);
+
+    let mut frozen = ledger();
+    frozen.set_balance(100, 10, 0).unwrap();
+    frozen.skip_empty_periods(4, 400);
+    assert_eq!(frozen.last_settled_distribution, 0);
+}
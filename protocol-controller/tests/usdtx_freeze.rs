@@ -0,0 +1,360 @@
+// coordinate_usdtx_freeze_for_yield (10) and coordinate_usdtx_unfreeze_from_yield (11)
+// from an empty state: initialize_protocol and initialize_minting, then the first freeze creates the
+// TWAB registry, the user's ledger, freeze position and freeze supply; the USDtx lock is the
+// usdtx-stand-in program (tests/programs/usdtx-stand-in), built with cargo build-sbf before running these
+
+use bytemuck::Zeroable;
+use mollusk::{result::InstructionResult, Mollusk};
+use solana_account::Account;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_option::COption,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+
+use protocol_controller::{
+    FreezePosition, FreezeSupply, MintingState, ProgramAccount, TwabLedger, TwabRegistry,
+    FREEZE_POSITION_SEED, FREEZE_SUPPLY_SEED, MINTING_STATE_SEED, PROTOCOL_PARAMETERS_SEED,
+    THALER_DISTRIBUTION_SEED, TWAB_LEDGER_SEED, TWAB_REGISTRY_SEED, USDC_MINT,
+};
+
+const INITIALIZE_PROTOCOL: u8 = 0;
+const INITIALIZE_MINTING: u8 = 3;
+const FREEZE_FOR_YIELD: u8 = 10;
+const UNFREEZE_FROM_YIELD: u8 = 11;
+
+const USER_BALANCE: u64 = 1_000_000_000;
+
+
+struct Setup {
+    mollusk: Mollusk,
+    accounts: Vec<(Pubkey, Account)>,
+    initialize: Vec<Instruction>,
+    user: Pubkey,
+    freeze_accounts: Vec<AccountMeta>,
+    unfreeze_accounts: Vec<AccountMeta>,
+    minting: Pubkey,
+    registry: Pubkey,
+    ledger: Pubkey,
+    position: Pubkey,
+    supply: Pubkey,
+}
+
+fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64, is_native: bool) -> Account {
+    let mut data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        is_native: if is_native { COption::Some(2_039_280) } else { COption::None },
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    Account {
+        lamports: 2_039_280 + if is_native { amount } else { 0 },
+        data,
+        owner: spl_token::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn mint_account(mint_authority: &Pubkey) -> Account {
+    let mut data = vec![0u8; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: COption::Some(*mint_authority),
+        supply: USER_BALANCE,
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    Account {
+        lamports: 1_461_600,
+        data,
+        owner: spl_token::ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn wallet() -> Account {
+    Account {
+        lamports: 10_000_000_000,
+        ..Account::default()
+    }
+}
+
+fn setup() -> Setup {
+    let program_id = Pubkey::new_from_array(protocol_controller::ID);
+    let usdtx_program = Pubkey::new_from_array(usdtx_token::ID);
+
+    let mut mollusk = Mollusk::new(&program_id, "protocol_controller");
+    mollusk.add_program(&usdtx_program, "usdtx_stand_in", &mollusk::program::loader_keys::LOADER_V3);
+    mollusk_svm_programs_token::token::add_program(&mut mollusk);
+    let (system_program, system_program_account) = mollusk::program::keyed_account_for_system_program();
+
+    // same seed as constants::pda_seeds::PROTOCOL_CONTROLLER_SEED
+    let (controller, _) = Pubkey::find_program_address(&[b"protocol_controller"], &program_id);
+    let (parameters, _) = Pubkey::find_program_address(&[PROTOCOL_PARAMETERS_SEED], &program_id);
+    let (minting, _) = Pubkey::find_program_address(&[MINTING_STATE_SEED], &program_id);
+
+    let authority = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let usdtx_mint = Pubkey::new_unique();
+    let sol_escrow = Pubkey::new_unique();
+    let psm_vault = Pubkey::new_unique();
+    let user_usdtx = Pubkey::new_unique();
+
+    let (registry, _) = Pubkey::find_program_address(&[TWAB_REGISTRY_SEED], &program_id);
+    let (ledger, _) = Pubkey::find_program_address(&[TWAB_LEDGER_SEED, user.as_ref()], &program_id);
+    let (position, _) = Pubkey::find_program_address(&[FREEZE_POSITION_SEED, user.as_ref()], &program_id);
+    let (supply, _) = Pubkey::find_program_address(&[FREEZE_SUPPLY_SEED], &program_id);
+    let (distribution, _) = Pubkey::find_program_address(&[THALER_DISTRIBUTION_SEED], &program_id);
+
+    let accounts = vec![
+        (controller, Account::default()),
+        (authority, wallet()),
+        (parameters, Account::default()),
+        (minting, Account::default()),
+        (usdtx_mint, mint_account(&controller)),
+        (sol_escrow, token_account(&spl_token::native_mint::ID, &controller, 0, true)),
+        (psm_vault, token_account(&Pubkey::new_from_array(USDC_MINT), &controller, 0, false)),
+        (user, wallet()),
+        (user_usdtx, token_account(&usdtx_mint, &user, USER_BALANCE, false)),
+        (registry, Account::default()),
+        (ledger, Account::default()),
+        (position, Account::default()),
+        (supply, Account::default()),
+        (distribution, Account::default()),
+        (usdtx_program, mollusk::program::create_program_account_loader_v3(&usdtx_program)),
+        (system_program, system_program_account),
+        mollusk_svm_programs_token::token::keyed_account(),
+    ];
+
+    // program addresses (5 × 32), controller, authority, parameters, system program, then unused slots
+    let mut initialize_protocol_accounts = vec![
+        AccountMeta::new(controller, false),
+        AccountMeta::new(authority, true),
+        AccountMeta::new(parameters, false),
+        AccountMeta::new_readonly(system_program, false),
+    ];
+    initialize_protocol_accounts.extend((0..6).map(|_| AccountMeta::new_readonly(system_program, false)));
+
+    let mut initialize_protocol_data = vec![INITIALIZE_PROTOCOL];
+    initialize_protocol_data.extend_from_slice(&[0u8; 192]);
+
+    let initialize = vec![
+        Instruction::new_with_bytes(program_id, &initialize_protocol_data, initialize_protocol_accounts),
+        Instruction::new_with_bytes(
+            program_id,
+            &[INITIALIZE_MINTING],
+            vec![
+                AccountMeta::new_readonly(parameters, false),
+                AccountMeta::new(authority, true),
+                AccountMeta::new(minting, false),
+                AccountMeta::new_readonly(usdtx_mint, false),
+                AccountMeta::new_readonly(sol_escrow, false),
+                AccountMeta::new_readonly(psm_vault, false),
+                AccountMeta::new_readonly(system_program, false),
+            ],
+        ),
+    ];
+
+    let freeze_accounts = vec![
+        AccountMeta::new(user, true),
+        AccountMeta::new(user_usdtx, false),
+        AccountMeta::new(registry, false),
+        AccountMeta::new(ledger, false),
+        AccountMeta::new(position, false),
+        AccountMeta::new(supply, false),
+        AccountMeta::new_readonly(usdtx_mint, false),
+        AccountMeta::new_readonly(usdtx_program, false),
+        AccountMeta::new_readonly(controller, false),
+        AccountMeta::new(minting, false),
+        AccountMeta::new_readonly(distribution, false),
+        AccountMeta::new_readonly(system_program, false),
+    ];
+
+    // no Thaler distribution yet, so its vault and the user's Thaler account are never read
+    let unfreeze_accounts = vec![
+        AccountMeta::new(user, true),
+        AccountMeta::new(registry, false),
+        AccountMeta::new(ledger, false),
+        AccountMeta::new(position, false),
+        AccountMeta::new(supply, false),
+        AccountMeta::new(user_usdtx, false),
+        AccountMeta::new_readonly(usdtx_mint, false),
+        AccountMeta::new_readonly(usdtx_program, false),
+        AccountMeta::new_readonly(controller, false),
+        AccountMeta::new_readonly(parameters, false),
+        AccountMeta::new_readonly(distribution, false),
+        AccountMeta::new_readonly(system_program, false),
+        AccountMeta::new_readonly(system_program, false),
+        AccountMeta::new_readonly(spl_token::ID, false),
+        AccountMeta::new(minting, false),
+    ];
+
+    Setup {
+        mollusk,
+        accounts,
+        initialize,
+        user,
+        freeze_accounts,
+        unfreeze_accounts,
+        minting,
+        registry,
+        ledger,
+        position,
+        supply,
+    }
+}
+
+impl Setup {
+    fn freeze(&self, amount: u64) -> Instruction {
+        let mut data = vec![FREEZE_FOR_YIELD];
+        data.extend_from_slice(&amount.to_le_bytes());
+        Instruction::new_with_bytes(
+            Pubkey::new_from_array(protocol_controller::ID),
+            &data,
+            self.freeze_accounts.clone(),
+        )
+    }
+
+    fn unfreeze(&self, amount: u64) -> Instruction {
+        let mut data = vec![UNFREEZE_FROM_YIELD];
+        data.extend_from_slice(&amount.to_le_bytes());
+        Instruction::new_with_bytes(
+            Pubkey::new_from_array(protocol_controller::ID),
+            &data,
+            self.unfreeze_accounts.clone(),
+        )
+    }
+
+    fn run(&self, freezes: &[u64]) -> InstructionResult {
+        self.run_then_unfreeze(freezes, &[])
+    }
+
+    fn run_then_unfreeze(&self, freezes: &[u64], unfreezes: &[u64]) -> InstructionResult {
+        let mut instructions = self.initialize.clone();
+        instructions.extend(freezes.iter().map(|amount| self.freeze(*amount)));
+        instructions.extend(unfreezes.iter().map(|amount| self.unfreeze(*amount)));
+        self.mollusk.process_instruction_chain(&instructions, &self.accounts)
+    }
+}
+
+fn account<'a>(accounts: &'a [(Pubkey, Account)], key: &Pubkey) -> &'a Account {
+    &accounts.iter().find(|(k, _)| k == key).unwrap().1
+}
+
+
+#[test]
+fn first_freeze_creates_twab_and_freeze_accounts() {
+    let setup = setup();
+    let result = setup.run(&[USER_BALANCE / 4]);
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+
+    let program_id = Pubkey::new_from_array(protocol_controller::ID);
+    for key in [&setup.registry, &setup.ledger, &setup.position, &setup.supply] {
+        assert_eq!(account(&result.resulting_accounts, key).owner, program_id);
+    }
+
+    let ledger = *TwabLedger::load(&account(&result.resulting_accounts, &setup.ledger).data).unwrap();
+    assert_eq!(ledger.is_initialized, 1);
+    assert_eq!(ledger.owner, setup.user.to_bytes());
+    assert_eq!(ledger.balance, USER_BALANCE / 4);
+    assert_eq!(ledger.checkpoint_count, 1);
+
+    let registry = *TwabRegistry::load(&account(&result.resulting_accounts, &setup.registry).data).unwrap();
+    assert_eq!(registry.total_balance, USER_BALANCE / 4);
+    assert_eq!(registry.staker_count, 1);
+
+    let position = *FreezePosition::load(&account(&result.resulting_accounts, &setup.position).data).unwrap();
+    assert_eq!(position.frozen_amount, USER_BALANCE / 4);
+
+    let supply = *FreezeSupply::load(&account(&result.resulting_accounts, &setup.supply).data).unwrap();
+    assert_eq!(supply.total_frozen_supply, USER_BALANCE / 4);
+    assert_eq!(supply.frozen_position_count, 1);
+
+    let minting = *MintingState::load(&account(&result.resulting_accounts, &setup.minting).data).unwrap();
+    assert_eq!(minting.frozen_supply, USER_BALANCE / 4);
+}
+
+#[test]
+fn second_freeze_adds_to_existing_accounts() {
+    let setup = setup();
+    let result = setup.run(&[USER_BALANCE / 4, USER_BALANCE / 2]);
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+
+    let ledger = *TwabLedger::load(&account(&result.resulting_accounts, &setup.ledger).data).unwrap();
+    assert_eq!(ledger.balance, USER_BALANCE * 3 / 4);
+
+    let registry = *TwabRegistry::load(&account(&result.resulting_accounts, &setup.registry).data).unwrap();
+    assert_eq!(registry.total_balance, USER_BALANCE * 3 / 4);
+    assert_eq!(registry.staker_count, 1);
+
+    let supply = *FreezeSupply::load(&account(&result.resulting_accounts, &setup.supply).data).unwrap();
+    assert_eq!(supply.frozen_position_count, 1);
+}
+
+#[test]
+fn refuses_freeze_over_token_balance() {
+    let setup = setup();
+    let result = setup.run(&[USER_BALANCE + 1]);
+    assert!(result.program_result.is_err());
+}
+
+#[test]
+fn cooldown_runs_from_the_latest_freeze() {
+    let mut position = FreezePosition::zeroed();
+    position.frozen_since = 500;
+    position.last_frozen_at = 1_000;
+
+    assert_eq!(position.cooldown_ends_at(86_400), 87_400);
+    assert_eq!(position.cooldown_ends_at(0), 1_000);
+    position.last_frozen_at = i64::MAX - 1;
+    assert_eq!(position.cooldown_ends_at(86_400), i64::MAX);
+}
+
+#[test]
+fn unfreeze_before_any_distribution_releases_the_balance() {
+    let setup = setup();
+    let result = setup.run_then_unfreeze(&[USER_BALANCE / 2], &[USER_BALANCE / 4]);
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+
+    let ledger = *TwabLedger::load(&account(&result.resulting_accounts, &setup.ledger).data).unwrap();
+    assert_eq!(ledger.balance, USER_BALANCE / 4);
+
+    let position = *FreezePosition::load(&account(&result.resulting_accounts, &setup.position).data).unwrap();
+    assert_eq!(position.frozen_amount, USER_BALANCE / 4);
+
+    let supply = *FreezeSupply::load(&account(&result.resulting_accounts, &setup.supply).data).unwrap();
+    assert_eq!(supply.total_frozen_supply, USER_BALANCE / 4);
+
+    let minting = *MintingState::load(&account(&result.resulting_accounts, &setup.minting).data).unwrap();
+    assert_eq!(minting.frozen_supply, USER_BALANCE / 4);
+}
+
+#[test]
+fn full_unfreeze_closes_the_position() {
+    let setup = setup();
+    let result = setup.run_then_unfreeze(&[USER_BALANCE / 2], &[USER_BALANCE / 2]);
+    assert!(result.program_result.is_ok(), "{:?}", result.program_result);
+
+    let registry = *TwabRegistry::load(&account(&result.resulting_accounts, &setup.registry).data).unwrap();
+    assert_eq!(registry.total_balance, 0);
+    assert_eq!(registry.staker_count, 0);
+
+    let supply = *FreezeSupply::load(&account(&result.resulting_accounts, &setup.supply).data).unwrap();
+    assert_eq!(supply.frozen_position_count, 0);
+    assert_eq!(supply.total_frozen_supply, 0);
+}
+
+#[test]
+fn refuses_unfreeze_over_the_frozen_amount() {
+    let setup = setup();
+    let result = setup.run_then_unfreeze(&[USER_BALANCE / 4], &[USER_BALANCE / 2]);
+    assert!(result.program_result.is_err());
+}
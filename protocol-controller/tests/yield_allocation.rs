@@ -0,0 +1,75 @@
+// freeze-based yield allocation
+// scores from APR and risk tier, weights by score under the caps, stepped towards the target
+
+use protocol_controller::{risk_adjusted_score, score_weights, step_weights};
+
+
+#[test]
+fn riskier_tiers_score_less_the_more_is_liquid() {
+    assert_eq!(risk_adjusted_score(500, 1, 10_000), 5_000_000);
+    assert_eq!(risk_adjusted_score(500, 3, 10_000), 3_000_000);
+    assert_eq!(risk_adjusted_score(500, 3, 5_000), 4_000_000);
+    // all frozen, no penalty
+    assert_eq!(risk_adjusted_score(500, 3, 0), 5_000_000);
+}
+
+#[test]
+fn unharvested_strategy_keeps_a_minimal_score() {
+    assert_eq!(risk_adjusted_score(0, 1, 10_000), 10_000);
+}
+
+#[test]
+fn weights_follow_the_scores() {
+    let mut weights = [0u16; 2];
+    score_weights(&[3, 1], &[10_000, 10_000], &mut weights);
+    assert_eq!(weights, [7_500, 2_500]);
+}
+
+#[test]
+fn capped_weight_goes_to_the_others() {
+    let mut weights = [0u16; 2];
+    score_weights(&[3, 1], &[5_000, 10_000], &mut weights);
+    assert_eq!(weights, [5_000, 5_000]);
+}
+
+#[test]
+fn rounding_dust_keeps_the_total() {
+    let mut weights = [0u16; 3];
+    score_weights(&[1, 1, 1], &[10_000; 3], &mut weights);
+    assert_eq!(weights.iter().map(|&w| w as u64).sum::<u64>(), 10_000);
+    assert!(weights.iter().all(|&w| w == 3_333 || w == 3_334));
+}
+
+#[test]
+fn rounding_dust_skips_a_capped_strategy() {
+    let mut weights = [0u16; 4];
+    // the best strategy is held at its cap, the 5_000 left splits three ways with 2 bps of dust
+    score_weights(&[5, 1, 1, 1], &[5_000, 10_000, 10_000, 10_000], &mut weights);
+    assert_eq!(weights[0], 5_000);
+    assert_eq!(weights.iter().map(|&w| w as u64).sum::<u64>(), 10_000);
+    assert!(weights[1..].iter().all(|&w| w == 1_666 || w == 1_668));
+}
+
+#[test]
+fn steps_at_most_max_step() {
+    let mut next = [0u16; 2];
+    step_weights(&[5_000, 5_000], &[8_000, 2_000], 1_000, &mut next);
+    assert_eq!(next, [6_000, 4_000]);
+
+    // within a step lands on the target
+    step_weights(&[5_000, 5_000], &[5_500, 4_500], 1_000, &mut next);
+    assert_eq!(next, [5_500, 4_500]);
+}
+
+#[test]
+fn stepped_weights_keep_the_total() {
+    let current = [7_000, 2_000, 1_000];
+    let target = [1_000, 4_500, 4_500];
+    let mut next = [0u16; 3];
+    step_weights(&current, &target, 700, &mut next);
+
+    assert_eq!(next.iter().map(|&w| w as u64).sum::<u64>(), 10_000);
+    for i in 0..3 {
+        assert!(next[i].abs_diff(current[i]) <= 701, "strategy {} moved {} -> {}", i, current[i], next[i]);
+    }
+}
@@ -0,0 +1,131 @@
+// yield epoch merkle proofs
+// proofs from EpochMerkleTree have to verify against its root for every leaf, and only for the
+// leaf they were built for; entitlements come from the closed period and the TWAB ledgers
+
+use bytemuck::Zeroable;
+
+use protocol_controller::{
+    entitlement_leaf, entitlements_for_period, verify_proof, EpochMerkleTree, ThalerDistribution, TwabLedger,
+    TWAB_CHECKPOINT_CAPACITY,
+};
+
+const EPOCH: u64 = 3;
+
+
+fn entitlements(count: usize) -> Vec<([u8; 32], u64)> {
+    (0..count).map(|i| ([i as u8 + 1; 32], 1_000 * (i as u64 + 1))).collect()
+}
+
+fn proves_every_leaf(count: usize) {
+    let entitlements = entitlements(count);
+    let tree = EpochMerkleTree::from_entitlements(EPOCH, &entitlements);
+    let root = tree.root();
+
+    for (index, (user, amount)) in entitlements.iter().enumerate() {
+        let leaf = entitlement_leaf(EPOCH, index as u32, user, *amount);
+        assert!(verify_proof(leaf, &tree.proof(index), &root), "leaf {} of {}", index, count);
+    }
+}
+
+
+#[test]
+fn proves_every_leaf_of_even_and_odd_trees() {
+    for count in [1, 2, 3, 4, 5, 7, 8, 13] {
+        proves_every_leaf(count);
+    }
+}
+
+#[test]
+fn single_leaf_is_its_own_root() {
+    let entitlements = entitlements(1);
+    let tree = EpochMerkleTree::from_entitlements(EPOCH, &entitlements);
+    assert_eq!(tree.root(), entitlement_leaf(EPOCH, 0, &entitlements[0].0, entitlements[0].1));
+    assert!(tree.proof(0).is_empty());
+}
+
+#[test]
+fn rejects_changed_amount_index_or_epoch() {
+    let entitlements = entitlements(5);
+    let tree = EpochMerkleTree::from_entitlements(EPOCH, &entitlements);
+    let root = tree.root();
+    let (user, amount) = entitlements[2];
+    let proof = tree.proof(2);
+
+    assert!(!verify_proof(entitlement_leaf(EPOCH, 2, &user, amount + 1), &proof, &root));
+    assert!(!verify_proof(entitlement_leaf(EPOCH, 3, &user, amount), &proof, &root));
+    assert!(!verify_proof(entitlement_leaf(EPOCH + 1, 2, &user, amount), &proof, &root));
+}
+
+#[test]
+fn rejects_proof_of_another_leaf() {
+    let entitlements = entitlements(6);
+    let tree = EpochMerkleTree::from_entitlements(EPOCH, &entitlements);
+    let (user, amount) = entitlements[1];
+    assert!(!verify_proof(entitlement_leaf(EPOCH, 1, &user, amount), &tree.proof(4), &tree.root()));
+}
+
+
+// the period about to be closed, Thalers with no decimals
+fn period(period_thalers: u64, start_timestamp: i64, end_timestamp: i64, global_twab: u128) -> ThalerDistribution {
+    let mut distribution = ThalerDistribution::zeroed();
+    distribution.distribution_count = EPOCH;
+    distribution.period_thalers = period_thalers;
+    distribution.period_start_timestamp = start_timestamp;
+    distribution.period_end_timestamp = end_timestamp;
+    distribution.period_end_twab = global_twab;
+    distribution
+}
+
+fn ledger(owner: [u8; 32], balance: u64, since: i64) -> TwabLedger {
+    let mut ledger = TwabLedger::zeroed();
+    ledger.owner = owner;
+    ledger.is_initialized = 1;
+    ledger.set_balance(balance, since, 0).unwrap();
+    ledger
+}
+
+#[test]
+fn shares_period_by_balance_seconds() {
+    // 300 and 100 frozen over the whole period
+    let period = period(1_000, 100, 200, 400 * 100);
+    let ledgers = [ledger([1; 32], 300, 50), ledger([2; 32], 100, 50), ledger([3; 32], 0, 50)];
+
+    let result = entitlements_for_period(&period, 0, &ledgers).unwrap();
+    assert_eq!((result.epoch, result.total_amount), (EPOCH, 1_000));
+    // the empty ledger still gets a leaf to claim past the epoch with
+    assert_eq!(result.entitlements, vec![([1; 32], 750), ([2; 32], 250), ([3; 32], 0)]);
+    assert!(result.unpriceable.is_empty());
+}
+
+#[test]
+fn scales_the_period_thalers_to_base_units() {
+    let period = period(3, 100, 200, 100 * 100);
+    let result = entitlements_for_period(&period, 6, &[ledger([1; 32], 100, 50)]).unwrap();
+    assert_eq!(result.entitlements, vec![([1; 32], 3_000_000)]);
+}
+
+#[test]
+fn skips_ledgers_settled_past_the_period() {
+    let period = period(1_000, 100, 200, 100 * 100);
+    let mut settled = ledger([2; 32], 0, 50);
+    settled.last_settled_distribution = EPOCH;
+
+    let result = entitlements_for_period(&period, 0, &[ledger([1; 32], 100, 50), settled]).unwrap();
+    assert_eq!(result.entitlements, vec![([1; 32], 1_000)]);
+}
+
+#[test]
+fn reports_a_ledger_past_its_history_without_failing_the_tree() {
+    let period = period(1_000, 100, 200, 100 * 100);
+
+    // every checkpoint written after the period start, one per boundary, older ones settled away
+    let mut overwritten = ledger([1; 32], 100, 150);
+    overwritten.last_settled_timestamp = i64::MAX;
+    for i in 1..=TWAB_CHECKPOINT_CAPACITY as i64 {
+        overwritten.set_balance(100 + i as u64, 150 + 10 * i, 145 + 10 * i).unwrap();
+    }
+
+    let result = entitlements_for_period(&period, 0, &[ledger([2; 32], 100, 50), overwritten]).unwrap();
+    assert_eq!(result.entitlements, vec![([2; 32], 1_000)]);
+    assert_eq!(result.unpriceable, vec![[1; 32]]);
+}
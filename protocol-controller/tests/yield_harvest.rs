@@ -0,0 +1,65 @@
+// harvest_all_yield (26) and the per-strategy yield records
+// only modes 1 (SOL), 2 (USDC) and 3 (all) harvest, a strategy is harvested at most once per
+// yield distribution frequency and every harvest snapshots its APR against the principal
+
+use bytemuck::Zeroable;
+use mollusk::Mollusk;
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+
+use protocol_controller::{annualized_rate_bps, StrategyYieldRecord, SECONDS_PER_YEAR};
+
+const HARVEST_ALL_YIELD: u8 = 26;
+
+const DAY: i64 = 86_400;
+
+
+#[test]
+fn apr_is_annualized_in_bps_of_the_principal() {
+    // 1% over a quarter of a year
+    assert_eq!(annualized_rate_bps(100, 10_000, SECONDS_PER_YEAR / 4), 400);
+    assert_eq!(annualized_rate_bps(100, 0, DAY as u64), 0);
+    assert_eq!(annualized_rate_bps(100, 10_000, 0), 0);
+}
+
+#[test]
+fn never_harvested_strategy_is_due() {
+    let record = StrategyYieldRecord::zeroed();
+    assert!(record.is_due(1_000, DAY as u64));
+}
+
+#[test]
+fn harvest_waits_for_the_distribution_frequency() {
+    let mut record = StrategyYieldRecord::zeroed();
+    record.record_harvest(1_000_000, 100_000_000, DAY);
+
+    assert!(!record.is_due(DAY + DAY - 1, DAY as u64));
+    assert!(record.is_due(DAY + DAY, DAY as u64));
+}
+
+#[test]
+fn harvest_records_yield_and_an_apr_snapshot() {
+    let mut record = StrategyYieldRecord::zeroed();
+    // the first harvest has no period to annualize over
+    record.record_harvest(1_000_000, 365_000_000, DAY);
+    assert_eq!((record.last_apr_bps, record.harvest_count), (0, 1));
+
+    // $1 a day on $365 is 100% a year
+    record.record_harvest(1_000_000, 365_000_000, 2 * DAY);
+    assert_eq!(record.last_apr_bps, 10_000);
+    assert_eq!(record.last_yield, 1_000_000);
+    assert_eq!(record.total_yield, 2_000_000);
+    assert_eq!(record.last_principal_usd, 365_000_000);
+    assert_eq!(record.last_harvest_timestamp, 2 * DAY);
+    assert_eq!(record.harvest_count, 2);
+}
+
+#[test]
+fn refuses_an_unknown_harvest_mode() {
+    let program_id = Pubkey::new_from_array(protocol_controller::ID);
+    let mollusk = Mollusk::new(&program_id, "protocol_controller");
+
+    for mode in [0u8, 4] {
+        let harvest = Instruction::new_with_bytes(program_id, &[HARVEST_ALL_YIELD, mode], vec![]);
+        assert!(mollusk.process_instruction(&harvest, &[]).program_result.is_err(), "mode {}", mode);
+    }
+}